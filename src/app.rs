@@ -1,26 +1,126 @@
 use std::sync::Arc;
-use cgmath::{InnerSpace, Vector2, Vector3, Zero};
-use winit::{application::ApplicationHandler, event::{DeviceEvent, Event, KeyEvent, MouseButton, WindowEvent}, event_loop::ActiveEventLoop, keyboard::{KeyCode, PhysicalKey}, window::{Window, WindowId}};
-use crate::{physics::Entity, rendering::RenderState, settings::MOVE_SPEED, ui::UI, vectors::{replace_xz, xyz_to_xz}, world::{Coordinate, GameWorld}};
+use cgmath::{InnerSpace, Point2, Point3};
+use winit::{application::ApplicationHandler, event::{DeviceEvent, Event, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent}, event_loop::ActiveEventLoop, keyboard::{KeyCode, PhysicalKey}, window::{Window, WindowId}};
+use crate::{physics::Entity, rendering::RenderState, ui::{UI, commands, menu::MenuAction, state::SlotClick}, user_settings::UserSettings, vectors::{replace_xz, xyz_to_xz}, world::{GameWorld, time::WorldTime}};
+
+/// What the app is doing
+enum GameState {
+    /// In the main menu, with no world loaded
+    MainMenu,
+    /// Playing in a world, which doesn't tick while paused
+    InGame {
+        world: Box<GameWorld>,
+        paused: bool,
+    },
+}
 
 /// Stores top-level info on the entire app
 pub struct App {
     render_state: Option<RenderState>,
     mouse_trapped: bool,
+    settings: UserSettings,
 
-    world: GameWorld,
+    state: GameState,
     ui: Option<UI>,
 }
 
 impl App {
     pub fn new() -> Self {
-        Self { 
+        Self {
             render_state: None,
             mouse_trapped: false,
-            world: GameWorld::new(),
+            settings: UserSettings::load(),
+            state: GameState::MainMenu,
             ui: None,
         }
     }
+
+    /// Locks the cursor to the window and hides it for looking around, or
+    /// frees it to click on the UI
+    fn trap_mouse(&mut self, trapped: bool) {
+        let Some(render_state) = &self.render_state else {
+            return;
+        };
+
+        let grab = if trapped {
+            winit::window::CursorGrabMode::Locked
+        } else {
+            winit::window::CursorGrabMode::None
+        };
+        render_state.window.set_cursor_grab(grab).unwrap();
+        render_state.window.set_cursor_visible(!trapped);
+        self.mouse_trapped = trapped;
+    }
+
+    /// Carries out whatever was picked in a menu
+    fn handle_menu_action(&mut self, event_loop: &ActiveEventLoop, action: MenuAction) {
+        let ui = self.ui.as_mut().unwrap();
+        match action {
+            MenuAction::Play(info) => {
+                ui.menu.hide();
+                self.state = GameState::InGame {
+                    world: Box::new(GameWorld::open(info, self.settings.render_dist)),
+                    paused: false,
+                };
+                self.trap_mouse(true);
+            },
+
+            MenuAction::Resume => {
+                ui.menu.hide();
+                if let GameState::InGame { world, paused } = &mut self.state {
+                    *paused = false;
+                    world.resume();
+                }
+                self.trap_mouse(true);
+            },
+
+            MenuAction::SaveSettings => {
+                if let Err(e) = self.settings.save() {
+                    log::error!("Couldn't save settings: {:#}", e);
+                }
+            },
+
+            MenuAction::SaveAndQuit => {
+                if let GameState::InGame { world, .. } = &mut self.state {
+                    world.save();
+                }
+                self.state = GameState::MainMenu;
+                ui.console.close();
+                ui.menu.show_main();
+                self.trap_mouse(false);
+            },
+
+            MenuAction::Quit => event_loop.exit(),
+        }
+    }
+
+    /// Puts changed settings into effect everywhere they're used
+    fn apply_settings(&mut self, settings: UserSettings) {
+        let mut textures_changed = false;
+        if let Some(render_state) = &mut self.render_state {
+            textures_changed = render_state.apply_settings(&settings);
+        }
+        if let Some(ui) = &mut self.ui {
+            ui.set_font_size(settings.font_size);
+        }
+        if let GameState::InGame { world, .. } = &mut self.state {
+            world.set_render_dist(settings.render_dist);
+            if textures_changed {
+                world.rebuild_meshes();
+            }
+        }
+        self.settings = settings;
+    }
+
+    /// Passes a window event on to the UI
+    fn forward_to_ui(&mut self, window_id: WindowId, event: WindowEvent) {
+        if let (Some(ui), Some(render_state)) = (&mut self.ui, &self.render_state) {
+            ui.handle_event(
+                &render_state.window,
+                &Event::WindowEvent { window_id, event }
+            );
+        }
+    }
 }
 
 impl ApplicationHandler<()> for App {
@@ -30,11 +130,25 @@ impl ApplicationHandler<()> for App {
             .with_inner_size(winit::dpi::LogicalSize::new(1800, 1200));
 
         let win = Arc::new(event_loop.create_window(win_atts).unwrap());
-        self.ui = Some(UI::new(&win));
+        let mut ui = UI::new(&win, &self.settings);
+        ui.menu.show_main();
+
+        let render_state = pollster::block_on(RenderState::new(win, &self.settings)).unwrap();
+        ui.state.set_gpu(render_state.adapter_info());
+        self.render_state = Some(render_state);
+        self.ui = Some(ui);
+    }
+
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        // Keep settings changed right before closing the window
+        if self.ui.as_ref().is_some_and(|ui| ui.menu.is_settings()) &&
+            let Err(e) = self.settings.save() {
 
-        self.render_state = Some(
-            pollster::block_on(RenderState::new(win)).unwrap()
-        );
+            log::error!("Couldn't save settings: {:#}", e);
+        }
+        if let GameState::InGame { world, .. } = &mut self.state {
+            world.save();
+        }
     }
 
     fn device_event(
@@ -47,6 +161,9 @@ impl ApplicationHandler<()> for App {
             Some(x) => x,
             None => return,
         };
+        let GameState::InGame { world, .. } = &mut self.state else {
+            return;
+        };
 
         match event {
             DeviceEvent::MouseMotion { delta } => {
@@ -55,10 +172,10 @@ impl ApplicationHandler<()> for App {
                     render_state.camera.update_direction(dx, dy);
                     let direction = render_state.camera.get_direction();
 
-                    let player = self.world.player_mut();
+                    let player = world.player_mut();
                     let player_v = player.get_velocity();
                     let old_player_dir = xyz_to_xz(player_v);
-                    let new_player_dir = 
+                    let new_player_dir =
                         xyz_to_xz(direction).normalize()
                         * old_player_dir.magnitude();
                     player.set_velocity(replace_xz(player_v, new_player_dir));
@@ -74,36 +191,87 @@ impl ApplicationHandler<()> for App {
         window_id: WindowId,
         event: WindowEvent,
     ) {
-        let render_state = match &mut self.render_state {
-            Some(x) => x,
-            None => return,
-        };
+        if self.render_state.is_none() {
+            return;
+        }
 
         match event {
             WindowEvent::CloseRequested => event_loop.exit(),
             WindowEvent::Resized(size) =>
-                render_state.resize(size.width, size.height),
+                self.render_state.as_mut().unwrap().resize(size.width, size.height),
             WindowEvent::RedrawRequested => {
-                // Calculate physics and load new chunks
-                self.world.player_mut().facing =
-                    render_state.camera.get_direction();
-                self.world.do_tick();
-                self.world.update_chunks_to_player();
-
-                // Update camera position to player's
-                render_state.camera
-                    .update_position(self.world.player_mut().get_precise_pos());
-
-                // Update UI overlay
-                self.ui.as_mut().unwrap().state.update(&self.world);
-
-                // Render!
-                render_state.update(self.world.get_highlight());
-                let center = self.world.player().get_precise_pos();
-                let mut meshes = self.world.get_meshes_mut();
+                let render_state = self.render_state.as_mut().unwrap();
                 let ui = self.ui.as_mut().unwrap();
 
-                match render_state.render(&mut meshes[..], ui, center) {
+                let render_result = match &mut self.state {
+                    GameState::InGame { world, paused } => {
+                        // Calculate physics and load new chunks
+                        if !*paused {
+                            world.player_mut().facing =
+                                render_state.camera.get_direction();
+                            world.do_tick();
+                        }
+                        world.update_chunks_to_player();
+
+                        // Pick up edits to the resource pack
+                        if render_state.reload_resource_pack() {
+                            world.rebuild_meshes();
+                        }
+
+                        // Update camera position to player's
+                        render_state.camera
+                            .update_position(world.player_render_pos());
+
+                        // Update UI overlay
+                        ui.state.update(world);
+
+                        // Render!
+                        render_state.update(
+                            world.get_highlight(),
+                            world.loaded_circle(),
+                            world.time().sun_direction(),
+                            world.time().daylight(),
+                        );
+                        world.update_meshes(|block, side| render_state.block_texture(block, side));
+                        world.update_mob_mesh(|name| render_state.texture(name));
+                        let center = world.player_render_pos();
+                        world.update_occlusion(center);
+                        let (mut meshes, mut far_meshes) = world.get_meshes_mut();
+
+                        let render_result = render_state.render(&mut meshes[..], &mut far_meshes[..], ui, center);
+
+                        // Handle clicks in the container window
+                        match ui.state.take_slot_click() {
+                            Some(SlotClick::Store(slot)) => world.store_in_container(slot),
+                            Some(SlotClick::Take(slot)) => world.take_from_container(slot),
+                            None => {},
+                        }
+
+                        // Run commands entered into the console
+                        if let Some(line) = ui.console.take_submitted() {
+                            let result = commands::run(world, &line);
+                            ui.console.print(result);
+                        }
+
+                        render_result
+                    },
+
+                    GameState::MainMenu => {
+                        render_state.reload_resource_pack();
+
+                        // Just the sky behind the menu
+                        let time = WorldTime::new();
+                        render_state.update(
+                            None,
+                            (Point2::new(0.0, 0.0), 0.0),
+                            time.sun_direction(),
+                            time.daylight(),
+                        );
+                        render_state.render(&mut [], &mut [], ui, Point3::new(0.0, 0.0, 0.0))
+                    },
+                };
+
+                match render_result {
                     Ok(_) => {}
                     // Reconfigure the surface if it's lost or outdated
                     Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
@@ -114,6 +282,13 @@ impl ApplicationHandler<()> for App {
                         log::error!("Unable to render {}", e);
                     }
                 }
+
+                if let Some(settings) = ui.menu.take_changed_settings() {
+                    self.apply_settings(settings);
+                }
+                if let Some(action) = self.ui.as_mut().unwrap().menu.take_action() {
+                    self.handle_menu_action(event_loop, action);
+                }
             },
 
             WindowEvent::KeyboardInput {
@@ -121,99 +296,204 @@ impl ApplicationHandler<()> for App {
                     KeyEvent {
                         physical_key: PhysicalKey::Code(code),
                         state: key_state,
+                        repeat,
                         ..
                     },
                     ..
             } => {
-                if key_state.is_pressed() {
-                    match code {
-                        KeyCode::KeyQ => {
-                            event_loop.exit();
+                // The main menu only takes typing, which goes to the UI
+                if let GameState::InGame { world, paused } = &mut self.state {
+                    let ui = self.ui.as_mut().unwrap();
+
+                    if key_state.is_pressed() && ui.console.is_open() {
+                        // Everything else is typing
+                        if code == KeyCode::Escape {
+                            ui.console.close();
                         }
-
-                        KeyCode::Escape => {
-                            render_state.window.set_cursor_visible(true);
-                            render_state.window
-                                .set_cursor_grab(winit::window::CursorGrabMode::None)
-                                .unwrap();
-                            self.mouse_trapped = false;
+                    } else if key_state.is_pressed() && code == KeyCode::Escape {
+                        if ui.menu.is_settings() {
+                            ui.menu.close_settings();
+                        } else if *paused {
+                            ui.menu.hide();
+                            *paused = false;
+                            world.resume();
+                            self.trap_mouse(true);
+                        } else if world.open_container().is_some() {
+                            world.close_container();
+                            self.trap_mouse(false);
+                        } else {
+                            ui.menu.show_pause();
+                            *paused = true;
+                            world.player_mut().release_controls();
+                            self.trap_mouse(false);
                         }
+                    } else if key_state.is_pressed() && *paused {
+                        // Nothing to control while paused
+                    } else if key_state.is_pressed() {
+                        match code {
+                            KeyCode::KeyQ => {
+                                event_loop.exit();
+                            }
+
+                            KeyCode::Slash if !repeat => {
+                                world.player_mut().release_controls();
+                                ui.console.open("/");
+                                self.trap_mouse(false);
+                                // Don't type the slash twice
+                                return;
+                            }
+
+                            KeyCode::KeyW => {
+                                world.player_mut().w_pressed = true;
+                            }
+
+                            KeyCode::KeyA => {
+                                world.player_mut().a_pressed = true;
+                            }
+
+                            KeyCode::KeyS => {
+                                world.player_mut().s_pressed = true;
+                            }
+
+                            KeyCode::KeyD => {
+                                world.player_mut().d_pressed = true;
+                            }
+
+                            KeyCode::ShiftLeft | KeyCode::ShiftRight => {
+                                world.player_mut().sprint = true;
+                            }
+
+                            KeyCode::ControlLeft | KeyCode::ControlRight => {
+                                world.player_mut().sneak = true;
+                            }
+
+                            KeyCode::Space => {
+                                let player = world.player_mut();
+                                player.jump = true;
+                                player.space_pressed = true;
+                                if !repeat {
+                                    player.tap_space();
+                                }
+                            }
+
+                            KeyCode::F3 if !repeat => {
+                                ui.state.toggle_debug();
+                            }
+
+                            KeyCode::F4 if !repeat => {
+                                let player = world.player_mut();
+                                player.set_game_mode(player.game_mode().next());
+                            }
+
+                            _ => {},
+                        };
+                    } else {
+                        match code {
+                            KeyCode::KeyW => {
+                                world.player_mut().w_pressed = false;
+                            }
+
+                            KeyCode::KeyA => {
+                                world.player_mut().a_pressed = false;
+                            }
+
+                            KeyCode::KeyS => {
+                                world.player_mut().s_pressed = false;
+                            }
+
+                            KeyCode::KeyD => {
+                                world.player_mut().d_pressed = false;
+                            }
+
+                            KeyCode::ShiftLeft | KeyCode::ShiftRight => {
+                                world.player_mut().sprint = false;
+                            }
+
+                            KeyCode::ControlLeft | KeyCode::ControlRight => {
+                                world.player_mut().sneak = false;
+                            }
+
+                            KeyCode::Space => {
+                                world.player_mut().space_pressed = false;
+                            }
+
+                            _ => {},
+                        };
+                    }
+                }
+            },
 
-                        KeyCode::KeyW => {
-                            self.world.player_mut().w_pressed = true;
-                        }
+            WindowEvent::MouseInput { state, button: MouseButton::Right, .. }
+                if state.is_pressed() && self.mouse_trapped => {
 
-                        KeyCode::KeyA => {
-                            self.world.player_mut().a_pressed = true;
-                        }
+                if let GameState::InGame { world, .. } = &mut self.state {
+                    // Sneaking places against blocks that could be used
+                    // instead
+                    let sneaking = world.player().sneak;
+                    // Food is eaten by holding right click, rather than placed
+                    if (sneaking || !world.use_block()) &&
+                        !world.player_mut().start_eating() {
 
-                        KeyCode::KeyS => {
-                            self.world.player_mut().s_pressed = true;
-                        }
+                        world.place_block();
+                    }
 
-                        KeyCode::KeyD => {
-                            self.world.player_mut().d_pressed = true;
-                        }
+                    // Free the mouse to click around in the container
+                    if world.open_container().is_some() {
+                        self.trap_mouse(false);
+                    }
+                }
+            }
 
-                        KeyCode::ShiftLeft | KeyCode::ShiftRight => {
-                            self.world.player_mut().sprint = true;
-                        }
+            WindowEvent::MouseInput { state, button: MouseButton::Right, .. }
+                if !state.is_pressed() => {
 
-                        KeyCode::Space => {
-                            self.world.player_mut().jump = true;
-                        }
+                if let GameState::InGame { world, .. } = &mut self.state {
+                    world.player_mut().stop_eating();
+                }
+            }
 
-                        _ => {},
+            WindowEvent::MouseWheel { delta, .. } if self.mouse_trapped => {
+                if let GameState::InGame { world, .. } = &mut self.state {
+                    let steps = match delta {
+                        MouseScrollDelta::LineDelta(_, y) => y,
+                        MouseScrollDelta::PixelDelta(p) => p.y as f32,
                     };
-                } else {
-                    match code {
-                        KeyCode::KeyW => {
-                            self.world.player_mut().w_pressed = false;
-                        }
-
-                        KeyCode::KeyA => {
-                            self.world.player_mut().a_pressed = false;
-                        }
-
-                        KeyCode::KeyS => {
-                            self.world.player_mut().s_pressed = false;
-                        }
-
-                        KeyCode::KeyD => {
-                            self.world.player_mut().d_pressed = false;
-                        }
+                    // Scrolling down moves on to the next block
+                    if steps != 0.0 {
+                        world.player_mut().cycle_held(-steps.signum() as i32);
+                    }
+                }
+            }
 
-                        KeyCode::ShiftLeft | KeyCode::ShiftRight => {
-                            self.world.player_mut().sprint = false;
-                        }
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. }
+                if !state.is_pressed() => {
 
-                        _ => {},
-                    };
+                if let GameState::InGame { world, .. } = &mut self.state {
+                    world.stop_breaking();
                 }
-            },
+            }
 
             WindowEvent::MouseInput { state, button, .. } => {
-                if state.is_pressed() && button == MouseButton::Left {
-                    if self.mouse_trapped {
-                        self.world.destroy_block();
+                let for_ui = !self.mouse_trapped &&
+                    self.ui.as_ref().is_some_and(|ui| ui.wants_mouse());
+                if let GameState::InGame { world, paused: false } = &mut self.state &&
+                    state.is_pressed() && button == MouseButton::Left && !for_ui {
+
+                    if self.mouse_trapped && !world.attack() {
+                        world.start_breaking();
+                    }
+                    world.close_container();
+                    if let Some(ui) = &mut self.ui {
+                        ui.console.close();
                     }
 
-                    render_state.window
-                        .set_cursor_grab(winit::window::CursorGrabMode::Locked)
-                        .unwrap();
-                    render_state.window.set_cursor_visible(false);
-                    self.mouse_trapped = true;
+                    self.trap_mouse(true);
                 }
             }
 
             _ => {},
         }
 
-        if let Some(ui) = &mut self.ui {
-            ui.handle_event(
-                &render_state.window,
-                &Event::WindowEvent { window_id, event }
-            );
-        }
+        self.forward_to_ui(window_id, event);
     }
 }
@@ -15,10 +15,14 @@ mod physics;
 mod ui;
 /// Vector math helpers
 mod vectors;
+/// Settings the player can change, saved between runs
+mod user_settings;
 
 fn main() -> anyhow::Result<()> {
     env_logger::builder().filter_level(log::LevelFilter::Info).init();
 
+    world::block::load_registry(settings::BLOCK_REGISTRY)?;
+
     let event_loop = EventLoop::new()?;
     let mut app = App::new();
     event_loop.run_app(&mut app)?;
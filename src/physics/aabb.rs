@@ -52,4 +52,13 @@ impl AABB {
     pub fn get_bounds(&self, position: Point3<f32>) -> (Point3<f32>, Point3<f32>) {
         (position + self.min_off, position + self.max_off)
     }
+
+    pub fn origin(&self) -> Point3<f32> {
+        self.origin
+    }
+
+    /// The volume of the box in cubic meters
+    pub fn volume(&self) -> f32 {
+        self.x_sz * self.y_sz * self.z_sz
+    }
 }
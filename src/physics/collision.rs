@@ -0,0 +1,324 @@
+use std::borrow::Cow;
+use cgmath::{Point3, Vector3, num_traits::ToPrimitive};
+use crate::{vectors::Dimension, world::GameWorld};
+
+/// Opposite (min, max) corners of a box in world space
+pub type Bounds = (Point3<f32>, Point3<f32>);
+
+/// Slack given when comparing against block boundaries, to absorb float error
+/// from collision resolution. Boxes touching a block are not overlapping it.
+pub const EPSILON: f32 = 1.0e-4;
+
+/// A box within a single block, as (min, max) corners in block-local
+/// coordinates. Usually within 0 to 1, but can reach up out of the block.
+pub type LocalBox = ([f32; 3], [f32; 3]);
+
+/// A whole block
+pub const FULL_BOX: LocalBox = ([0.0; 3], [1.0; 3]);
+
+/// How far boxes can reach up out of their blocks, so neighbours below get
+/// checked too
+const MAX_BOX_OVERHANG: f32 = 0.5;
+
+/// A grid of blocks that entities can collide with
+pub trait Collider {
+    fn is_solid(&self, x: i32, y: i32, z: i32) -> bool;
+    fn is_fluid(&self, x: i32, y: i32, z: i32) -> bool;
+
+    /// The boxes entities collide with at the given block. Solid blocks are
+    /// full cubes unless this says otherwise.
+    fn collision_boxes(&self, x: i32, y: i32, z: i32) -> Cow<'static, [LocalBox]> {
+        if self.is_solid(x, y, z) {
+            Cow::Borrowed(&[FULL_BOX])
+        } else {
+            Cow::Borrowed(&[])
+        }
+    }
+}
+
+impl Collider for GameWorld {
+    fn is_solid(&self, x: i32, y: i32, z: i32) -> bool {
+        self.get_block((x, y, z)).is_some_and(|b| b.kind.is_solid())
+    }
+
+    fn is_fluid(&self, x: i32, y: i32, z: i32) -> bool {
+        self.get_block((x, y, z)).is_some_and(|b| b.is_fluid())
+    }
+
+    fn collision_boxes(&self, x: i32, y: i32, z: i32) -> Cow<'static, [LocalBox]> {
+        match self.get_block((x, y, z)) {
+            Some(block) if block.kind.is_solid() => {
+                block.kind.shape().collision_boxes(self.model_context((x, y, z), block))
+            },
+            _ => Cow::Borrowed(&[]),
+        }
+    }
+}
+
+/// The world space bounds of a box in the given block
+pub fn box_bounds((x, y, z): (i32, i32, i32), (min, max): LocalBox) -> Bounds {
+    let corner = Vector3::new(x as f32, y as f32, z as f32);
+    (Point3::from(min) + corner, Point3::from(max) + corner)
+}
+
+/// Whether two bounds overlap by more than `EPSILON` on every axis
+pub fn overlaps(a: Bounds, b: Bounds) -> bool {
+    a.0.x < b.1.x - EPSILON && a.1.x > b.0.x + EPSILON &&
+    a.0.y < b.1.y - EPSILON && a.1.y > b.0.y + EPSILON &&
+    a.0.z < b.1.z - EPSILON && a.1.z > b.0.z + EPSILON
+}
+
+/// Calls `f` with the world space bounds of every collision box belonging to
+/// the blocks that the given bounds overlap (or that could reach up into
+/// them)
+fn for_each_box(world: &impl Collider, bounds: Bounds, mut f: impl FnMut(Bounds)) {
+    let reach = (
+        Point3::new(bounds.0.x, bounds.0.y - MAX_BOX_OVERHANG, bounds.0.z),
+        bounds.1,
+    );
+    for_each_block(reach, |x, y, z| {
+        for local in world.collision_boxes(x, y, z).iter() {
+            f(box_bounds((x, y, z), *local));
+        }
+    });
+}
+
+pub fn get_axis<T: Copy>(v: [T; 3], axis: Dimension) -> T {
+    match axis {
+        Dimension::X => v[0],
+        Dimension::Y => v[1],
+        Dimension::Z => v[2],
+    }
+}
+
+/// Moves the bounds by `d` along the given axis
+pub fn offset_bounds(bounds: Bounds, axis: Dimension, d: f32) -> Bounds {
+    let offset = match axis {
+        Dimension::X => Vector3::new(d, 0.0, 0.0),
+        Dimension::Y => Vector3::new(0.0, d, 0.0),
+        Dimension::Z => Vector3::new(0.0, 0.0, d),
+    };
+
+    (bounds.0 + offset, bounds.1 + offset)
+}
+
+/// The range of block coordinates that a span of space overlaps
+fn block_range(min: f32, max: f32) -> std::ops::Range<i32> {
+    let start = (min + EPSILON).floor().to_i32().unwrap();
+    let end = (max - EPSILON).ceil().to_i32().unwrap();
+    start..end
+}
+
+/// Calls `f` with every block position that the given bounds overlap
+pub fn for_each_block(bounds: Bounds, mut f: impl FnMut(i32, i32, i32)) {
+    for y in block_range(bounds.0.y, bounds.1.y) {
+        for x in block_range(bounds.0.x, bounds.1.x) {
+            for z in block_range(bounds.0.z, bounds.1.z) {
+                f(x, y, z);
+            }
+        }
+    }
+}
+
+pub fn collides_with_solid(world: &impl Collider, bounds: Bounds) -> bool {
+    let mut hit = false;
+    for_each_box(world, bounds, |b| hit |= overlaps(bounds, b));
+    hit
+}
+
+/// How far up the given bounds would have to move to sit on top of every
+/// collision box they overlap
+pub fn step_climb(world: &impl Collider, bounds: Bounds) -> f32 {
+    let mut climb: f32 = 0.0;
+    for_each_box(world, bounds, |b| {
+        if overlaps(bounds, b) {
+            climb = climb.max(b.1.y - bounds.0.y);
+        }
+    });
+    climb
+}
+
+/// Whether there is anything solid directly beneath the given bounds
+pub fn is_supported(world: &impl Collider, bounds: Bounds) -> bool {
+    let below = (
+        Point3::new(bounds.0.x, bounds.0.y - 0.1, bounds.0.z),
+        Point3::new(bounds.1.x, bounds.0.y, bounds.1.z),
+    );
+    collides_with_solid(world, below)
+}
+
+/// Sweeps the bounds `delta` meters along one axis, returning how far they
+/// can actually travel before the leading face hits a collision box.
+///
+/// Every block along the way is checked, so nothing is skipped no matter how
+/// large `delta` is. Boxes the bounds already overlap are ignored, so stuck
+/// entities can still move out.
+pub fn sweep_axis(world: &impl Collider, bounds: Bounds, axis: Dimension, delta: f32) -> f32 {
+    if delta == 0.0 {
+        return 0.0;
+    }
+
+    let swept = offset_bounds(bounds, axis, delta);
+    let region = (
+        Point3::new(
+            bounds.0.x.min(swept.0.x),
+            bounds.0.y.min(swept.0.y),
+            bounds.0.z.min(swept.0.z),
+        ),
+        Point3::new(
+            bounds.1.x.max(swept.1.x),
+            bounds.1.y.max(swept.1.y),
+            bounds.1.z.max(swept.1.z),
+        ),
+    );
+
+    // Only boxes in the way on the other two axes can block movement
+    let in_path = |b: Bounds| {
+        let (a0, a1): ([f32; 3], [f32; 3]) = (bounds.0.into(), bounds.1.into());
+        let (b0, b1): ([f32; 3], [f32; 3]) = (b.0.into(), b.1.into());
+        [Dimension::X, Dimension::Y, Dimension::Z].into_iter()
+            .filter(|d| *d != axis)
+            .all(|d| {
+                get_axis(a0, d) < get_axis(b1, d) - EPSILON &&
+                get_axis(a1, d) > get_axis(b0, d) + EPSILON
+            })
+    };
+
+    let mut allowed = delta;
+    for_each_box(world, region, |b| {
+        if !in_path(b) {
+            return;
+        }
+
+        if delta > 0.0 {
+            let lead = get_axis(bounds.1.into(), axis);
+            let face = get_axis(b.0.into(), axis);
+            if face >= lead - EPSILON {
+                allowed = allowed.min((face - lead).max(0.0));
+            }
+        } else {
+            let lead = get_axis(bounds.0.into(), axis);
+            let face = get_axis(b.1.into(), axis);
+            if face <= lead + EPSILON {
+                allowed = allowed.max((face - lead).min(0.0));
+            }
+        }
+    });
+
+    allowed
+}
+
+/// The distance along the ray from `origin` in direction `dir` at which it
+/// enters the given bounds, if it hits them at all
+pub fn ray_hits(origin: Point3<f32>, dir: Vector3<f32>, bounds: Bounds) -> Option<f32> {
+    let mut near = f32::NEG_INFINITY;
+    let mut far = f32::INFINITY;
+
+    for d in [Dimension::X, Dimension::Y, Dimension::Z] {
+        let o = get_axis(origin.into(), d);
+        let v = get_axis(dir.into(), d);
+        let (min, max) = (get_axis(bounds.0.into(), d), get_axis(bounds.1.into(), d));
+
+        if v == 0.0 {
+            if o < min || o > max {
+                return None;
+            }
+            continue;
+        }
+
+        let t0 = (min - o) / v;
+        let t1 = (max - o) / v;
+        near = near.max(t0.min(t1));
+        far = far.min(t0.max(t1));
+    }
+
+    (near <= far && far >= 0.0).then_some(near.max(0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A floor of solid blocks at y = 10, stretching out in every direction
+    struct Floor;
+
+    impl Collider for Floor {
+        fn is_solid(&self, _x: i32, y: i32, _z: i32) -> bool {
+            y == 10
+        }
+
+        fn is_fluid(&self, _x: i32, _y: i32, _z: i32) -> bool {
+            false
+        }
+    }
+
+    fn unit_box(min: Point3<f32>) -> Bounds {
+        (min, min + Vector3::new(1.0, 1.0, 1.0))
+    }
+
+    #[test]
+    fn sweep_stops_at_floor_from_far_above() {
+        let bounds = unit_box(Point3::new(0.5, 200.0, 0.5));
+        let moved = sweep_axis(&Floor, bounds, Dimension::Y, -1000.0);
+        assert!((moved - -189.0).abs() < 1.0e-3, "moved {moved}");
+    }
+
+    #[test]
+    fn sweep_passes_through_when_clear() {
+        let bounds = unit_box(Point3::new(0.5, 20.0, 0.5));
+        let moved = sweep_axis(&Floor, bounds, Dimension::Y, 50.0);
+        assert_eq!(moved, 50.0);
+    }
+
+    #[test]
+    fn sweep_ignores_blocks_already_overlapped() {
+        let bounds = unit_box(Point3::new(0.5, 10.5, 0.5));
+        let moved = sweep_axis(&Floor, bounds, Dimension::Y, 3.0);
+        assert_eq!(moved, 3.0);
+    }
+
+    /// Slabs on a single row of blocks at y = 10
+    struct Slabs;
+
+    impl Collider for Slabs {
+        fn is_solid(&self, _x: i32, y: i32, _z: i32) -> bool {
+            y == 10
+        }
+
+        fn is_fluid(&self, _x: i32, _y: i32, _z: i32) -> bool {
+            false
+        }
+
+        fn collision_boxes(&self, x: i32, y: i32, z: i32) -> Cow<'static, [LocalBox]> {
+            if self.is_solid(x, y, z) {
+                Cow::Borrowed(&[([0.0; 3], [1.0, 0.5, 1.0])])
+            } else {
+                Cow::Borrowed(&[])
+            }
+        }
+    }
+
+    #[test]
+    fn lands_on_top_of_slab() {
+        let bounds = unit_box(Point3::new(0.5, 30.0, 0.5));
+        let moved = sweep_axis(&Slabs, bounds, Dimension::Y, -100.0);
+        assert!((moved - -19.5).abs() < 1.0e-3, "moved {moved}");
+        assert!((step_climb(&Slabs, unit_box(Point3::new(0.5, 10.2, 0.5))) - 0.3).abs() < 1.0e-3);
+    }
+
+    #[test]
+    fn ray_hits_box_in_front() {
+        let bounds = unit_box(Point3::new(2.0, 0.0, 0.0));
+        let t = ray_hits(Point3::new(0.0, 0.5, 0.5), Vector3::new(1.0, 0.0, 0.0), bounds);
+        assert_eq!(t, Some(2.0));
+        assert_eq!(ray_hits(Point3::new(0.0, 0.5, 0.5), Vector3::new(-1.0, 0.0, 0.0), bounds), None);
+        assert_eq!(ray_hits(Point3::new(0.0, 5.0, 0.5), Vector3::new(1.0, 0.0, 0.0), bounds), None);
+    }
+
+    #[test]
+    fn resting_box_does_not_move_down() {
+        let bounds = unit_box(Point3::new(0.5, 11.0, 0.5));
+        let moved = sweep_axis(&Floor, bounds, Dimension::Y, -0.5);
+        assert_eq!(moved, 0.0);
+    }
+}
@@ -1,6 +1,5 @@
-use cgmath::{Point3, Vector3, Zero, num_traits::{Signed, ToPrimitive}};
-use wgpu::wgc::MAX_VERTEX_BUFFERS;
-use crate::{physics::{AABB, entity}, settings::PHYSICS_TICK_RATE, vectors::Dimension, world::{Coordinate, GameWorld, ThreeDimPos, block::BlockType}};
+use cgmath::{Point3, Vector3, Zero};
+use crate::{physics::{AABB, collision::{Bounds, Collider, collides_with_solid, for_each_block, get_axis, is_supported, offset_bounds, step_climb, sweep_axis}}, settings::{BUOYANCY_A, PHYSICS_TICK_RATE, WATER_DRAG}, vectors::Dimension, world::{Coordinate, GameWorld, ThreeDimPos}};
 
 #[derive(Debug)]
 /// A raw `Entity` that only has a position, velocity, accel, and AABB
@@ -13,8 +12,32 @@ pub struct RawEntity {
     acceleration: Vector3<f32>,
     /// Bounding box, for collision detection
     bounding_box: AABB,
+    /// Fraction (0 to 1) of the bounding box that was inside of a fluid as of
+    /// the last tick
+    submersion: f32,
+    /// Whether the entity was standing on a solid block as of the last tick
+    on_ground: bool,
+    /// The tallest ledge (in meters) the entity will walk up onto without
+    /// jumping
+    step_height: f32,
+    /// If set, the entity won't walk off of the edge of whatever it is
+    /// standing on
+    edge_guard: bool,
+    /// If set, the entity passes through blocks and ignores fluids
+    noclip: bool,
 }   
 
+/// Slack given when comparing ledge heights against the step height
+const STEP_EPSILON: f32 = 1.0e-3;
+
+fn add_axis(p: &mut Point3<f32>, axis: Dimension, d: f32) {
+    match axis {
+        Dimension::X => p.x += d,
+        Dimension::Y => p.y += d,
+        Dimension::Z => p.z += d,
+    };
+}
+
 /// A dynamic, physics-affected thing in-game (players, mobs, whatever)
 pub trait Entity {
     /// Computes one physics tick of the entity
@@ -31,7 +54,7 @@ pub trait Entity {
     fn get_world_pos(&self) -> ThreeDimPos {
         (
             self.get_precise_pos().x.floor() as Coordinate,
-            self.get_precise_pos().y.floor() as u8,
+            self.get_precise_pos().y.floor() as Coordinate,
             self.get_precise_pos().z.floor() as Coordinate,
         )
     }
@@ -44,92 +67,165 @@ impl RawEntity {
             velocity: Vector3::zero(),
             acceleration: Vector3::zero(),
             bounding_box,
+            submersion: 0.0,
+            on_ground: false,
+            step_height: 0.0,
+            edge_guard: false,
+            noclip: false,
         }
     }
-}
 
-impl Entity for RawEntity {
-    fn tick(&mut self, world: &GameWorld) {
+    pub fn is_on_ground(&self) -> bool {
+        self.on_ground
+    }
+
+    /// The world space bounds of the entity's bounding box
+    pub fn bounds(&self) -> Bounds {
+        self.bounding_box.get_bounds(self.position)
+    }
+
+    pub fn set_noclip(&mut self, noclip: bool) {
+        self.noclip = noclip;
+    }
+
+    pub fn set_step_height(&mut self, h: f32) {
+        self.step_height = h;
+    }
+
+    pub fn set_edge_guard(&mut self, guard: bool) {
+        self.edge_guard = guard;
+    }
+
+    /// Swaps out the bounding box, keeping the bottom of the entity in place.
+    /// Returns false (and leaves the box as it was) if the new box wouldn't
+    /// fit where the entity is.
+    pub fn set_bounding_box(&mut self, world: &impl Collider, bounding_box: AABB) -> bool {
+        let shift = bounding_box.origin().y - self.bounding_box.origin().y;
+        let new_position = self.position + Vector3::new(0.0, shift, 0.0);
+
+        if collides_with_solid(world, bounding_box.get_bounds(new_position)) {
+            return false;
+        }
+
+        self.position = new_position;
+        self.bounding_box = bounding_box;
+        true
+    }
+
+    /// Computes one physics tick against the given blocks
+    pub fn step(&mut self, world: &impl Collider) {
         self.velocity += self.acceleration / PHYSICS_TICK_RATE;
 
-        let tick_v = self.velocity / PHYSICS_TICK_RATE;
-        let dx = tick_v.x;
-        let dy = tick_v.y;
-        let dz = tick_v.z;
+        if self.noclip {
+            self.position += self.velocity / PHYSICS_TICK_RATE;
+            self.submersion = 0.0;
+            self.on_ground = false;
+            return;
+        }
 
-        let mut check_axis_collisions = |axis: Dimension| {
-            match axis {
-                Dimension::X => self.position.x += dx,
-                Dimension::Y => self.position.y += dy,
-                Dimension::Z => self.position.z += dz,
-            };
-            let bounds = self.bounding_box.get_bounds(self.position);
+        // Fluids push up and slow down whatever is in them
+        self.submersion = self.compute_submersion(world);
+        if self.submersion > 0.0 {
+            self.velocity.y += BUOYANCY_A * self.submersion / PHYSICS_TICK_RATE;
 
-            let pos_movement = match axis {
-                Dimension::X => dx > 0.0,
-                Dimension::Y => dy > 0.0,
-                Dimension::Z => dz > 0.0,
-            };
+            let drag = WATER_DRAG * self.submersion / PHYSICS_TICK_RATE;
+            self.velocity *= (1.0 - drag).max(0.0);
+        }
 
-            let (entity_axis_min, entity_axis_max) = match axis {
-                Dimension::X => (bounds.0.x, bounds.1.x),
-                Dimension::Y => (bounds.0.y, bounds.1.y),
-                Dimension::Z => (bounds.0.z, bounds.1.z),
-            };
+        let was_on_ground = self.on_ground;
+        self.on_ground = false;
 
-            let start_x = bounds.0.x.floor().to_i32().unwrap();
-            let end_x = bounds.1.x.ceil().to_i32().unwrap();
-            let start_y = bounds.0.y.floor().to_i32().unwrap();
-            let end_y = bounds.1.y.ceil().to_i32().unwrap();
-            let start_z = bounds.0.z.floor().to_i32().unwrap();
-            let end_z = bounds.1.z.ceil().to_i32().unwrap();
-            let mut max_overlap = 0.0;
-            for y in start_y..end_y {
-                if y > u8::MAX as i32 { break; }
-                for x in start_x..end_x {
-                    for z in start_z..end_z {
-                        if let Some(b) = world.get_block((x, y as u8, z)) &&
-                            b.is_solid() {
-
-                            let block_axis = match axis {
-                                Dimension::X => x as f32,
-                                Dimension::Y => y as f32,
-                                Dimension::Z => z as f32,
-                            };
-
-                            let overlap = if pos_movement {
-                                entity_axis_max - block_axis
-                            } else {
-                                (block_axis + 1.0) - entity_axis_min
-                            };
-                            
-                            if overlap > max_overlap {
-                                max_overlap = overlap;
-                            }
-                        }
-                    }
-                }
+        self.move_axis(world, Dimension::X, was_on_ground);
+        self.move_axis(world, Dimension::Y, was_on_ground);
+        self.move_axis(world, Dimension::Z, was_on_ground);
+    }
+
+    /// Moves the entity along one axis for this tick, stopping it at the first
+    /// solid block in the way
+    fn move_axis(&mut self, world: &impl Collider, axis: Dimension, was_on_ground: bool) {
+        let delta = get_axis(self.velocity.into(), axis) / PHYSICS_TICK_RATE;
+        if delta == 0.0 {
+            return;
+        }
+
+        let old_position = self.position;
+        let bounds = self.bounding_box.get_bounds(self.position);
+        let moved = sweep_axis(world, bounds, axis, delta);
+        let blocked = moved != delta;
+
+        let horizontal = axis != Dimension::Y;
+        let mut stepped = false;
+
+        // Walk up ledges low enough to step onto rather than stopping
+        if blocked && horizontal && was_on_ground && self.step_height > 0.0 {
+            let target = offset_bounds(bounds, axis, delta);
+            let climb = step_climb(world, target);
+
+            if climb > 0.0 && climb <= self.step_height + STEP_EPSILON &&
+                sweep_axis(world, bounds, Dimension::Y, climb) == climb &&
+                !collides_with_solid(world, offset_bounds(target, Dimension::Y, climb)) {
+
+                self.position.y += climb;
+                add_axis(&mut self.position, axis, delta);
+                stepped = true;
             }
+        }
 
-            if pos_movement {
-                max_overlap *= -1.0;
-            };
+        if !stepped {
+            add_axis(&mut self.position, axis, moved);
+        }
+
+        // Don't let guarded entities walk off of whatever they stand on
+        if self.edge_guard && was_on_ground && horizontal &&
+            !is_supported(world, self.bounding_box.get_bounds(self.position)) {
+
+            self.position = old_position;
+        }
+
+        if blocked && !stepped {
+            // Landing on something counts as being on ground
+            if axis == Dimension::Y && delta < 0.0 {
+                self.on_ground = true;
+            }
 
             match axis {
-                Dimension::X => self.position.x += max_overlap,
-                Dimension::Y => self.position.y += max_overlap,
-                Dimension::Z => self.position.z += max_overlap,
+                Dimension::X => self.velocity.x = 0.0,
+                Dimension::Y => self.velocity.y = 0.0,
+                Dimension::Z => self.velocity.z = 0.0,
             };
+        }
+    }
 
-            // Reset gravity velocity if we're on ground
-            if let Dimension::Y = axis && max_overlap > 0.0 && dy.is_negative() {
-                self.velocity.y = 0.0;
-            }
+    /// Fraction (0 to 1) of the entity that is submerged in a fluid
+    pub fn get_submersion(&self) -> f32 {
+        self.submersion
+    }
+
+    /// Computes what fraction of the bounding box overlaps fluid blocks
+    fn compute_submersion(&self, world: &impl Collider) -> f32 {
+        let (min, max) = self.bounding_box.get_bounds(self.position);
+
+        let overlap = |b_min: i32, e_min: f32, e_max: f32| {
+            let b_min = b_min as f32;
+            (e_max.min(b_min + 1.0) - e_min.max(b_min)).max(0.0)
         };
 
-        check_axis_collisions(Dimension::X);
-        check_axis_collisions(Dimension::Y);
-        check_axis_collisions(Dimension::Z);
+        let mut volume = 0.0;
+        for_each_block((min, max), |x, y, z| {
+            if world.is_fluid(x, y, z) {
+                volume += overlap(x, min.x, max.x)
+                    * overlap(y, min.y, max.y)
+                    * overlap(z, min.z, max.z);
+            }
+        });
+
+        (volume / self.bounding_box.volume()).min(1.0)
+    }
+}
+
+impl Entity for RawEntity {
+    fn tick(&mut self, world: &GameWorld) {
+        self.step(world);
     }
 
     fn get_precise_pos(&self) -> Point3<f32> {
@@ -152,3 +248,143 @@ impl Entity for RawEntity {
         self.velocity = v;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::{GRAVITY_A, MOVE_SPEED, PLAYER_AABB, PLAYER_STEP_HEIGHT};
+
+    /// A single block thick, 10x10 floor at y = 10
+    struct Platform;
+
+    impl Collider for Platform {
+        fn is_solid(&self, x: i32, y: i32, z: i32) -> bool {
+            y == 10 && (-5..5).contains(&x) && (-5..5).contains(&z)
+        }
+
+        fn is_fluid(&self, _x: i32, _y: i32, _z: i32) -> bool {
+            false
+        }
+    }
+
+    fn feet(entity: &RawEntity) -> f32 {
+        entity.bounding_box.get_bounds(entity.position).0.y
+    }
+
+    #[test]
+    fn fast_fall_lands_on_thin_floor() {
+        let mut entity = RawEntity::new(Point3::new(0.5, 40.0, 0.5), PLAYER_AABB);
+        entity.set_acceleration(GRAVITY_A);
+        entity.set_velocity(Vector3::new(0.0, -2000.0, 0.0));
+
+        entity.step(&Platform);
+
+        assert!((feet(&entity) - 11.0).abs() < 1.0e-3, "feet at {}", feet(&entity));
+        assert_eq!(entity.get_velocity().y, 0.0);
+        assert!(entity.on_ground);
+    }
+
+    #[test]
+    fn long_fall_stays_on_thin_floor() {
+        let mut entity = RawEntity::new(Point3::new(-2.5, 200.0, 3.5), PLAYER_AABB);
+        entity.set_acceleration(GRAVITY_A);
+
+        for _ in 0..(PHYSICS_TICK_RATE as usize * 10) {
+            entity.step(&Platform);
+        }
+
+        assert!((feet(&entity) - 11.0).abs() < 1.0e-3, "feet at {}", feet(&entity));
+    }
+
+    #[test]
+    fn fast_fall_beside_floor_keeps_falling() {
+        let mut entity = RawEntity::new(Point3::new(20.5, 40.0, 0.5), PLAYER_AABB);
+        entity.set_velocity(Vector3::new(0.0, -2000.0, 0.0));
+
+        entity.step(&Platform);
+
+        assert!(feet(&entity) < 11.0);
+        assert!(!entity.on_ground);
+    }
+
+    #[test]
+    fn fast_sideways_move_stops_at_wall() {
+        struct Wall;
+        impl Collider for Wall {
+            fn is_solid(&self, x: i32, _y: i32, _z: i32) -> bool {
+                x == 10
+            }
+
+            fn is_fluid(&self, _x: i32, _y: i32, _z: i32) -> bool {
+                false
+            }
+        }
+
+        let mut entity = RawEntity::new(Point3::new(0.5, 50.0, 0.5), PLAYER_AABB);
+        entity.set_velocity(Vector3::new(3000.0, 0.0, 0.0));
+
+        entity.step(&Wall);
+
+        let max_x = entity.bounding_box.get_bounds(entity.position).1.x;
+        assert!((max_x - 10.0).abs() < 1.0e-3, "stopped at {max_x}");
+    }
+
+    /// A floor at y = 10 everywhere, with a ledge the given number of blocks
+    /// tall on it from x = 3 on
+    struct Ledge(i32);
+
+    impl Collider for Ledge {
+        fn is_solid(&self, x: i32, y: i32, _z: i32) -> bool {
+            y == 10 || (x >= 3 && y > 10 && y <= 10 + self.0)
+        }
+
+        fn is_fluid(&self, _x: i32, _y: i32, _z: i32) -> bool {
+            false
+        }
+    }
+
+    /// An entity standing on the floor at x = 1.5, that steps up to
+    /// `PLAYER_STEP_HEIGHT`, walking towards positive X for two seconds
+    fn walk(world: &impl Collider, edge_guard: bool, x: f32) -> RawEntity {
+        let mut entity = RawEntity::new(Point3::new(x, 12.6, 0.5), PLAYER_AABB);
+        entity.set_acceleration(GRAVITY_A);
+        entity.set_step_height(PLAYER_STEP_HEIGHT);
+        entity.set_edge_guard(edge_guard);
+        entity.step(world);
+        assert!(entity.on_ground);
+
+        for _ in 0..(PHYSICS_TICK_RATE as usize * 2) {
+            entity.set_velocity(Vector3::new(MOVE_SPEED, entity.get_velocity().y, 0.0));
+            entity.step(world);
+        }
+        entity
+    }
+
+    #[test]
+    fn steps_up_low_ledge() {
+        let entity = walk(&Ledge(1), false, 1.5);
+
+        assert!((feet(&entity) - 12.0).abs() < 1.0e-3, "feet at {}", feet(&entity));
+        assert!(entity.position.x > 5.0, "stopped at x = {}", entity.position.x);
+    }
+
+    #[test]
+    fn stopped_by_tall_ledge() {
+        let entity = walk(&Ledge(2), false, 1.5);
+
+        assert!((feet(&entity) - 11.0).abs() < 1.0e-3, "feet at {}", feet(&entity));
+        let max_x = entity.bounding_box.get_bounds(entity.position).1.x;
+        assert!((max_x - 3.0).abs() < 1.0e-3, "stopped at {max_x}");
+    }
+
+    #[test]
+    fn edge_guard_stops_at_drop() {
+        let guarded = walk(&Platform, true, 3.5);
+        assert!((feet(&guarded) - 11.0).abs() < 1.0e-3, "feet at {}", feet(&guarded));
+        let min_x = guarded.bounding_box.get_bounds(guarded.position).0.x;
+        assert!(min_x < 5.0, "walked off at {min_x}");
+
+        let unguarded = walk(&Platform, false, 3.5);
+        assert!(feet(&unguarded) < 11.0, "never fell");
+    }
+}
@@ -2,6 +2,8 @@
 mod entity;
 /// Axis-aligned bounding boxes
 mod aabb;
+/// Swept collision against the block grid
+pub mod collision;
 
 pub use entity::Entity;
 pub use entity::RawEntity;
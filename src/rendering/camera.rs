@@ -1,6 +1,6 @@
 use cgmath::{InnerSpace, Matrix3, Point3, Rad, Vector3};
 
-use crate::settings::{CAMERA_SPEED, FOV, MOUSE_SENSITIVITY};
+use crate::{settings::{CHUNK_SIZE, FAR_TERRAIN_DIST, FOG_START, view_dist}, user_settings::UserSettings};
 
 #[rustfmt::skip]
 pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::from_cols(
@@ -10,16 +10,6 @@ pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::from_co
     cgmath::Vector4::new(0.0, 0.0, 0.5, 1.0),
 );
 
-#[derive(Debug)]
-pub struct Controller {
-    pub is_forward_pressed: bool,
-    pub is_backward_pressed: bool,
-    pub is_left_pressed: bool,
-    pub is_right_pressed: bool,
-    pub is_up_pressed: bool,
-    pub is_down_pressed: bool,
-}
-
 /// Scene camera
 #[derive(Debug)]
 pub struct Camera {
@@ -30,36 +20,42 @@ pub struct Camera {
     pub(super) fovy: f32,
     pub(super) znear: f32,
     pub(super) zfar: f32,
-    pub controller: Controller,
+    /// Radians turned per pixel the mouse moves
+    sensitivity: f32,
 }
 
 impl Camera {
-    pub fn new(width: f32, height: f32) -> Self {
-        Self {
+    pub fn new(width: f32, height: f32, settings: &UserSettings) -> Self {
+        let mut camera = Self {
             eye: (0.0, 70.0, 0.0).into(),
             target: (5.0, 70.0, 5.00).into(),
             // which way is "up"
             up: cgmath::Vector3::unit_y(),
             aspect: width / height,
-            fovy: FOV,
             znear: 0.1,
-            zfar: 1000.0,
-            controller: Controller { 
-                is_forward_pressed: false,
-                is_backward_pressed: false,
-                is_left_pressed: false,
-                is_right_pressed: false,
-                is_up_pressed: false,
-                is_down_pressed: false,
-            }
-        }
+            // Set from the settings below
+            fovy: 0.0,
+            zfar: 0.0,
+            sensitivity: 0.0,
+        };
+        camera.apply_settings(settings);
+        camera
+    }
+
+    /// Picks up changes to the field of view, render distance and mouse
+    /// sensitivity. The projection is rebuilt from these every frame.
+    pub fn apply_settings(&mut self, settings: &UserSettings) {
+        self.fovy = settings.fov;
+        // Far enough to see everything out at the corners of the screen
+        self.zfar = view_dist(settings.render_dist) as f32 * 1.5;
+        self.sensitivity = settings.mouse_sensitivity;
     }
 
     /// **"Where the Magic Happens"** per https://sotrh.github.io/learn-wgpu/beginner/tutorial6-uniforms/#a-perspective-camera
 /// 1. The view matrix moves the world to be at the position and rotation of the camera. It's essentially an inverse of whatever the transform matrix of the camera would be.
 /// 2. The proj matrix warps the scene to give the effect of depth. Without this, objects up close would be the same size as objects far away.
 /// 3. The coordinate system in Wgpu is based on DirectX and Metal's coordinate systems. That means that in normalized device coordinates (opens new window), the x-axis and y-axis are in the range of -1.0 to +1.0, and the z-axis is 0.0 to +1.0. The cgmath crate (as well as most game math crates) is built for OpenGL's coordinate system. This matrix will scale and translate our scene from OpenGL's coordinate system to WGPU's. We'll define it as follows.
-    fn view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
+    pub fn view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
         // 1.
         let view = cgmath::Matrix4::look_at_rh(self.eye, self.target, self.up);
         // 2.
@@ -83,8 +79,8 @@ impl Camera {
     pub fn update_direction(&mut self, dx: f64, dy: f64) {
         let mut forward = (self.target - self.eye).normalize();
         let right = forward.cross(self.up).normalize();
-        let yaw   = -dx as f32 * MOUSE_SENSITIVITY;
-        let pitch = -dy as f32 * MOUSE_SENSITIVITY;
+        let yaw   = -dx as f32 * self.sensitivity;
+        let pitch = -dy as f32 * self.sensitivity;
 
         let yaw_rot = Matrix3::from_axis_angle(self.up, Rad(yaw));
         forward = yaw_rot * forward;
@@ -118,17 +114,62 @@ pub struct CameraUniform {
     // We can't use cgmath with bytemuck directly, so we'll have
     // to convert the Matrix4 into a 4x4 f32 array
     pub view_proj: [[f32; 4]; 4],
+    /// For turning screen positions back into view rays, for the sky
+    pub inv_view_proj: [[f32; 4]; 4],
+    pub position: [f32; 3],
+    /// Distance that fog starts fading in at on chunks
+    pub fog_start: f32,
+    /// Distance that chunks are fully fogged at, the edge of the loaded
+    /// chunks
+    pub fog_end: f32,
+    /// Distance that fog starts fading in at on far terrain
+    pub far_fog_start: f32,
+    /// Distance that far terrain is fully fogged at
+    pub far_fog_end: f32,
+    /// Due to uniforms requiring 16 byte (4 float) spacing, we need to pad
+    /// before the next vector
+    _padding0: u32,
+    /// Color of the sky straight up during the day
+    pub sky_color: [f32; 3],
+    /// Ditto, for the end
+    _padding1: u32,
 }
 
 impl CameraUniform {
-    pub fn new() -> Self {
+    pub fn new(settings: &UserSettings) -> Self {
         use cgmath::SquareMatrix;
-        Self {
+        let mut uniform = Self {
             view_proj: cgmath::Matrix4::identity().into(),
-        }
+            inv_view_proj: cgmath::Matrix4::identity().into(),
+            position: [0.0; 3],
+            // Set from the settings below
+            fog_start: 0.0,
+            fog_end: 0.0,
+            far_fog_start: FAR_TERRAIN_DIST as f32 * FOG_START,
+            far_fog_end: FAR_TERRAIN_DIST as f32,
+            _padding0: 0,
+            sky_color: [0.0; 3],
+            _padding1: 0,
+        };
+        uniform.apply_settings(settings);
+        uniform
+    }
+
+    /// Picks up changes to the render distance and sky color
+    pub fn apply_settings(&mut self, settings: &UserSettings) {
+        let chunk_dist = (settings.render_dist * CHUNK_SIZE) as f32;
+        self.fog_start = chunk_dist * FOG_START;
+        self.fog_end = chunk_dist;
+        self.sky_color = settings.sky_color;
     }
 
     pub fn update_view_proj(&mut self, camera: &Camera) {
-        self.view_proj = camera.view_projection_matrix().into();
+        use cgmath::SquareMatrix;
+        let view_proj = camera.view_projection_matrix();
+        self.view_proj = view_proj.into();
+        self.inv_view_proj = view_proj.invert()
+            .unwrap_or(cgmath::Matrix4::identity())
+            .into();
+        self.position = camera.eye.into();
     }
 }
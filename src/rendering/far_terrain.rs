@@ -0,0 +1,29 @@
+use cgmath::Point2;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+/// Parameters for drawing far terrain
+pub struct FarTerrainUniform {
+    hole_center: [f32; 2],
+    /// Radius of the circle around `hole_center` that far terrain is cut out
+    /// of, as loaded chunks are drawn there
+    hole_radius: f32,
+    /// Due to uniforms requiring 16 byte (4 float) spacing, we need to use a
+    /// padding field here
+    _padding: u32,
+}
+
+impl FarTerrainUniform {
+    pub fn new() -> Self {
+        Self {
+            hole_center: [0.0; 2],
+            hole_radius: 0.0,
+            _padding: 0,
+        }
+    }
+
+    pub fn update(&mut self, hole: (Point2<f32>, f32)) {
+        self.hole_center = hole.0.into();
+        self.hole_radius = hole.1;
+    }
+}
@@ -0,0 +1,45 @@
+use cgmath::{InnerSpace, Matrix, Matrix4, Point3, Vector3, Vector4};
+
+/// The volume visible through a view projection matrix, as six planes facing
+/// inwards
+#[derive(Debug, Copy, Clone)]
+pub struct Frustum {
+    planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Extracts the frustum planes from a view projection matrix. Assumes wgpu
+    /// clip space, where depth runs from 0 to 1.
+    ///
+    /// See https://www.gribb.com/compgraph/plane_extraction.pdf
+    pub fn from_matrix(m: Matrix4<f32>) -> Self {
+        let (r0, r1, r2, r3) = (m.row(0), m.row(1), m.row(2), m.row(3));
+
+        let planes = [
+            r3 + r0, // Left
+            r3 - r0, // Right
+            r3 + r1, // Bottom
+            r3 - r1, // Top
+            r2,      // Near
+            r3 - r2, // Far
+        ].map(|p| p / p.truncate().magnitude());
+
+        Self { planes }
+    }
+
+    /// Whether any part of the box between the given corners is inside the
+    /// frustum. Can give false positives near the corners of the frustum,
+    /// but never false negatives.
+    pub fn intersects_box(&self, min: Point3<f32>, max: Point3<f32>) -> bool {
+        self.planes.iter().all(|plane| {
+            // The corner furthest along the plane normal
+            let corner = Vector3::new(
+                if plane.x >= 0.0 { max.x } else { min.x },
+                if plane.y >= 0.0 { max.y } else { min.y },
+                if plane.z >= 0.0 { max.z } else { min.z },
+            );
+
+            plane.truncate().dot(corner) + plane.w >= 0.0
+        })
+    }
+}
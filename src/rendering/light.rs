@@ -1,6 +1,6 @@
 use cgmath::{Deg, EuclideanSpace, InnerSpace, Matrix, Matrix3, Matrix4, Point3, SquareMatrix, Vector3, ortho};
 
-use crate::rendering::{camera::OPENGL_TO_WGPU_MATRIX, mesh::Mesh, textures::tex_cords_to_lin, vertex::*};
+use crate::rendering::{camera::OPENGL_TO_WGPU_MATRIX, mesh::Mesh, vertex::*};
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
@@ -28,6 +28,17 @@ impl Sun {
         }
     }
 
+    /// Points the light along the given direction, with the given color
+    pub fn set_light(&mut self, direction: Vector3<f32>, color: [f32; 3]) {
+        self.direction = direction.into();
+        self.color = color;
+    }
+
+    /// Whether the sun is up, rather than shining up from under the world
+    pub fn is_above_horizon(&self) -> bool {
+        self.direction[1] < 0.0
+    }
+
     /// Updates the view projection matrix to match the current location of the
     /// light. Mostly generated by ChatGPT.
     pub fn update_view_proj(&mut self, center: Point3<f32>, scene_size: f32) {
@@ -48,7 +59,12 @@ impl Sun {
         self.view_proj = vp.into();
     }
 
-    pub fn sun_mesh(&mut self, center: Point3<f32>) -> Mesh {
+    pub fn view_projection_matrix(&self) -> Matrix4<f32> {
+        self.view_proj.into()
+    }
+
+    /// Builds a mesh of the sun, with the given texture array layer
+    pub fn sun_mesh(&mut self, center: Point3<f32>, texture: u32) -> Mesh {
         let mut mesh = Mesh::new();
 
         let dir = Vector3::from(self.direction).normalize();
@@ -58,154 +74,176 @@ impl Sun {
         let x_f = sun_pos.x - (SUN_SZ / 2.);
         let y_f = sun_pos.y - (SUN_SZ / 2.);
         let z_f = sun_pos.z - (SUN_SZ / 2.);
-        let t_x = 9;
-        let t_y = 0;
-        let block = [-1, -1, -1];
+        let block = NO_BLOCK;
 
         mesh.verticies = vec![
             Vertex { // BL
                 position: [x_f, y_f, z_f],
-                texture_cords: tex_cords_to_lin(t_x+1, t_y+1),
+                texture_cords: [1.0, 1.0],
                 normal: NORMAL_FRONT,
                 block,
+                texture,
             },
             Vertex { // TL
                 position: [x_f, y_f + SUN_SZ, z_f],
-                texture_cords: tex_cords_to_lin(t_x+1, t_y),
+                texture_cords: [1.0, 0.0],
                 normal: NORMAL_FRONT,
                 block,
+                texture,
             },
             Vertex { // BR
                 position: [x_f + SUN_SZ, y_f, z_f],
-                texture_cords: tex_cords_to_lin(t_x, t_y+1),
+                texture_cords: [0.0, 1.0],
                 normal: NORMAL_FRONT,
                 block,
+                texture,
             },
             Vertex { // TR
                 position: [x_f + SUN_SZ, y_f + SUN_SZ, z_f],
-                texture_cords: tex_cords_to_lin(t_x, t_y),
+                texture_cords: [0.0, 0.0],
                 normal: NORMAL_FRONT,
                 block,
+                texture,
             },
             Vertex { // BL
                 position: [x_f + SUN_SZ, y_f, z_f + SUN_SZ],
-                texture_cords: tex_cords_to_lin(t_x+1, t_y+1),
+                texture_cords: [1.0, 1.0],
                 normal: NORMAL_BACK,
                 block,
+                texture,
             },
             Vertex { // TL
                 position: [x_f + SUN_SZ, y_f + SUN_SZ, z_f + SUN_SZ],
-                texture_cords: tex_cords_to_lin(t_x+1, t_y),
+                texture_cords: [1.0, 0.0],
                 normal: NORMAL_BACK,
                 block,
+                texture,
             },
             Vertex { // BR
                 position: [x_f, y_f, z_f + SUN_SZ],
-                texture_cords: tex_cords_to_lin(t_x, t_y+1),
+                texture_cords: [0.0, 1.0],
                 normal: NORMAL_BACK,
                 block,
+                texture,
             },
             Vertex { // TR
                 position: [x_f, y_f + SUN_SZ, z_f + SUN_SZ],
-                texture_cords: tex_cords_to_lin(t_x, t_y),
+                texture_cords: [0.0, 0.0],
                 normal: NORMAL_BACK,
                 block,
+                texture,
             },
             Vertex { // BL
                 position: [x_f, y_f + SUN_SZ, z_f],
-                texture_cords: tex_cords_to_lin(t_x+1, t_y+1),
+                texture_cords: [1.0, 1.0],
                 normal: NORMAL_UP,
                 block,
+                texture,
             },
             Vertex { // TL
                 position: [x_f, y_f + SUN_SZ, z_f + SUN_SZ],
-                texture_cords: tex_cords_to_lin(t_x+1, t_y),
+                texture_cords: [1.0, 0.0],
                 normal: NORMAL_UP,
                 block,
+                texture,
             },
             Vertex { // BR
                 position: [x_f + SUN_SZ, y_f + SUN_SZ, z_f],
-                texture_cords: tex_cords_to_lin(t_x, t_y+1),
+                texture_cords: [0.0, 1.0],
                 normal: NORMAL_UP,
                 block,
+                texture,
             },
             Vertex { // TR
                 position: [x_f + SUN_SZ, y_f + SUN_SZ, z_f + SUN_SZ],
-                texture_cords: tex_cords_to_lin(t_x, t_y),
+                texture_cords: [0.0, 0.0],
                 normal: NORMAL_UP,
                 block,
+                texture,
             },
             Vertex { // BL
                 position: [x_f, y_f, z_f + SUN_SZ],
-                texture_cords: tex_cords_to_lin(t_x+1, t_y+1),
+                texture_cords: [1.0, 1.0],
                 normal: NORMAL_DOWN,
                 block,
+                texture,
             },
             Vertex { // TL
                 position: [x_f, y_f, z_f],
-                texture_cords: tex_cords_to_lin(t_x+1, t_y),
+                texture_cords: [1.0, 0.0],
                 normal: NORMAL_DOWN,
                 block,
+                texture,
             },
             Vertex { // BR
                 position: [x_f + SUN_SZ, y_f, z_f + SUN_SZ],
-                texture_cords: tex_cords_to_lin(t_x, t_y+1),
+                texture_cords: [0.0, 1.0],
                 normal: NORMAL_DOWN,
                 block,
+                texture,
             },
             Vertex { // TR
                 position: [x_f + SUN_SZ, y_f, z_f],
-                texture_cords: tex_cords_to_lin(t_x, t_y),
+                texture_cords: [0.0, 0.0],
                 normal: NORMAL_DOWN,
                 block,
+                texture,
             },
             Vertex { // BL
                 position: [x_f, y_f, z_f + SUN_SZ],
-                texture_cords: tex_cords_to_lin(t_x+1, t_y+1),
+                texture_cords: [1.0, 1.0],
                 normal: NORMAL_LEFT,
                 block,
+                texture,
             },
             Vertex { // TL
                 position: [x_f, y_f + SUN_SZ, z_f + SUN_SZ],
-                texture_cords: tex_cords_to_lin(t_x+1, t_y),
+                texture_cords: [1.0, 0.0],
                 normal: NORMAL_LEFT,
                 block,
+                texture,
             },
             Vertex { // BR
                 position: [x_f, y_f, z_f],
-                texture_cords: tex_cords_to_lin(t_x, t_y+1),
+                texture_cords: [0.0, 1.0],
                 normal: NORMAL_LEFT,
                 block,
+                texture,
             },
             Vertex { // TR
                 position: [x_f, y_f + SUN_SZ, z_f],
-                texture_cords: tex_cords_to_lin(t_x, t_y),
+                texture_cords: [0.0, 0.0],
                 normal: NORMAL_LEFT,
                 block,
+                texture,
             },
             Vertex { // BL
                 position: [x_f + SUN_SZ, y_f, z_f],
-                texture_cords: tex_cords_to_lin(t_x+1, t_y+1),
+                texture_cords: [1.0, 1.0],
                 normal: NORMAL_RIGHT,
                 block,
+                texture,
             },
             Vertex { // TL
                 position: [x_f + SUN_SZ, y_f + SUN_SZ, z_f],
-                texture_cords: tex_cords_to_lin(t_x+1, t_y),
+                texture_cords: [1.0, 0.0],
                 normal: NORMAL_RIGHT,
                 block,
+                texture,
             },
             Vertex { // BR
                 position: [x_f + SUN_SZ, y_f, z_f + SUN_SZ],
-                texture_cords: tex_cords_to_lin(t_x, t_y+1),
+                texture_cords: [0.0, 1.0],
                 normal: NORMAL_RIGHT,
                 block,
+                texture,
             },
             Vertex { // TR
                 position: [x_f + SUN_SZ, y_f + SUN_SZ, z_f + SUN_SZ],
-                texture_cords: tex_cords_to_lin(t_x, t_y),
+                texture_cords: [0.0, 0.0],
                 normal: NORMAL_RIGHT,
                 block,
+                texture,
             },
         ];
 
@@ -1,6 +1,7 @@
+use cgmath::Point3;
 use wgpu::{Buffer, Device, RenderPass, util::DeviceExt};
 
-use crate::rendering::vertex::Vertex;
+use crate::rendering::{frustum::Frustum, vertex::Vertex};
 
 #[derive(Debug)]
 /// A 3D mesh that can be rendered.
@@ -10,6 +11,12 @@ pub struct Mesh {
 
     vtx_buf: Option<Buffer>,
     idx_buf: Option<Buffer>,
+    /// Opposite corners of the box containing every vertex. Computed along
+    /// with the buffers, None if there are no verticies.
+    bounds: Option<(Point3<f32>, Point3<f32>)>,
+    /// Set if the mesh is hidden behind other geometry, so it can be skipped
+    /// in the main pass. Still casts shadows.
+    pub occluded: bool,
 }
 
 impl Mesh {
@@ -19,10 +26,22 @@ impl Mesh {
             indicies: vec![],
             vtx_buf: None,
             idx_buf: None,
+            bounds: None,
+            occluded: false,
         }
     }
 
     pub fn set_buffers(&mut self, device: &Device) {
+        self.bounds = self.verticies.iter()
+            .map(|v| Point3::from(v.position))
+            .fold(None, |bounds, p| match bounds {
+                None => Some((p, p)),
+                Some((min, max)) => Some((
+                    Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                    Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+                )),
+            });
+
         self.vtx_buf = Some(device.create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
                 label: Some("Mesh Vertex Buffer"),
@@ -44,6 +63,19 @@ impl Mesh {
         self.vtx_buf.is_some() && self.idx_buf.is_some()
     }
 
+    /// Whether any of the mesh could be visible in the given frustum. Meshes
+    /// without buffers set are assumed to be visible.
+    pub fn is_visible(&self, frustum: &Frustum) -> bool {
+        if !self.are_buffers_set() {
+            return true;
+        }
+
+        match self.bounds {
+            Some((min, max)) => frustum.intersects_box(min, max),
+            None => false,
+        }
+    }
+
     pub fn draw(&self, render_pass: &mut RenderPass) {
         let (v_buf, i_buf) = match (&self.vtx_buf, &self.idx_buf) {
             (Some(v), Some(i)) => (v, i),
@@ -4,11 +4,17 @@ mod render_state;
 pub mod vertex;
 /// Texture helpers
 pub mod textures;
+/// Loading block textures from resource packs
+pub mod resource_pack;
 /// Camera and helpers
 pub mod camera;
 /// Meshes
 pub mod mesh;
 /// Lights!
 pub mod light;
+/// View frustums, for culling
+pub mod frustum;
+/// Drawing low detail terrain past the loaded chunks
+pub mod far_terrain;
 
 pub use render_state::RenderState;
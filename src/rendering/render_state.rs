@@ -1,11 +1,28 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 use anyhow::Context;
-use cgmath::{Point3, Vector3, Zero};
-use log::{info};
-use wgpu::{Buffer, Device, Queue, RenderPassDescriptor, RenderPipeline, Sampler, Surface, SurfaceConfiguration, Texture, TextureView, util::DeviceExt, BindGroup};
+use cgmath::{Point2, Point3, Vector3};
+use log::{info, warn};
+use wgpu::{AdapterInfo, Buffer, Device, Queue, RenderPassDescriptor, RenderPipeline, Sampler, Surface, SurfaceConfiguration, Texture, TextureView, util::DeviceExt, BindGroup, BindGroupLayout};
 use winit::window::Window;
 
-use crate::{rendering::{camera::{Camera, CameraUniform}, light::Sun, mesh::Mesh, textures::{DEPTH_FORMAT, DepthTexture, create_diffue_bind_group}, vertex::Vertex}, settings, ui::UI, world::ThreeDimPos};
+use crate::{rendering::{camera::{Camera, CameraUniform}, far_terrain::FarTerrainUniform, frustum::Frustum, light::Sun, mesh::Mesh, resource_pack::ResourcePack, textures::{DEPTH_FORMAT, DepthTexture, create_diffuse_bind_group, create_diffuse_bind_group_layout}, vertex::{NO_BLOCK, Vertex}}, settings, ui::UI, user_settings::UserSettings, world::{ThreeDimPos, block::{BlockSide, BlockType}}};
+
+/// Like `wgpu::include_wgsl!`, but joins several files together, for sharing
+/// functions between shaders
+macro_rules! include_wgsl_concat {
+    ($path:literal $(, $extra:literal)*) => {
+        wgpu::ShaderModuleDescriptor {
+            label: Some($path),
+            source: wgpu::ShaderSource::Wgsl(concat!(
+                include_str!($path),
+                $(include_str!($extra),)*
+            ).into()),
+        }
+    };
+}
+
+/// Color of the light at night, which daylight brightens to white
+const NIGHT_LIGHT: [f32; 3] = [0.3, 0.35, 0.5];
 
 /// Stores state of the window and rendering
 pub struct RenderState {
@@ -14,6 +31,8 @@ pub struct RenderState {
 
     /// The part of the window that we draw to
     surface: Surface<'static>,
+    /// Which GPU we're drawing with
+    adapter_info: AdapterInfo,
     /// Handle to the GPU
     device: Device,
     /// GPU command queue
@@ -23,6 +42,7 @@ pub struct RenderState {
     surface_configured: bool,
 
     render_pipeline: RenderPipeline,
+    sky_pipeline: RenderPipeline,
     diffuse_bind_group: BindGroup,
     camera_bind_group: BindGroup,
     sun_bind_group: BindGroup,
@@ -41,13 +61,28 @@ pub struct RenderState {
     shadow_texture: Texture,
     shadow_view: TextureView,
     shadow_sampler: Sampler,
+    shadow_bind_group_layout: BindGroupLayout,
     shadow_pipeline: RenderPipeline,
 
     highlight_buffer: Buffer,
+
+    far_pipeline: RenderPipeline,
+    far_uniform: FarTerrainUniform,
+    far_buffer: Buffer,
+    far_bind_group: BindGroup,
+
+    /// Where block textures come from
+    resource_pack: ResourcePack,
+    /// The resource pack setting that `resource_pack` was loaded for
+    pack_setting: String,
+    texture_bind_group_layout: BindGroupLayout,
+    anisotropic: bool,
+    /// When the resource pack was last checked for changes
+    last_pack_check: Instant,
 }
 
 impl RenderState {
-    pub async fn new(window: Arc<Window>) -> anyhow::Result<Self> {
+    pub async fn new(window: Arc<Window>, user_settings: &UserSettings) -> anyhow::Result<Self> {
         // --- INITIAL SETUP --- 
         let size = window.inner_size();
 
@@ -64,7 +99,8 @@ impl RenderState {
             force_fallback_adapter: false,
         }).await.context("Couldn't find suitable GPU")?;
 
-        info!("Using GPU \"{}\"", adapter.get_info().name);
+        let adapter_info = adapter.get_info();
+        info!("Using GPU \"{}\"", adapter_info.name);
 
         let mut limits = wgpu::Limits::default();
         limits.max_bind_groups = 5;
@@ -80,6 +116,9 @@ impl RenderState {
             trace: wgpu::Trace::Off,
         }).await.context("Failed to get rendering device & queue")?;
 
+        let anisotropic = adapter.get_downlevel_capabilities().flags
+            .contains(wgpu::DownlevelFlags::ANISOTROPIC_FILTERING);
+
         let surface_caps = surface.get_capabilities(&adapter);
         let surface_format = surface_caps.formats.iter()
             .find(|x| x.is_srgb())
@@ -102,12 +141,16 @@ impl RenderState {
             desired_maximum_frame_latency: 2, // default
         };
 
-        let (texture_bind_group_layout, diffuse_bind_group) =
-            create_diffue_bind_group(&device, &queue);
+        let resource_pack = load_resource_pack(&user_settings.resource_pack)?;
+
+        let texture_bind_group_layout = create_diffuse_bind_group_layout(&device);
+        let diffuse_bind_group = create_diffuse_bind_group(
+            &device, &queue, &texture_bind_group_layout, &resource_pack, anisotropic,
+        );
 
         // --- CAMERA ---
-        let camera = Camera::new(config.width as f32, config.height as f32);
-        let mut camera_uniform = CameraUniform::new();
+        let camera = Camera::new(config.width as f32, config.height as f32, user_settings);
+        let mut camera_uniform = CameraUniform::new(user_settings);
         camera_uniform.update_view_proj(&camera);
 
         let camera_buffer = device.create_buffer_init(
@@ -122,7 +165,8 @@ impl RenderState {
             entries: &[
                 wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
+                    // Fragment shaders need the camera position for fog
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -180,24 +224,8 @@ impl RenderState {
         });
 
         // --- SHADOW MAPPING ---
-        let shadow_texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("Shadow Map"),
-            size: wgpu::Extent3d {
-                width: settings::SHADOW_RES,
-                height: settings::SHADOW_RES,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: DEPTH_FORMAT,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT |
-                   wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[],
-        });
-
-        let shadow_view =
-            shadow_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let (shadow_texture, shadow_view) =
+            create_shadow_map(&device, user_settings.shadow_res);
 
         let shadow_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             label: Some("Shadow Sampler"),
@@ -237,26 +265,15 @@ impl RenderState {
             },
         );
 
-        let shadow_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Shadow Bind Group"),
-            layout: &shadow_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&shadow_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&shadow_sampler),
-                },
-            ],
-        });
+        let shadow_bind_group = create_shadow_bind_group(
+            &device, &shadow_bind_group_layout, &shadow_view, &shadow_sampler,
+        );
 
         // --- HIGHLIGHTED BLOCK ---
         let highlight_buffer = device.create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
                 label: Some("Highlighted Block"),
-                contents: bytemuck::cast_slice(&[[-1, -1, -1]]),
+                contents: bytemuck::cast_slice(&[NO_BLOCK]),
                 usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             }
         );
@@ -285,10 +302,47 @@ impl RenderState {
             label: None,
         });
 
+        // --- FAR TERRAIN ---
+        let far_uniform = FarTerrainUniform::new();
+        let far_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Far Terrain"),
+                contents: bytemuck::cast_slice(&[far_uniform]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+
+        let far_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: None,
+            });
+
+        let far_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &far_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: far_buffer.as_entire_binding(),
+            }],
+            label: None,
+        });
 
         // --- MAIN PIPELINE ---
         let shader =
-            device.create_shader_module(wgpu::include_wgsl!("../shaders/main.wgsl"));
+            device.create_shader_module(include_wgsl_concat!(
+                "../shaders/main.wgsl",
+                "../shaders/sky_common.wgsl",
+                "../shaders/texels.wgsl"
+            ));
 
         let pipeline_layout = device.create_pipeline_layout(
             &wgpu::PipelineLayoutDescriptor {
@@ -353,6 +407,126 @@ impl RenderState {
             cache: None, // 6.
         });
 
+        // --- SKY PIPELINE ---
+        let sky_shader =
+            device.create_shader_module(include_wgsl_concat!(
+                "../shaders/sky.wgsl",
+                "../shaders/sky_common.wgsl"
+            ));
+
+        // Shares the start of the main layout, so the same bind groups work
+        let sky_pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Sky Pipeline Layout"),
+                bind_group_layouts: &[
+                    &texture_bind_group_layout,
+                    &camera_bind_group_layout,
+                    &sun_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            }
+        );
+
+        let sky_pipeline = device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Sky Pipeline"),
+                layout: Some(&sky_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &sky_shader,
+                    entry_point: Some("vs_sky"),
+                    buffers: &[],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &sky_shader,
+                    entry_point: Some("fs_sky"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: config.format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    cull_mode: None,
+                    ..Default::default()
+                },
+                // Behind everything, so it doesn't touch the depth buffer
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DEPTH_FORMAT,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::Always,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            }
+        );
+
+        // --- FAR TERRAIN PIPELINE ---
+        let far_shader =
+            device.create_shader_module(include_wgsl_concat!(
+                "../shaders/far_terrain.wgsl",
+                "../shaders/sky_common.wgsl",
+                "../shaders/texels.wgsl"
+            ));
+
+        let far_pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Far Terrain Pipeline Layout"),
+                bind_group_layouts: &[
+                    &texture_bind_group_layout,
+                    &camera_bind_group_layout,
+                    &sun_bind_group_layout,
+                    &far_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            }
+        );
+
+        let far_pipeline = device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Far Terrain Pipeline"),
+                layout: Some(&far_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &far_shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[Vertex::desc_layout()],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &far_shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: config.format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DEPTH_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            }
+        );
+
         // --- SHADOW PIPELINE ---
         let shadow_shader =
             device.create_shader_module(wgpu::include_wgsl!("../shaders/shadow.wgsl"));
@@ -362,6 +536,7 @@ impl RenderState {
                 label: Some("Shadow Pipeline Layout"),
                 bind_group_layouts: &[
                     &sun_bind_group_layout,
+                    &texture_bind_group_layout,
                 ],
                 push_constant_ranges: &[],
             }
@@ -377,7 +552,13 @@ impl RenderState {
                     buffers: &[Vertex::desc_layout()],
                     compilation_options: wgpu::PipelineCompilationOptions::default(),
                 },
-                fragment: None, // depth-only
+                // Depth only, but sprites need their see-through parts cut out
+                fragment: Some(wgpu::FragmentState {
+                    module: &shadow_shader,
+                    entry_point: Some("fs_shadow"),
+                    targets: &[],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
                 primitive: wgpu::PrimitiveState {
                     topology: wgpu::PrimitiveTopology::TriangleList,
                     front_face: wgpu::FrontFace::Ccw,
@@ -414,6 +595,7 @@ impl RenderState {
             surface_configured: false,
 
             render_pipeline,
+            sky_pipeline,
             diffuse_bind_group,
             camera_bind_group,
             sun_bind_group,
@@ -430,30 +612,142 @@ impl RenderState {
             shadow_texture,
             shadow_view,
             shadow_sampler,
+            shadow_bind_group_layout,
             shadow_pipeline,
 
             highlight_buffer,
+
+            far_pipeline,
+            far_uniform,
+            far_buffer,
+            far_bind_group,
+
+            resource_pack,
+            pack_setting: user_settings.resource_pack.clone(),
+            texture_bind_group_layout,
+            anisotropic,
+            last_pack_check: Instant::now(),
+            adapter_info,
         })
     }
 
-    pub fn update(&mut self, highlight: Option<ThreeDimPos>) {
+    /// Reloads the resource pack if any of its files changed, checking every
+    /// `PACK_POLL_INTERVAL` seconds. Returns whether block faces now use
+    /// different textures, in which case meshes need to be rebuilt.
+    pub fn reload_resource_pack(&mut self) -> bool {
+        if self.last_pack_check.elapsed().as_secs_f32() < settings::PACK_POLL_INTERVAL {
+            return false;
+        }
+        self.last_pack_check = Instant::now();
+
+        if !self.resource_pack.check_changed() {
+            return false;
+        }
+
+        let pack = match self.resource_pack.reload() {
+            Ok(pack) => pack,
+            Err(e) => {
+                log::error!("Couldn't reload resource pack, keeping the old one: {:#}", e);
+                return false;
+            },
+        };
+        info!("Reloaded resource pack \"{}\"", pack.name);
+        self.use_resource_pack(pack)
+    }
+
+    /// Switches to drawing with the given resource pack. Returns whether
+    /// block faces now use different textures, in which case meshes need to
+    /// be rebuilt.
+    fn use_resource_pack(&mut self, pack: ResourcePack) -> bool {
+        self.diffuse_bind_group = create_diffuse_bind_group(
+            &self.device,
+            &self.queue,
+            &self.texture_bind_group_layout,
+            &pack,
+            self.anisotropic,
+        );
+        let changed = !pack.same_block_faces(&self.resource_pack);
+        self.resource_pack = pack;
+        changed
+    }
+
+    /// Picks up changes to the camera, fog, sky color, shadow map resolution
+    /// and resource pack. Returns whether block faces now use different
+    /// textures, in which case meshes need to be rebuilt.
+    pub fn apply_settings(&mut self, user_settings: &UserSettings) -> bool {
+        let mut textures_changed = false;
+        if user_settings.resource_pack != self.pack_setting {
+            self.pack_setting = user_settings.resource_pack.clone();
+            match load_resource_pack(&self.pack_setting) {
+                Ok(pack) => textures_changed = self.use_resource_pack(pack),
+                Err(e) => log::error!("{:#}", e),
+            }
+        }
+
+        self.camera.apply_settings(user_settings);
+        self.camera_uniform.apply_settings(user_settings);
+
+        if self.shadow_texture.width() != user_settings.shadow_res {
+            let (texture, view) = create_shadow_map(&self.device, user_settings.shadow_res);
+            self.shadow_bind_group = create_shadow_bind_group(
+                &self.device, &self.shadow_bind_group_layout, &view, &self.shadow_sampler,
+            );
+            self.shadow_texture = texture;
+            self.shadow_view = view;
+        }
+
+        textures_changed
+    }
+
+    pub fn adapter_info(&self) -> &AdapterInfo {
+        &self.adapter_info
+    }
+
+    /// The texture array layer of the texture with the given name, per the
+    /// applied resource pack
+    pub fn texture(&self, name: &str) -> u32 {
+        self.resource_pack.texture(name)
+    }
+
+    /// The texture array layer for the given face of the given block, per the
+    /// applied resource pack. None if the face isn't drawn.
+    pub fn block_texture(&self, block: BlockType, side: BlockSide) -> Option<u32> {
+        self.resource_pack.block_texture(block, side)
+    }
+
+    /// Updates uniforms for the next frame. `loaded_circle` is the area
+    /// covered by loaded chunks, where far terrain isn't drawn. `daylight` is
+    /// how light it is, from 0 at night to 1 during the day.
+    pub fn update(&mut self,
+        highlight: Option<ThreeDimPos>,
+        loaded_circle: (Point2<f32>, f32),
+        sun_direction: Vector3<f32>,
+        daylight: f32,
+    ) {
         self.depth_texture = DepthTexture::new(&self.device, &self.config, "depth_texture");
 
         self.camera_uniform.update_view_proj(&self.camera);
         self.queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[self.camera_uniform]));
 
+        let light_color = [0, 1, 2]
+            .map(|i| NIGHT_LIGHT[i] + (1.0 - NIGHT_LIGHT[i]) * daylight);
+        self.sun.set_light(sun_direction, light_color);
+
         let center = self.camera.get_position();
         self.sun.update_view_proj(center, settings::SHADOW_RENDER_SZ);
         self.queue.write_buffer(&self.sun_buffer, 0, bytemuck::cast_slice(&[self.sun]));
 
         let highlight_data = match highlight {
-            Some((h_x, h_y, h_z)) => [h_x, h_y as i32, h_z],
-            None => [-1, -1, -1],
+            Some((h_x, h_y, h_z)) => [h_x, h_y, h_z],
+            None => NO_BLOCK,
         };
         self.queue.write_buffer(
             &self.highlight_buffer, 0,
             bytemuck::cast_slice(&[highlight_data])
         );
+
+        self.far_uniform.update(loaded_circle);
+        self.queue.write_buffer(&self.far_buffer, 0, bytemuck::cast_slice(&[self.far_uniform]));
     }
 
     pub fn resize(&mut self, w: u32, h: u32) {
@@ -467,6 +761,7 @@ impl RenderState {
 
     pub fn render(&mut self, 
         meshes: &mut [&mut Mesh],
+        far_meshes: &mut [&mut Mesh],
         ui: &mut UI,
         center: Point3<f32>,
     ) ->
@@ -502,12 +797,17 @@ impl RenderState {
 
         shadow_pass.set_pipeline(&self.shadow_pipeline);
         shadow_pass.set_bind_group(0, &self.sun_bind_group, &[]);
+        shadow_pass.set_bind_group(1, &self.diffuse_bind_group, &[]);
 
+        let sun_frustum = Frustum::from_matrix(self.sun.view_projection_matrix());
         for mesh in &mut *meshes {
             if !mesh.are_buffers_set() {
                 mesh.set_buffers(&self.device);
             }
-            mesh.draw(&mut shadow_pass);
+
+            if mesh.is_visible(&sun_frustum) {
+                mesh.draw(&mut shadow_pass);
+            }
         }
         drop(shadow_pass);
         self.queue.submit(std::iter::once(shadow_encoder.finish()));
@@ -534,7 +834,8 @@ impl RenderState {
                 // What to do with the colors
                 ops: wgpu::Operations {
                     // What to do with previous frame's colors (clear & replace)
-                    load: wgpu::LoadOp::Clear(crate::settings::SKY_COLOR),
+                    // Fully covered by the sky anyways
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
                     // What to do with these frame's colors
                     store: wgpu::StoreOp::Store,
                 }
@@ -551,22 +852,59 @@ impl RenderState {
             timestamp_writes: None,
         });
 
-        render_pass.set_pipeline(&self.render_pipeline);
         render_pass.set_bind_group(0, &self.diffuse_bind_group, &[]);
         render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
         render_pass.set_bind_group(2, &self.sun_bind_group, &[]);
         render_pass.set_bind_group(3, &self.shadow_bind_group, &[]);
         render_pass.set_bind_group(4, &self.highlight_bind_group, &[]);
+
+        render_pass.set_pipeline(&self.sky_pipeline);
+        render_pass.draw(0..3, 0..1);
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        let camera_frustum = Frustum::from_matrix(self.camera.view_projection_matrix());
+        let mut drawn = 0;
+        let mut culled = 0;
+        let mut occluded = 0;
+        let mut vertices = 0;
+        let mut indices = 0;
         for mesh in meshes {
+            if mesh.occluded {
+                occluded += 1;
+            } else if mesh.is_visible(&camera_frustum) {
+                mesh.draw(&mut render_pass);
+                drawn += 1;
+                vertices += mesh.verticies.len();
+                indices += mesh.indicies.len();
+            } else {
+                culled += 1;
+            }
+        }
+
+        // Drawn after the chunks so most of it fails the depth test early
+        render_pass.set_pipeline(&self.far_pipeline);
+        render_pass.set_bind_group(3, &self.far_bind_group, &[]);
+        for mesh in far_meshes {
             if !mesh.are_buffers_set() {
                 mesh.set_buffers(&self.device);
             }
-            mesh.draw(&mut render_pass);
+
+            if mesh.is_visible(&camera_frustum) {
+                mesh.draw(&mut render_pass);
+                vertices += mesh.verticies.len();
+                indices += mesh.indicies.len();
+            }
         }
+        ui.state.update_render_stats(drawn, culled, occluded, vertices, indices);
 
-        let mut sun_mesh = self.sun.sun_mesh(center);
-        sun_mesh.set_buffers(&self.device);
-        sun_mesh.draw(&mut render_pass);
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(3, &self.shadow_bind_group, &[]);
+        render_pass.set_bind_group(4, &self.highlight_bind_group, &[]);
+        if self.sun.is_above_horizon() {
+            let mut sun_mesh = self.sun.sun_mesh(center, self.resource_pack.texture("sun"));
+            sun_mesh.set_buffers(&self.device);
+            sun_mesh.draw(&mut render_pass);
+        }
 
         drop(render_pass); // Release borrow on the encoder
 
@@ -585,3 +923,60 @@ impl RenderState {
         Ok(())
     }
 }
+
+/// Makes a square shadow map with the given width and height
+fn create_shadow_map(device: &Device, res: u32) -> (Texture, TextureView) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Shadow Map"),
+        size: wgpu::Extent3d {
+            width: res,
+            height: res,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT |
+               wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    (texture, view)
+}
+
+fn create_shadow_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    view: &TextureView,
+    sampler: &Sampler,
+) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Shadow Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    })
+}
+
+/// Loads the resource pack with the given directory name, falling back to the
+/// default one if it can't be
+fn load_resource_pack(name: &str) -> anyhow::Result<ResourcePack> {
+    let pack = ResourcePack::load(name)
+        .or_else(|e| {
+            warn!("Couldn't load resource pack \"{}\", using default: {:#}", name, e);
+            ResourcePack::load(settings::DEFAULT_RESOURCE_PACK)
+        })
+        .context("Couldn't load default resource pack")?;
+    info!("Using resource pack \"{}\"", pack.name);
+    Ok(pack)
+}
@@ -0,0 +1,237 @@
+use std::{collections::HashMap, path::{Path, PathBuf}, time::SystemTime};
+use anyhow::Context;
+use image::{RgbaImage, imageops};
+use serde::Deserialize;
+use crate::{settings::RESOURCE_PACK_DIR, world::block::{BlockSide, BlockType, FaceTextures}};
+
+/// Texture array layer of the placeholder used for anything missing
+pub const MISSING_TEXTURE: u32 = 0;
+
+/// A pack's `pack.toml`
+#[derive(Deserialize)]
+struct Manifest {
+    /// Shown instead of the directory name
+    name: Option<String>,
+    /// Textures swapped out for each block, by block name
+    #[serde(default)]
+    blocks: HashMap<String, FaceTextures>,
+}
+
+/// A directory of block textures (one PNG each, in `textures/`) along with a
+/// `pack.toml` manifest, which can swap out the textures the block registry
+/// puts on each block face
+pub struct ResourcePack {
+    pub name: String,
+    dir: PathBuf,
+    /// Square images, all the same size, one per texture array layer
+    pub layers: Vec<RgbaImage>,
+    /// Layer of each texture, by name
+    textures: HashMap<String, u32>,
+    /// Layer of the texture on each block face
+    block_faces: HashMap<(BlockType, BlockSide), u32>,
+    /// The latest modification of any file in the pack, for hot reloading
+    modified: Option<SystemTime>,
+}
+
+impl ResourcePack {
+    /// Loads the pack with the given directory name from `RESOURCE_PACK_DIR`
+    pub fn load(name: &str) -> anyhow::Result<Self> {
+        let dir = Path::new(RESOURCE_PACK_DIR).join(name);
+        let modified = last_modified(&dir);
+        let manifest_path = dir.join("pack.toml");
+        let manifest = std::fs::read_to_string(&manifest_path)
+            .with_context(|| format!("Couldn't read {}", manifest_path.display()))?;
+        let manifest: Manifest = toml::from_str(&manifest)
+            .with_context(|| format!("Couldn't parse {}", manifest_path.display()))?;
+
+        let mut images = vec![];
+        let texture_dir = dir.join("textures");
+        let entries = std::fs::read_dir(&texture_dir)
+            .with_context(|| format!("Couldn't read {}", texture_dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|e| e != "png") {
+                continue;
+            }
+
+            let texture_name = path.file_stem().unwrap().to_string_lossy().to_string();
+            let image = image::open(&path)
+                .with_context(|| format!("Couldn't load {}", path.display()))?
+                .to_rgba8();
+            images.push((texture_name, image));
+        }
+        // So layers come out the same every time
+        images.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let size = images.iter()
+            .map(|(_, image)| image.width().max(image.height()))
+            .max()
+            .unwrap_or(1)
+            .next_power_of_two();
+
+        let mut layers = vec![missing_texture(size)];
+        let mut textures = HashMap::new();
+        for (texture_name, image) in images {
+            let image = if image.dimensions() == (size, size) {
+                image
+            } else {
+                imageops::resize(&image, size, size, imageops::FilterType::Nearest)
+            };
+
+            textures.insert(texture_name, layers.len() as u32);
+            layers.push(image);
+        }
+
+        let mut overrides = HashMap::new();
+        for (block_name, textures) in &manifest.blocks {
+            match BlockType::from_name(block_name) {
+                Some(block) => {
+                    overrides.insert(block, textures);
+                },
+                None => log::warn!(
+                    "Resource pack \"{}\" has textures for unknown block \"{}\"", name, block_name
+                ),
+            }
+        }
+
+        let mut block_faces = HashMap::new();
+        for block in BlockType::all() {
+            for side in BlockSide::ALL {
+                // Blocks without a texture in the registry aren't drawn at all
+                let Some(default) = block.texture_name(side) else {
+                    continue;
+                };
+
+                let texture_name = overrides.get(&block)
+                    .and_then(|t| t.get(side))
+                    .unwrap_or(default);
+
+                match textures.get(texture_name) {
+                    Some(layer) => {
+                        block_faces.insert((block, side), *layer);
+                    },
+                    None => log::warn!(
+                        "Resource pack \"{}\" is missing texture \"{}\"", name, texture_name
+                    ),
+                }
+            }
+        }
+
+        let display_name = manifest.name.unwrap_or_else(|| name.to_string());
+
+        Ok(Self {
+            name: display_name,
+            dir,
+            layers,
+            textures,
+            block_faces,
+            modified,
+        })
+    }
+
+    /// Loads this pack again from disk
+    pub fn reload(&self) -> anyhow::Result<Self> {
+        let name = self.dir.file_name().unwrap().to_string_lossy();
+        Self::load(&name)
+    }
+
+    /// The texture array layer of the texture with the given name
+    pub fn texture(&self, name: &str) -> u32 {
+        self.textures.get(name).copied().unwrap_or(MISSING_TEXTURE)
+    }
+
+    /// Width and height of every layer, in pixels
+    pub fn size(&self) -> u32 {
+        self.layers[0].width()
+    }
+
+    /// Whether any of the pack's files changed since it was loaded or this
+    /// was last called
+    pub fn check_changed(&mut self) -> bool {
+        let modified = last_modified(&self.dir);
+        let changed = modified != self.modified;
+        self.modified = modified;
+        changed
+    }
+
+    /// The texture array layer for the given face of the given block. None
+    /// if the block registry doesn't draw that face.
+    pub fn block_texture(&self, block: BlockType, side: BlockSide) -> Option<u32> {
+        block.texture_name(side)?;
+        Some(self.block_faces.get(&(block, side)).copied().unwrap_or(MISSING_TEXTURE))
+    }
+
+    /// Whether this pack puts the same textures on every block face as the
+    /// given one, so meshes built with either look the same
+    pub fn same_block_faces(&self, other: &ResourcePack) -> bool {
+        self.block_faces == other.block_faces
+    }
+}
+
+/// The directory names of every resource pack in `RESOURCE_PACK_DIR`, sorted
+pub fn list_packs() -> Vec<String> {
+    let entries = match std::fs::read_dir(RESOURCE_PACK_DIR) {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::error!("Couldn't list resource packs in {}: {}", RESOURCE_PACK_DIR, e);
+            return vec![];
+        },
+    };
+
+    let mut packs: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join("pack.toml").is_file())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    packs.sort();
+    packs
+}
+
+/// A magenta and black checkerboard
+fn missing_texture(size: u32) -> RgbaImage {
+    RgbaImage::from_fn(size, size, |x, y| {
+        let half = (size / 2).max(1);
+        if (x / half + y / half).is_multiple_of(2) {
+            image::Rgba([255, 0, 255, 255])
+        } else {
+            image::Rgba([0, 0, 0, 255])
+        }
+    })
+}
+
+/// The latest modification time of the given directory or anything inside of
+/// it. Directories' own times cover files being added or removed.
+fn last_modified(path: &Path) -> Option<SystemTime> {
+    let mut latest = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+
+    if let Ok(entries) = std::fs::read_dir(path) {
+        for entry in entries.flatten() {
+            latest = latest.max(last_modified(&entry.path()));
+        }
+    }
+
+    latest
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{settings::BLOCK_REGISTRY, world::block::load_registry};
+    use super::*;
+
+    #[test]
+    fn default_pack_covers_every_block() {
+        load_registry(BLOCK_REGISTRY).unwrap();
+        let pack = ResourcePack::load("default").unwrap();
+        for block in BlockType::all() {
+            if block == BlockType::AIR {
+                continue;
+            }
+
+            for side in BlockSide::ALL {
+                let layer = pack.block_faces.get(&(block, side));
+                assert!(layer.is_some_and(|l| *l != MISSING_TEXTURE), "{:?} {:?}", block, side);
+            }
+        }
+        assert_ne!(pack.texture("sun"), MISSING_TEXTURE);
+    }
+}
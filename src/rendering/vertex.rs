@@ -6,8 +6,18 @@ pub struct Vertex {
     pub texture_cords: [f32; 2],
     pub normal: [f32; 3],
     pub block: [i32; 3],
+    /// Layer of the block texture array to sample
+    pub texture: u32,
 }
 
+/// `Vertex::block` value for verticies that aren't part of a block
+pub const NO_BLOCK: [i32; 3] = [i32::MIN, i32::MIN, i32::MIN];
+/// `Vertex::block` value for verticies that are lit like blocks without being
+/// part of one, like mobs. Never matches the highlighted block.
+pub const ENTITY_BLOCK: [i32; 3] = [i32::MIN, 0, i32::MIN];
+/// Like `ENTITY_BLOCK`, but for entities that were just hurt and flash red
+pub const HURT_ENTITY_BLOCK: [i32; 3] = [i32::MIN, 1, i32::MIN];
+
 pub const NORMAL_UP: [f32; 3] = [0.0, 1.0, 0.0];
 pub const NORMAL_DOWN: [f32; 3] = [0.0, -1.0, 0.0];
 pub const NORMAL_LEFT: [f32; 3] = [-1.0, 0.0, 0.0];
@@ -43,6 +53,11 @@ impl Vertex {
                     shader_location: 3,
                     format: wgpu::VertexFormat::Sint32x3,
                 },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 11]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Uint32,
+                },
             ]
         }
     }
@@ -1,34 +1,80 @@
 use cgmath::{Point3, Vector3};
 use crate::physics::AABB;
 
-/// The color of the skybox
-pub const SKY_COLOR: wgpu::Color = wgpu::Color {
-    r: 0.0,
-    g: 1.0,
-    b: 1.0,
-    a: 1.0,
-};
-
-pub const SHADOW_RES: u32 = 8192;
+/// Shadow map resolution until it's changed in the settings file
+pub const DEFAULT_SHADOW_RES: u32 = 8192;
 pub const SHADOW_RENDER_SZ: f32 = 300.0;
 
-pub const CAMERA_SPEED: f32 = 0.50;
-pub const MOUSE_SENSITIVITY: f32 = 0.007;
-pub const FOV: f32 = 70.0;
+/// Mouse sensitivity until it's changed in the settings file
+pub const DEFAULT_MOUSE_SENSITIVITY: f32 = 0.007;
+/// Vertical field of view in degrees until it's changed in the settings file
+pub const DEFAULT_FOV: f32 = 70.0;
 pub const MAX_HIGHLIGHT_DIST: usize = 8;
 
 pub const CHUNK_SIZE: usize = 16;
-/// The number of chunks to render away from the player
-pub const RENDER_DIST: usize = 8;
-
-pub const SEED: u32 = 613;
+/// The lowest block Y coordinate in the world. Must be a multiple of
+/// `SECTION_HEIGHT`.
+pub const WORLD_MIN_Y: i32 = -64;
+/// One past the highest block Y coordinate in the world. Must be a multiple
+/// of `SECTION_HEIGHT`.
+pub const WORLD_MAX_Y: i32 = 320;
+/// How far from the origin coordinates typed into commands can be. Positions
+/// are f32s, which get too coarse to move around in much further out.
+pub const WORLD_BORDER: i32 = 1_000_000;
+/// The height of the slices chunks are split into for meshing and culling
+pub const SECTION_HEIGHT: usize = 16;
+/// The number of chunks to render away from the player, until it's changed
+/// in the settings file
+pub const DEFAULT_RENDER_DIST: usize = 8;
+/// How many blocks away from the player coarse far terrain is drawn out to
+pub const FAR_TERRAIN_DIST: usize = 2048;
+/// The width of the square tiles far terrain is built in, in blocks
+pub const FAR_TILE_SIZE: usize = 128;
+/// Blocks between heightmap samples in the nearest far terrain tiles.
+/// Doubles with every doubling in distance.
+pub const FAR_BASE_STEP: usize = 4;
+/// The most far terrain tiles (re)built in one frame
+pub const FAR_TILES_PER_FRAME: usize = 8;
+/// Whether to draw far terrain at all
+pub const FAR_TERRAIN: bool = true;
+/// How far away anything is drawn with the given render distance, in blocks
+pub const fn view_dist(render_dist: usize) -> usize {
+    let chunk_dist = render_dist * CHUNK_SIZE;
+    if FAR_TERRAIN && FAR_TERRAIN_DIST > chunk_dist {
+        FAR_TERRAIN_DIST
+    } else {
+        chunk_dist
+    }
+}
+/// Fraction of the distance fog ends at that it starts fading in at. Fog
+/// ends at the edge of the loaded chunks for chunks, and at
+/// `FAR_TERRAIN_DIST` for far terrain.
+pub const FOG_START: f32 = 0.5;
 
 /// Ticks per second
 pub const PHYSICS_TICK_RATE: f32 = 60.0;
+/// The most ticks run in one frame before giving up on catching up
+pub const MAX_TICKS_PER_FRAME: usize = 10;
 pub const MOVE_SPEED: f32 = 7.0;
 pub const JUMP_SPEED: f32 = 9.0;
 pub const GRAVITY_A: Vector3<f32> = Vector3::new(0.0, -30.0, 0.0);
 pub const SPRINT_MULTIPLIER: f32 = 2.0;
+/// Upwards acceleration applied to a fully submerged entity. Slightly weaker
+/// than gravity so things sink slowly.
+pub const BUOYANCY_A: f32 = 27.0;
+/// Fraction of velocity lost per second while fully submerged
+pub const WATER_DRAG: f32 = 3.0;
+/// Vertical speed when holding space underwater
+pub const SWIM_SPEED: f32 = 4.0;
+pub const WATER_MOVE_MULTIPLIER: f32 = 0.5;
+pub const SNEAK_MULTIPLIER: f32 = 0.3;
+/// Horizontal speed multiplier while flying
+pub const FLY_MULTIPLIER: f32 = 1.5;
+pub const FLY_VERTICAL_SPEED: f32 = 8.0;
+/// Max seconds between two presses of space for them to count as a double tap
+pub const DOUBLE_TAP_WINDOW: f32 = 0.3;
+/// The tallest ledge the player walks up without jumping
+pub const PLAYER_STEP_HEIGHT: f32 = 1.0;
 
 pub const PLAYER_AABB: AABB = AABB::new(
     0.6,
@@ -37,4 +83,110 @@ pub const PLAYER_AABB: AABB = AABB::new(
     Point3::new(0.3, 1.6, 0.3),
 ).unwrap();
 
-pub const FONT_SZ: f64 = 18.0;
+/// The player's bounding box while sneaking. Shorter, with the eyes lowered to
+/// match.
+pub const SNEAK_AABB: AABB = AABB::new(
+    0.6,
+    1.5,
+    0.6,
+    Point3::new(0.3, 1.27, 0.3),
+).unwrap();
+
+pub const MAX_HEALTH: f32 = 20.0;
+/// Slots in the player's inventory, which survival players carry blocks in
+pub const PLAYER_SLOTS: usize = 36;
+/// How far below the bottom of the world the player has to fall before
+/// taking void damage
+pub const VOID_DEPTH: f32 = 64.0;
+/// Health lost per second in the void
+pub const VOID_DAMAGE: f32 = 8.0;
+/// Seconds after being hurt before anything can be hurt again
+pub const HURT_COOLDOWN: f32 = 0.5;
+/// Seconds things flash red for after being hurt. They can't steer for that
+/// long either, so knockback carries them.
+pub const HURT_FLASH_TIME: f32 = 0.3;
+/// Horizontal speed things get knocked back at when hit, in m/s
+pub const KNOCKBACK_SPEED: f32 = 6.0;
+/// Upwards speed things get knocked back at when hit, in m/s
+pub const KNOCKBACK_UP: f32 = 5.0;
+/// Food the player has when full
+pub const MAX_HUNGER: f32 = 20.0;
+/// Saturation the player starts out with
+pub const START_SATURATION: f32 = 5.0;
+/// Exhaustion it takes to use up a point of saturation or food
+pub const EXHAUSTION_PER_POINT: f32 = 4.0;
+/// Exhaustion from sprinting a block
+pub const SPRINT_EXHAUSTION: f32 = 0.1;
+/// Exhaustion from jumping
+pub const JUMP_EXHAUSTION: f32 = 0.05;
+/// Exhaustion from jumping while sprinting
+pub const SPRINT_JUMP_EXHAUSTION: f32 = 0.2;
+/// Exhaustion from healing a point of health
+pub const REGEN_EXHAUSTION: f32 = 6.0;
+/// Food needed for health to regenerate
+pub const REGEN_HUNGER: f32 = 18.0;
+/// The player can only sprint with more food than this
+pub const SPRINT_HUNGER: f32 = 6.0;
+/// Seconds between healing a point when fed, or losing one when starving
+pub const HUNGER_INTERVAL: f32 = 4.0;
+/// Seconds right click has to be held to eat something
+pub const EAT_TIME: f32 = 1.6;
+
+/// How far away the player can hit things from
+pub const ATTACK_REACH: f32 = 4.0;
+/// Seconds between the player's attacks
+pub const ATTACK_COOLDOWN: f32 = 0.4;
+/// Damage done by the player's attacks
+pub const ATTACK_DAMAGE: f32 = 4.0;
+
+/// Seconds in a full day and night
+pub const DAY_LENGTH: f32 = 600.0;
+/// The time of day new games start at, as in `WorldTime::time_of_day`
+pub const START_TIME: f32 = 0.15;
+
+/// Mobs spawn at least this far from the player, in blocks
+pub const MOB_SPAWN_MIN_DIST: f32 = 24.0;
+/// Mobs spawn at most this far from the player, in blocks
+pub const MOB_SPAWN_MAX_DIST: f32 = 64.0;
+/// Chance of trying to spawn a mob each tick
+pub const MOB_SPAWN_CHANCE: f64 = 0.05;
+/// Mobs further than this from the player are removed. Must be within the
+/// loaded chunks.
+pub const MOB_DESPAWN_DIST: f32 = 96.0;
+/// The most passive mobs around at once
+pub const MAX_PASSIVE_MOBS: usize = 12;
+/// The most hostile mobs around at once
+pub const MAX_HOSTILE_MOBS: usize = 16;
+/// How close the player has to get for hostile mobs to chase them
+pub const MOB_CHASE_DIST: f32 = 24.0;
+
+/// The most blocks the `/fill` command changes at once
+pub const MAX_FILL_BLOCKS: usize = 32768;
+/// Lines kept in the console's log
+pub const CONSOLE_LOG_LINES: usize = 200;
+
+/// The data file listing every block type
+pub const BLOCK_REGISTRY: &str = "data/blocks.toml";
+
+/// Where resource packs are looked for
+pub const RESOURCE_PACK_DIR: &str = "resourcepacks";
+/// The resource pack used unless another is picked in the settings, and
+/// fallen back on if that one can't be loaded
+pub const DEFAULT_RESOURCE_PACK: &str = "default";
+/// Seconds between checks for changes to the resource pack's files
+pub const PACK_POLL_INTERVAL: f32 = 1.0;
+
+/// Where worlds are saved, each in its own directory. Edited chunks are
+/// loaded back from there instead of being generated again.
+pub const SAVES_DIR: &str = "saves";
+/// The seed of worlds saved before seeds could be picked
+pub const LEGACY_SEED: u32 = 613;
+
+/// The file the player's settings are saved to
+pub const USER_SETTINGS_FILE: &str = "settings.toml";
+
+/// UI font size until it's changed in the settings file
+pub const DEFAULT_FONT_SZ: f32 = 18.0;
+/// Color of the sky straight up during the day until it's changed in the
+/// settings file
+pub const DEFAULT_SKY_COLOR: [f32; 3] = [0.12, 0.32, 0.8];
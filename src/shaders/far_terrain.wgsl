@@ -0,0 +1,86 @@
+// See rendering::camera
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    inv_view_proj: mat4x4<f32>,
+    position: vec3<f32>,
+    fog_start: f32,
+    fog_end: f32,
+    far_fog_start: f32,
+    far_fog_end: f32,
+    sky_color: vec3<f32>,
+};
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+// See rendering::light
+struct LightUniform {
+    view_proj: mat4x4<f32>,
+    direction: vec3<f32>,
+    color: vec3<f32>,
+};
+@group(2) @binding(0)
+var<uniform> sun: LightUniform;
+
+// See rendering::far_terrain
+struct FarTerrainUniform {
+    hole_center: vec2<f32>,
+    hole_radius: f32,
+};
+@group(3) @binding(0)
+var<uniform> far: FarTerrainUniform;
+
+// See rendering::vertex::Vertex;
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) texture_cords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(3) block: vec3<i32>,
+    @location(4) texture: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) texture_cords: vec2<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) world_position: vec3<f32>,
+    @location(3) @interpolate(flat) texture: u32,
+};
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.texture_cords = in.texture_cords;
+    out.clip_position = camera.view_proj * vec4<f32>(in.position, 1.0);
+    out.world_normal = in.normal;
+    out.world_position = in.position;
+    out.texture = in.texture;
+
+    return out;
+}
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d_array<f32>;
+@group(0) @binding(1)
+var s_diffuse: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let base_color = sample_texels(t_diffuse, s_diffuse, in.texture_cords, in.texture);
+
+    // Loaded chunks are drawn here instead
+    if distance(in.world_position.xz, far.hole_center) < far.hole_radius {
+        discard;
+    }
+
+    // Lit like blocks, minus shadows, which don't reach this far
+    let ambient_color = sun.color * 0.15;
+    let sun_dir = normalize(-sun.direction);
+    let diffuse_color = sun.color * max(dot(normalize(in.world_normal), sun_dir), 0.0);
+    let lit_color = (ambient_color + diffuse_color) * base_color.xyz;
+
+    let fog = fog_amount(in.world_position, camera.far_fog_start, camera.far_fog_end);
+    let fogged_color = mix(lit_color, fog_color(in.world_position), fog);
+
+    return vec4<f32>(fogged_color, 1.0);
+}
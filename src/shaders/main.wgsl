@@ -1,6 +1,13 @@
 // See rendering::camera
 struct CameraUniform {
     view_proj: mat4x4<f32>,
+    inv_view_proj: mat4x4<f32>,
+    position: vec3<f32>,
+    fog_start: f32,
+    fog_end: f32,
+    far_fog_start: f32,
+    far_fog_end: f32,
+    sky_color: vec3<f32>,
 };
 @group(1) @binding(0)
 var<uniform> camera: CameraUniform;
@@ -20,6 +27,7 @@ struct VertexInput {
     @location(1) texture_cords: vec2<f32>,
     @location(2) normal: vec3<f32>,
     @location(3) block: vec3<i32>,
+    @location(4) texture: u32,
 };
 
 struct VertexOutput {
@@ -29,6 +37,7 @@ struct VertexOutput {
     @location(2) world_position: vec3<f32>,
     @location(3) light_position: vec4<f32>,
     @location(4) block: vec3<i32>,
+    @location(5) @interpolate(flat) texture: u32,
 };
 
 @vertex
@@ -41,12 +50,13 @@ fn vs_main(in: VertexInput) -> VertexOutput {
     out.world_position = in.position;
     out.light_position = sun.view_proj * vec4<f32>(in.position, 1.0);
     out.block = in.block;
+    out.texture = in.texture;
 
     return out;
 }
 
 @group(0) @binding(0)
-var t_diffuse: texture_2d<f32>;
+var t_diffuse: texture_2d_array<f32>;
 @group(0) @binding(1)
 var s_diffuse: sampler;
 
@@ -60,6 +70,12 @@ var<uniform> highlighted_block: vec3<i32>;
 
 const SHADOW_BIAS: f32 = 1.00;
 
+// See rendering::vertex::NO_BLOCK
+const NO_BLOCK: i32 = -2147483647 - 1;
+// See rendering::vertex::HURT_ENTITY_BLOCK
+const HURT_ENTITY_BLOCK: vec3<i32> = vec3<i32>(NO_BLOCK, 1, NO_BLOCK);
+const HURT_TINT: vec3<f32> = vec3<f32>(1.0, 0.1, 0.1);
+
 fn compute_shadow(light_pos: vec4<f32>) -> f32 {
     // 1. If behind the light, don't shadow
     if (light_pos.w <= 0.0) {
@@ -88,9 +104,14 @@ fn compute_shadow(light_pos: vec4<f32>) -> f32 {
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let base_color = textureSample(t_diffuse, s_diffuse, in.texture_cords);
+    let base_color = sample_texels(t_diffuse, s_diffuse, in.texture_cords, in.texture);
+
+    // Cut out the see-through parts of sprites
+    if base_color.a < 0.5 {
+        discard;
+    }
 
-    if in.block.y < 0 {
+    if in.block.y == NO_BLOCK {
         // Invalid block coord, thus this isnt a block and doesn't need to
         // be lit normally.
         return base_color;
@@ -116,5 +137,12 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         final_color = 0.85 * final_color + 0.15 * white;
     }
 
+    if all(in.block == HURT_ENTITY_BLOCK) {
+        final_color = mix(final_color, HURT_TINT, 0.5);
+    }
+
+    let fog = fog_amount(in.world_position, camera.fog_start, camera.fog_end);
+    final_color = mix(final_color, fog_color(in.world_position), fog);
+
     return vec4<f32>(final_color, base_color.a);
 }
@@ -7,14 +7,22 @@ struct LightUniform {
 @group(0) @binding(0)
 var<uniform> sun: LightUniform;
 
+@group(1) @binding(0)
+var t_diffuse: texture_2d_array<f32>;
+@group(1) @binding(1)
+var s_diffuse: sampler;
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) texture_cords: vec2<f32>,
     @location(2) normal: vec3<f32>,
+    @location(4) texture: u32,
 };
 
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
+    @location(0) texture_cords: vec2<f32>,
+    @location(1) @interpolate(flat) texture: u32,
 };
 
 @vertex
@@ -23,6 +31,17 @@ fn vs_shadow(in: VertexInput) -> VertexOutput {
 
     let world_pos = vec4<f32>(in.position, 1.0);
     out.position = sun.view_proj * world_pos;
+    out.texture_cords = in.texture_cords;
+    out.texture = in.texture;
 
     return out;
 }
+
+// Only there so see-through parts of sprites don't cast shadows
+@fragment
+fn fs_shadow(in: VertexOutput) {
+    let alpha = textureSampleLevel(t_diffuse, s_diffuse, in.texture_cords, in.texture, 0.0).a;
+    if alpha < 0.5 {
+        discard;
+    }
+}
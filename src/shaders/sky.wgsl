@@ -0,0 +1,48 @@
+// See rendering::camera
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    inv_view_proj: mat4x4<f32>,
+    position: vec3<f32>,
+    fog_start: f32,
+    fog_end: f32,
+    far_fog_start: f32,
+    far_fog_end: f32,
+    sky_color: vec3<f32>,
+};
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+// See rendering::light
+struct LightUniform {
+    view_proj: mat4x4<f32>,
+    direction: vec3<f32>,
+    color: vec3<f32>,
+};
+@group(2) @binding(0)
+var<uniform> sun: LightUniform;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+};
+
+// Draws a single triangle covering the whole screen
+@vertex
+fn vs_sky(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var out: VertexOutput;
+
+    let ndc = vec2<f32>(f32(index / 2u) * 4.0 - 1.0, f32(index % 2u) * 4.0 - 1.0);
+    out.clip_position = vec4<f32>(ndc, 1.0, 1.0);
+    out.ndc = ndc;
+
+    return out;
+}
+
+@fragment
+fn fs_sky(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Unproject a point on the far plane to get the view ray
+    let far_point = camera.inv_view_proj * vec4<f32>(in.ndc, 1.0, 1.0);
+    let dir = normalize(far_point.xyz / far_point.w - camera.position);
+
+    return vec4<f32>(sky_color(dir), 1.0);
+}
@@ -0,0 +1,54 @@
+// Sky colors, shared between shaders. Expects `sun` and `camera` to be bound.
+
+const NIGHT_ZENITH: vec3<f32> = vec3<f32>(0.01, 0.01, 0.04);
+const NIGHT_HORIZON: vec3<f32> = vec3<f32>(0.03, 0.04, 0.08);
+// How much paler than straight up the sky is at the horizon during the day
+const DAY_HORIZON_PALENESS: f32 = 0.55;
+const SUNSET_GLOW: vec3<f32> = vec3<f32>(1.0, 0.45, 0.15);
+
+// The color of the sky looking in the given (normalized) direction
+fn sky_color(dir: vec3<f32>) -> vec3<f32> {
+    let sun_dir = normalize(-sun.direction);
+
+    // How far up the sun is, from 0 at night to 1 during the day
+    let day = clamp(sun_dir.y * 3.0 + 0.3, 0.0, 1.0);
+    let day_horizon = mix(camera.sky_color, vec3<f32>(1.0), DAY_HORIZON_PALENESS);
+    let zenith = mix(NIGHT_ZENITH, camera.sky_color, day);
+    let horizon = mix(NIGHT_HORIZON, day_horizon, day);
+
+    // Thicker atmosphere towards the horizon scatters the light more
+    let height = clamp(dir.y, 0.0, 1.0);
+    var color = mix(horizon, zenith, pow(height, 0.5));
+
+    // Light scattered around the sun, strongest when it is low
+    let towards_sun = max(dot(dir, sun_dir), 0.0);
+    let low_sun = 1.0 - clamp(abs(sun_dir.y) * 2.0, 0.0, 1.0);
+    color += SUNSET_GLOW * pow(towards_sun, 8.0) * low_sun * (1.0 - height);
+    color += sun.color * pow(towards_sun, 64.0) * 0.3 * day;
+
+    // Darken below the horizon
+    if dir.y < 0.0 {
+        color *= 1.0 - 0.5 * clamp(-dir.y * 4.0, 0.0, 1.0);
+    }
+
+    return color;
+}
+
+// The sky color at the horizon behind the given point in the world, so
+// distant things fade into it
+fn fog_color(world_position: vec3<f32>) -> vec3<f32> {
+    let offset = world_position - camera.position;
+    let flat_offset = vec3<f32>(offset.x, 0.0, offset.z);
+    if length(flat_offset) < 1.0e-4 {
+        return sky_color(vec3<f32>(1.0, 0.0, 0.0));
+    }
+
+    return sky_color(normalize(flat_offset));
+}
+
+// How fogged something at the given point is, from 0 to 1, with fog fading in
+// between the given distances
+fn fog_amount(world_position: vec3<f32>, start: f32, end: f32) -> f32 {
+    let dist = distance(world_position, camera.position);
+    return smoothstep(start, end, dist);
+}
@@ -0,0 +1,20 @@
+// Block texture sampling, shared between shaders
+
+// Samples a block texture with hard texel edges up close, like nearest
+// filtering, while still filtering (and picking mips) smoothly far away
+fn sample_texels(
+    t: texture_2d_array<f32>,
+    s: sampler,
+    uv: vec2<f32>,
+    layer: u32,
+) -> vec4<f32> {
+    // Resource packs decide how many texels there are
+    let texture_px = vec2<f32>(textureDimensions(t));
+    let texels = uv * texture_px;
+    let width = max(fwidth(texels), vec2<f32>(1.0e-5));
+    let edge = clamp((fract(texels) - 0.5) / width, vec2<f32>(-0.5), vec2<f32>(0.5));
+    let crisp_uv = (floor(texels) + 0.5 + edge) / texture_px;
+
+    // The nudged UVs jump at texel edges, so pick mips using the real ones
+    return textureSampleGrad(t, s, crisp_uv, layer, dpdx(uv), dpdy(uv));
+}
@@ -0,0 +1,441 @@
+use anyhow::{Context, bail};
+use cgmath::Point3;
+use crate::{settings::{MAX_FILL_BLOCKS, WORLD_BORDER}, world::{GameWorld, ThreeDimPos, block::BlockType, block_entity::MAX_STACK, player::GameMode, state::Block}};
+
+/// Named times of day for `/time set`, as in `WorldTime::time_of_day`
+const TIMES: [(&str, f32); 6] = [
+    ("sunrise", 0.0),
+    ("day", 0.1),
+    ("noon", 0.25),
+    ("sunset", 0.5),
+    ("night", 0.6),
+    ("midnight", 0.75),
+];
+
+/// What an argument to a command can be, which decides how it's completed
+#[derive(Copy, Clone, Debug)]
+enum ArgKind {
+    /// A whole number, or `~` for the player's own coordinate plus an
+    /// optional offset (like `~-3`)
+    Coordinate,
+    Block,
+    GameMode,
+    /// One of `TIMES`, or a fraction of a day
+    TimeOfDay,
+    /// One of the given words
+    Choice(&'static [&'static str]),
+}
+
+#[derive(Copy, Clone)]
+struct Arg {
+    name: &'static str,
+    kind: ArgKind,
+}
+
+impl Arg {
+    const fn new(name: &'static str, kind: ArgKind) -> Self {
+        Self { name, kind }
+    }
+
+    /// Everything this argument could be. Numbers are left out.
+    fn options(&self) -> Vec<String> {
+        match self.kind {
+            ArgKind::Coordinate => vec!["~".to_string()],
+            ArgKind::Block => BlockType::all().map(|b| b.name().to_string()).collect(),
+            ArgKind::GameMode => GameMode::ALL.iter().map(|m| game_mode_name(*m)).collect(),
+            ArgKind::TimeOfDay => TIMES.iter().map(|(name, _)| name.to_string()).collect(),
+            ArgKind::Choice(words) => words.iter().map(|w| w.to_string()).collect(),
+        }
+    }
+}
+
+/// Something that can be run from the console
+pub struct Command {
+    pub name: &'static str,
+    args: &'static [Arg],
+    pub help: &'static str,
+    /// Carries the command out, returning what to show in the console
+    run: fn(&mut GameWorld, &mut Args) -> anyhow::Result<String>,
+}
+
+impl Command {
+    /// How the command is typed, like `/setblock <x> <y> <z> <block>`
+    pub fn usage(&self) -> String {
+        let mut usage = format!("/{}", self.name);
+        for arg in self.args {
+            match arg.kind {
+                ArgKind::Choice([word]) => usage += &format!(" {}", word),
+                _ => usage += &format!(" <{}>", arg.name),
+            }
+        }
+        usage
+    }
+}
+
+const POS: [Arg; 3] = [
+    Arg::new("x", ArgKind::Coordinate),
+    Arg::new("y", ArgKind::Coordinate),
+    Arg::new("z", ArgKind::Coordinate),
+];
+
+/// Every command, by name
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "help",
+        args: &[],
+        help: "Lists every command",
+        run: help,
+    },
+    Command {
+        name: "tp",
+        args: &POS,
+        help: "Teleports to a block",
+        run: tp,
+    },
+    Command {
+        name: "give",
+        args: &[Arg::new("block", ArgKind::Block)],
+        help: "Holds a block, to place it, with a stack of it in survival",
+        run: give,
+    },
+    Command {
+        name: "time",
+        args: &[Arg::new("set", ArgKind::Choice(&["set"])), Arg::new("time", ArgKind::TimeOfDay)],
+        help: "Sets the time of day, by name or as a fraction of a day from sunrise",
+        run: time,
+    },
+    Command {
+        name: "seed",
+        args: &[],
+        help: "Shows the seed the world is generated from",
+        run: seed,
+    },
+    Command {
+        name: "setblock",
+        args: &[POS[0], POS[1], POS[2], Arg::new("block", ArgKind::Block)],
+        help: "Replaces a block",
+        run: setblock,
+    },
+    Command {
+        name: "fill",
+        args: &[
+            Arg::new("x1", ArgKind::Coordinate),
+            Arg::new("y1", ArgKind::Coordinate),
+            Arg::new("z1", ArgKind::Coordinate),
+            Arg::new("x2", ArgKind::Coordinate),
+            Arg::new("y2", ArgKind::Coordinate),
+            Arg::new("z2", ArgKind::Coordinate),
+            Arg::new("block", ArgKind::Block),
+        ],
+        help: "Replaces every block in a box between two corners",
+        run: fill,
+    },
+    Command {
+        name: "gamemode",
+        args: &[Arg::new("mode", ArgKind::GameMode)],
+        help: "Switches game mode",
+        run: gamemode,
+    },
+];
+
+/// The arguments given to a command, read in order
+pub struct Args<'a> {
+    command: &'static Command,
+    words: &'a [&'a str],
+    read: usize,
+}
+
+impl<'a> Args<'a> {
+    /// The next argument, and what it was typed as
+    fn next(&mut self) -> anyhow::Result<(&'static Arg, &'a str)> {
+        let arg = &self.command.args[self.read];
+        let Some(word) = self.words.get(self.read) else {
+            bail!("Missing <{}>, usage: {}", arg.name, self.command.usage());
+        };
+        self.read += 1;
+        Ok((arg, word))
+    }
+
+    /// Reads a coordinate, relative to the given one if it starts with `~`.
+    /// Errors if it's past `WORLD_BORDER`.
+    fn coordinate(&mut self, own: i32) -> anyhow::Result<i32> {
+        let (arg, word) = self.next()?;
+        let parsed = match word.strip_prefix('~') {
+            Some("") => Ok(own as i64),
+            Some(offset) => offset.parse::<i64>().map(|o| (own as i64).saturating_add(o)),
+            None => word.parse(),
+        };
+        let coordinate = parsed.with_context(|| format!(
+            "<{}> should be a whole number or ~, not \"{}\"", arg.name, word,
+        ))?;
+
+        let border = WORLD_BORDER as i64;
+        if !(-border..=border).contains(&coordinate) {
+            bail!("<{}> is {}, past the world border at {} either way", arg.name, coordinate, border);
+        }
+        Ok(coordinate as i32)
+    }
+
+    /// Reads three coordinates, relative to the given position
+    fn pos(&mut self, own: ThreeDimPos) -> anyhow::Result<ThreeDimPos> {
+        Ok((self.coordinate(own.0)?, self.coordinate(own.1)?, self.coordinate(own.2)?))
+    }
+
+    fn block(&mut self) -> anyhow::Result<BlockType> {
+        let (_, word) = self.next()?;
+        BlockType::from_name(word).with_context(|| format!("There's no block called \"{}\"", word))
+    }
+
+    fn game_mode(&mut self) -> anyhow::Result<GameMode> {
+        let (_, word) = self.next()?;
+        GameMode::ALL.into_iter()
+            .find(|m| game_mode_name(*m) == word)
+            .with_context(|| format!("There's no game mode called \"{}\"", word))
+    }
+
+    /// Reads a time of day, as in `WorldTime::time_of_day`
+    fn time_of_day(&mut self) -> anyhow::Result<f32> {
+        let (arg, word) = self.next()?;
+        if let Some((_, time)) = TIMES.iter().find(|(name, _)| *name == word) {
+            return Ok(*time);
+        }
+        word.parse::<f32>()
+            .ok()
+            .filter(|time| time.is_finite())
+            .with_context(|| format!(
+                "<{}> should be a time of day or a number, not \"{}\"", arg.name, word,
+            ))
+    }
+
+    /// Reads one of the words the argument can be
+    fn choice(&mut self) -> anyhow::Result<&'a str> {
+        let (arg, word) = self.next()?;
+        let ArgKind::Choice(words) = arg.kind else {
+            panic!("<{}> isn't a choice", arg.name);
+        };
+        if !words.contains(&word) {
+            bail!("<{}> should be one of {}, not \"{}\"", arg.name, words.join(", "), word);
+        }
+        Ok(word)
+    }
+}
+
+/// Runs a line typed into the console, returning what to show in response
+pub fn run(world: &mut GameWorld, line: &str) -> anyhow::Result<String> {
+    let words: Vec<&str> = line.trim_start_matches('/').split_whitespace().collect();
+    let Some((name, words)) = words.split_first() else {
+        bail!("Type a command, or /help to list them");
+    };
+    let command = COMMANDS.iter()
+        .find(|c| c.name == *name)
+        .with_context(|| format!("Unknown command /{}, try /help", name))?;
+    if words.len() > command.args.len() {
+        bail!("Too many arguments, usage: {}", command.usage());
+    }
+
+    (command.run)(world, &mut Args { command, words, read: 0 })
+}
+
+/// The result of completing a partly typed line
+#[derive(Debug, PartialEq)]
+pub struct Completion {
+    /// The line, with the last word filled in as far as all of its options
+    /// agree
+    pub line: String,
+    /// What the last word could be, if there's more than one option
+    pub options: Vec<String>,
+}
+
+/// Completes the last word of a partly typed line, from the names of commands
+/// or the options for the argument being typed
+pub fn complete(line: &str) -> Completion {
+    let words: Vec<&str> = line.trim_start_matches('/').split_whitespace().collect();
+    // A trailing space means a new word has been started
+    let typing = if line.ends_with(char::is_whitespace) || words.is_empty() {
+        ""
+    } else {
+        words[words.len() - 1]
+    };
+    let index = if typing.is_empty() { words.len() } else { words.len() - 1 };
+
+    let options = if index == 0 {
+        COMMANDS.iter().map(|c| c.name.to_string()).collect()
+    } else {
+        COMMANDS.iter()
+            .find(|c| c.name == words[0])
+            .and_then(|c| c.args.get(index - 1))
+            .map(|arg| arg.options())
+            .unwrap_or_default()
+    };
+    let options: Vec<String> = options.into_iter()
+        .filter(|o| o.starts_with(typing))
+        .collect();
+
+    let start = &line[..line.len() - typing.len()];
+    let start = if index == 0 && !start.starts_with('/') { format!("/{}", start) } else { start.to_string() };
+    match options.as_slice() {
+        [] => Completion { line: line.to_string(), options },
+        [only] => Completion { line: format!("{}{} ", start, only), options: Vec::new() },
+        [first, rest @ ..] => {
+            let shared = rest.iter().fold(first.len(), |len, o| {
+                first.bytes().zip(o.bytes()).take(len).take_while(|(a, b)| a == b).count()
+            });
+            Completion { line: format!("{}{}", start, &first[..shared]), options }
+        },
+    }
+}
+
+/// How a game mode is typed
+fn game_mode_name(mode: GameMode) -> String {
+    format!("{:?}", mode).to_lowercase()
+}
+
+/// The block the player's feet are in, which `~` is relative to
+fn player_feet(world: &GameWorld) -> ThreeDimPos {
+    let (min, _) = world.player().entity.bounds();
+    (min.x.floor() as i32, min.y.floor() as i32, min.z.floor() as i32)
+}
+
+fn help(_world: &mut GameWorld, _args: &mut Args) -> anyhow::Result<String> {
+    Ok(COMMANDS.iter()
+        .map(|c| format!("{} - {}", c.usage(), c.help))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+fn tp(world: &mut GameWorld, args: &mut Args) -> anyhow::Result<String> {
+    let (x, y, z) = args.pos(player_feet(world))?;
+    world.teleport_player(Point3::new(x as f32 + 0.5, y as f32, z as f32 + 0.5));
+    Ok(format!("Teleported to {:?}", (x, y, z)))
+}
+
+fn give(world: &mut GameWorld, args: &mut Args) -> anyhow::Result<String> {
+    let block = args.block()?;
+    let player = world.player_mut();
+    player.held = block;
+    if player.game_mode() == GameMode::Survival {
+        let given = (0..MAX_STACK).take_while(|_| player.inventory.add(block)).count();
+        return Ok(format!("Now holding {}, and given {} of it", block.name(), given));
+    }
+    Ok(format!("Now holding {}", block.name()))
+}
+
+fn time(world: &mut GameWorld, args: &mut Args) -> anyhow::Result<String> {
+    args.choice()?;
+    let time = args.time_of_day()?;
+    world.time_mut().set_time_of_day(time);
+    Ok(format!("Set the time of day to {:.2}", world.time().time_of_day()))
+}
+
+fn seed(world: &mut GameWorld, _args: &mut Args) -> anyhow::Result<String> {
+    Ok(format!("Seed: {}", world.seed()))
+}
+
+fn setblock(world: &mut GameWorld, args: &mut Args) -> anyhow::Result<String> {
+    let pos = args.pos(player_feet(world))?;
+    let block = args.block()?;
+    if world.edit_blocks(&[pos], Block::new(block))? > 0 {
+        bail!("{:?} has items in it, take them out first", pos);
+    }
+    Ok(format!("Set {:?} to {}", pos, block.name()))
+}
+
+fn fill(world: &mut GameWorld, args: &mut Args) -> anyhow::Result<String> {
+    let feet = player_feet(world);
+    let a = args.pos(feet)?;
+    let b = args.pos(feet)?;
+    let block = args.block()?;
+
+    let min = (a.0.min(b.0), a.1.min(b.1), a.2.min(b.2));
+    let max = (a.0.max(b.0), a.1.max(b.1), a.2.max(b.2));
+    // Corners on either side of the world can be too far apart for a usize
+    let size = [max.0 - min.0, max.1 - min.1, max.2 - min.2]
+        .iter()
+        .fold(1usize, |size, d| size.saturating_mul(*d as usize + 1));
+    if size > MAX_FILL_BLOCKS {
+        bail!("That's {} blocks, but at most {} can be filled at once", size, MAX_FILL_BLOCKS);
+    }
+
+    let positions: Vec<ThreeDimPos> = (min.0..=max.0)
+        .flat_map(|x| (min.1..=max.1).map(move |y| (x, y)))
+        .flat_map(|(x, y)| (min.2..=max.2).map(move |z| (x, y, z)))
+        .collect();
+    let kept = world.edit_blocks(&positions, Block::new(block))?;
+    if kept > 0 {
+        return Ok(format!(
+            "Filled {} blocks with {}, leaving {} containers that have items in them",
+            size - kept, block.name(), kept,
+        ));
+    }
+    Ok(format!("Filled {} blocks with {}", size, block.name()))
+}
+
+fn gamemode(world: &mut GameWorld, args: &mut Args) -> anyhow::Result<String> {
+    let mode = args.game_mode()?;
+    world.player_mut().set_game_mode(mode);
+    Ok(format!("Switched to {} mode", game_mode_name(mode)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{settings::BLOCK_REGISTRY, world::block::load_registry};
+    use super::*;
+
+    fn completed(line: &str) -> String {
+        complete(line).line
+    }
+
+    #[test]
+    fn completes_commands_and_arguments() {
+        load_registry(BLOCK_REGISTRY).unwrap();
+
+        assert_eq!(completed("se"), "/se");
+        assert_eq!(complete("/se").options, ["seed", "setblock"]);
+        assert_eq!(completed("/setb"), "/setblock ");
+        assert_eq!(completed("/setblock "), "/setblock ~ ");
+        assert_eq!(completed("/setblock 1 ~ 3 sto"), "/setblock 1 ~ 3 stone");
+        assert_eq!(completed("/setblock 1 ~ 3 ch"), "/setblock 1 ~ 3 chest ");
+        assert_eq!(completed("/gamemode cr"), "/gamemode creative ");
+        assert_eq!(completed("/time "), "/time set ");
+        assert_eq!(completed("/time set mid"), "/time set midnight ");
+
+        // Nothing to complete
+        assert_eq!(complete("/seed x"), Completion { line: "/seed x".into(), options: vec![] });
+        assert_eq!(completed("/nope "), "/nope ");
+    }
+
+    #[test]
+    fn coordinates_stay_inside_the_border() {
+        let command = COMMANDS.iter().find(|c| c.name == "tp").unwrap();
+        let words = ["~-3", "-1000000", "~2147483647"];
+        let mut args = Args { command, words: &words, read: 0 };
+
+        assert_eq!(args.coordinate(10).unwrap(), 7);
+        assert_eq!(args.coordinate(10).unwrap(), -WORLD_BORDER);
+        assert!(args.coordinate(i32::MAX).is_err());
+    }
+
+    #[test]
+    fn times_of_day_are_finite() {
+        let command = COMMANDS.iter().find(|c| c.name == "time").unwrap();
+        let time_of_day = |word| {
+            let words = ["set", word];
+            let mut args = Args { command, words: &words, read: 1 };
+            args.time_of_day()
+        };
+
+        assert_eq!(time_of_day("noon").unwrap(), 0.25);
+        assert_eq!(time_of_day("0.3").unwrap(), 0.3);
+        assert!(time_of_day("nan").is_err());
+        assert!(time_of_day("inf").is_err());
+    }
+
+    #[test]
+    fn usage_lists_arguments() {
+        let usage: Vec<String> = COMMANDS.iter()
+            .filter(|c| c.name == "time" || c.name == "setblock")
+            .map(|c| c.usage())
+            .collect();
+        assert_eq!(usage, ["/time set <time>", "/setblock <x> <y> <z> <block>"]);
+    }
+}
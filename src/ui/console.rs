@@ -0,0 +1,154 @@
+use std::collections::VecDeque;
+use imgui::{InputTextCallback, InputTextCallbackHandler, TextCallbackData};
+use crate::{settings::CONSOLE_LOG_LINES, ui::commands};
+
+const CONSOLE_SIZE: [f32; 2] = [700.0, 300.0];
+const COMMAND_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+const ERROR_COLOR: [f32; 4] = [1.0, 0.4, 0.4, 1.0];
+
+/// A line in the console's log
+enum LogLine {
+    /// A command that was entered
+    Command(String),
+    Output(String),
+    Error(String),
+}
+
+/// A window to type commands into, with a log of what they did
+pub struct Console {
+    open: bool,
+    input: String,
+    log: VecDeque<LogLine>,
+    /// The last line entered, waiting to be run
+    submitted: Option<String>,
+    /// Whether the input box should take the keyboard on the next frame
+    focus_input: bool,
+    /// Whether the log should scroll down to its newest line on the next
+    /// frame
+    scroll_down: bool,
+}
+
+/// Tab completes the input box, keeping what it could complete to when
+/// there's more than one option
+struct Completer<'a> {
+    options: &'a mut Vec<String>,
+}
+
+impl InputTextCallbackHandler for Completer<'_> {
+    fn on_completion(&mut self, mut data: TextCallbackData) {
+        let completion = commands::complete(data.str());
+        data.clear();
+        data.push_str(&completion.line);
+        *self.options = completion.options;
+    }
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            input: String::new(),
+            log: VecDeque::new(),
+            submitted: None,
+            focus_input: false,
+            scroll_down: false,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Opens the console, with the given text already typed in
+    pub fn open(&mut self, input: &str) {
+        self.open = true;
+        self.input = input.to_string();
+        self.focus_input = true;
+        self.scroll_down = true;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    /// The line entered since this was last called, if any
+    pub fn take_submitted(&mut self) -> Option<String> {
+        self.submitted.take()
+    }
+
+    /// Shows what running a command did in the log
+    pub fn print(&mut self, result: anyhow::Result<String>) {
+        match result {
+            Ok(output) => {
+                for line in output.lines() {
+                    self.push(LogLine::Output(line.to_string()));
+                }
+            },
+            Err(e) => self.push(LogLine::Error(format!("{:#}", e))),
+        }
+    }
+
+    fn push(&mut self, line: LogLine) {
+        if self.log.len() >= CONSOLE_LOG_LINES {
+            self.log.pop_front();
+        }
+        self.log.push_back(line);
+        self.scroll_down = true;
+    }
+
+    pub(super) fn generate(&mut self, gui: &mut imgui::Ui) {
+        if !self.open {
+            return;
+        }
+
+        let display_size = gui.io().display_size;
+        let mut options = Vec::new();
+        gui.window("Console")
+            .position([10.0, display_size[1] - 10.0], imgui::Condition::FirstUseEver)
+            .position_pivot([0.0, 1.0])
+            .size(CONSOLE_SIZE, imgui::Condition::FirstUseEver)
+            .collapsible(false)
+            .build(|| {
+                // Leave room for the input box below the log
+                let log_height = -gui.frame_height_with_spacing();
+                gui.child_window("Log").size([0.0, log_height]).build(|| {
+                    for line in &self.log {
+                        match line {
+                            LogLine::Command(text) => gui.text_colored(COMMAND_COLOR, text),
+                            LogLine::Output(text) => gui.text_wrapped(text),
+                            LogLine::Error(text) => gui.text_colored(ERROR_COLOR, text),
+                        }
+                    }
+                    if self.scroll_down {
+                        gui.set_scroll_here_y_with_ratio(1.0);
+                        self.scroll_down = false;
+                    }
+                });
+
+                if self.focus_input {
+                    gui.set_keyboard_focus_here();
+                    self.focus_input = false;
+                }
+                let entered = gui.input_text("##input", &mut self.input)
+                    .hint("/help to list commands, tab to complete")
+                    .enter_returns_true(true)
+                    .callback(InputTextCallback::COMPLETION, Completer { options: &mut options })
+                    .build();
+
+                if entered {
+                    // Keep typing after entering a command
+                    self.focus_input = true;
+                    if !self.input.trim().is_empty() {
+                        self.submitted = Some(std::mem::take(&mut self.input));
+                    }
+                }
+            });
+
+        if let Some(line) = &self.submitted {
+            self.push(LogLine::Command(line.clone()));
+        }
+        if !options.is_empty() {
+            self.push(LogLine::Output(options.join("  ")));
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use crate::{ui::settings_screen::SettingsScreen, user_settings::UserSettings, world::save::{WorldInfo, create_world, delete_world, list_worlds}};
+
+const ERROR_COLOR: [f32; 4] = [1.0, 0.4, 0.4, 1.0];
+const BUTTON_SIZE: [f32; 2] = [300.0, 0.0];
+const WORLD_LIST_SIZE: [f32; 2] = [300.0, 200.0];
+
+/// Something picked in a menu, waiting to be handled
+#[derive(Clone, Debug)]
+pub enum MenuAction {
+    /// Load the given world and play it
+    Play(WorldInfo),
+    Resume,
+    /// Write the settings file, now that they're done being changed
+    SaveSettings,
+    SaveAndQuit,
+    /// Close the game
+    Quit,
+}
+
+/// Which menu is showing
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Screen {
+    Hidden,
+    /// Picking, making or deleting a world
+    Main,
+    Pause,
+    Settings,
+}
+
+/// The main menu and pause menu
+pub struct Menu {
+    screen: Screen,
+    /// The screen to go back to from the settings
+    settings_from: Screen,
+    settings: SettingsScreen,
+    /// Worlds in the saves directory, as of opening the main menu
+    worlds: Vec<WorldInfo>,
+    /// Index of the world picked in the list
+    selected: Option<usize>,
+    /// Whether the player has asked to delete the selected world, and it's
+    /// waiting on them to make sure
+    confirm_delete: bool,
+    new_name: String,
+    new_seed: String,
+    /// The last thing that went wrong, if anything
+    error: Option<String>,
+    /// The last thing picked, waiting to be handled
+    action: Option<MenuAction>,
+}
+
+impl Menu {
+    pub fn new(settings: UserSettings) -> Self {
+        Self {
+            screen: Screen::Hidden,
+            settings_from: Screen::Hidden,
+            settings: SettingsScreen::new(settings),
+            worlds: vec![],
+            selected: None,
+            confirm_delete: false,
+            new_name: String::new(),
+            new_seed: String::new(),
+            error: None,
+            action: None,
+        }
+    }
+
+    /// Shows the main menu, listing the worlds saved at the moment
+    pub fn show_main(&mut self) {
+        self.screen = Screen::Main;
+        self.worlds = list_worlds();
+        self.selected = None;
+        self.confirm_delete = false;
+        self.error = None;
+    }
+
+    pub fn show_pause(&mut self) {
+        self.screen = Screen::Pause;
+    }
+
+    pub fn hide(&mut self) {
+        self.screen = Screen::Hidden;
+    }
+
+    /// Whether the main menu, or the settings opened from it, is showing
+    /// rather than a game
+    pub fn is_main(&self) -> bool {
+        self.screen == Screen::Main ||
+            (self.screen == Screen::Settings && self.settings_from == Screen::Main)
+    }
+
+    pub fn is_settings(&self) -> bool {
+        self.screen == Screen::Settings
+    }
+
+    fn open_settings(&mut self) {
+        self.settings_from = self.screen;
+        self.screen = Screen::Settings;
+    }
+
+    /// Goes back to the menu the settings were opened from, and has them
+    /// saved
+    pub fn close_settings(&mut self) {
+        self.screen = self.settings_from;
+        self.action = Some(MenuAction::SaveSettings);
+    }
+
+    /// The settings, if they were changed since this was last called
+    pub fn take_changed_settings(&mut self) -> Option<UserSettings> {
+        self.settings.take_changed()
+    }
+
+    /// The last thing picked since this was last called, if any
+    pub fn take_action(&mut self) -> Option<MenuAction> {
+        self.action.take()
+    }
+
+    pub(super) fn generate(&mut self, gui: &mut imgui::Ui) {
+        match self.screen {
+            Screen::Hidden => {},
+            Screen::Main => self.generate_main(gui),
+            Screen::Pause => self.generate_pause(gui),
+            Screen::Settings => self.generate_settings(gui),
+        }
+    }
+
+    fn generate_main(&mut self, gui: &imgui::Ui) {
+        let display_size = gui.io().display_size;
+        gui.window("AZP MC")
+            .position([display_size[0] / 2.0, display_size[1] / 2.0], imgui::Condition::Always)
+            .position_pivot([0.5, 0.5])
+            .always_auto_resize(true)
+            .collapsible(false)
+            .movable(false)
+            .build(|| {
+                gui.text("Worlds");
+                gui.child_window("Worlds").size(WORLD_LIST_SIZE).border(true).build(|| {
+                    if self.worlds.is_empty() {
+                        gui.text_disabled("No worlds yet");
+                    }
+
+                    for (i, world) in self.worlds.iter().enumerate() {
+                        let label = format!("{} (seed {})##{}", world.name, world.seed, i);
+                        let clicked = gui.selectable_config(label)
+                            .selected(self.selected == Some(i))
+                            .allow_double_click(true)
+                            .build();
+                        if clicked {
+                            self.selected = Some(i);
+                            self.confirm_delete = false;
+                            if gui.is_mouse_double_clicked(imgui::MouseButton::Left) {
+                                self.action = Some(MenuAction::Play(world.clone()));
+                            }
+                        }
+                    }
+                });
+
+                let selected = self.selected.and_then(|i| self.worlds.get(i)).cloned();
+                if let (Some(world), true) = (&selected, self.confirm_delete) {
+                    gui.text(format!("Delete \"{}\" forever?", world.name));
+                    if gui.button("Yes, delete it") {
+                        match delete_world(world) {
+                            Ok(()) => self.show_main(),
+                            Err(e) => self.error = Some(format!("{:#}", e)),
+                        }
+                    }
+                    gui.same_line();
+                    if gui.button("Cancel") {
+                        self.confirm_delete = false;
+                    }
+                } else {
+                    let _disabled = gui.begin_disabled(selected.is_none());
+                    if gui.button("Play") && let Some(world) = &selected {
+                        self.action = Some(MenuAction::Play(world.clone()));
+                    }
+                    gui.same_line();
+                    if gui.button("Delete") {
+                        self.confirm_delete = true;
+                    }
+                }
+
+                gui.separator();
+                gui.text("New world");
+                gui.input_text("Name", &mut self.new_name).build();
+                gui.input_text("Seed", &mut self.new_seed)
+                    .hint("Blank for a random one")
+                    .build();
+                if gui.button("Create") {
+                    match create_world(&self.new_name, parse_seed(&self.new_seed)) {
+                        Ok(world) => {
+                            self.new_name.clear();
+                            self.new_seed.clear();
+                            self.action = Some(MenuAction::Play(world));
+                        },
+                        Err(e) => self.error = Some(format!("{:#}", e)),
+                    }
+                }
+
+                gui.separator();
+                if gui.button_with_size("Settings", BUTTON_SIZE) {
+                    self.open_settings();
+                }
+                if gui.button_with_size("Quit", BUTTON_SIZE) {
+                    self.action = Some(MenuAction::Quit);
+                }
+
+                if let Some(error) = &self.error {
+                    gui.text_colored(ERROR_COLOR, error);
+                }
+            });
+    }
+
+    fn generate_pause(&mut self, gui: &imgui::Ui) {
+        let display_size = gui.io().display_size;
+        gui.window("Paused")
+            .position([display_size[0] / 2.0, display_size[1] / 2.0], imgui::Condition::Always)
+            .position_pivot([0.5, 0.5])
+            .always_auto_resize(true)
+            .collapsible(false)
+            .movable(false)
+            .build(|| {
+                if gui.button_with_size("Resume", BUTTON_SIZE) {
+                    self.action = Some(MenuAction::Resume);
+                }
+                if gui.button_with_size("Settings", BUTTON_SIZE) {
+                    self.open_settings();
+                }
+                if gui.button_with_size("Save and quit to title", BUTTON_SIZE) {
+                    self.action = Some(MenuAction::SaveAndQuit);
+                }
+            });
+    }
+
+    fn generate_settings(&mut self, gui: &imgui::Ui) {
+        let display_size = gui.io().display_size;
+        let mut done = false;
+        gui.window("Settings")
+            .position([display_size[0] / 2.0, display_size[1] / 2.0], imgui::Condition::Always)
+            .position_pivot([0.5, 0.5])
+            .always_auto_resize(true)
+            .collapsible(false)
+            .movable(false)
+            .build(|| {
+                done = self.settings.generate(gui, BUTTON_SIZE);
+            });
+
+        if done {
+            self.close_settings();
+        }
+    }
+}
+
+/// The seed typed in for a new world. Numbers are used as they are, other
+/// text is hashed, and nothing picks a random seed.
+fn parse_seed(text: &str) -> u32 {
+    let text = text.trim();
+    if text.is_empty() {
+        return rand::random();
+    }
+
+    text.parse().unwrap_or_else(|_| {
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        hasher.finish() as u32
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeds_from_text() {
+        assert_eq!(parse_seed(" 613 "), 613);
+        assert_eq!(parse_seed("hello"), parse_seed("hello"));
+        assert_ne!(parse_seed("hello"), parse_seed("world"));
+    }
+}
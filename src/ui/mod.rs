@@ -4,9 +4,17 @@ use imgui_wgpu::Renderer;
 use imgui_winit_support::{HiDpiMode, WinitPlatform};
 use wgpu::{CommandEncoder, TextureView};
 use winit::{event::Event, window::Window};
-use crate::{settings, ui::state::UIState};
+use crate::{ui::{console::Console, menu::Menu, state::UIState}, user_settings::UserSettings};
 
-mod state;
+pub mod state;
+/// The window commands are typed into
+pub mod console;
+/// Everything that can be typed into the console
+pub mod commands;
+/// The main menu and pause menu
+pub mod menu;
+/// The menu screen for changing settings
+pub mod settings_screen;
 
 pub struct UI {
     context: imgui::Context,
@@ -14,31 +22,27 @@ pub struct UI {
     renderer: Option<Renderer>,
     last_frame: Instant,
     last_cursor: Option<MouseCursor>,
+    font_size: f32,
+    /// Whether the font size changed, so the font needs to be built again
+    /// before the next frame
+    rebuild_font: bool,
 
     pub state: UIState,
+    pub console: Console,
+    pub menu: Menu,
 }
 
 impl UI {
     pub fn new(
         win: &Window,
+        settings: &UserSettings,
     ) -> Self {
         let mut context = imgui::Context::create();
         let mut platform = WinitPlatform::new(&mut context);
         platform.attach_window(context.io_mut(), win, HiDpiMode::Default);
 
-        let dpi_factor = win.scale_factor();
-        let font_sz = (settings::FONT_SZ * dpi_factor) as f32;
-        context.io_mut().font_global_scale = (1.0 / dpi_factor) as f32;
-        
         context.set_ini_filename(None);
-        context.fonts().add_font(&[FontSource::DefaultFontData {
-            config: Some(imgui::FontConfig {
-                oversample_h: 1,
-                pixel_snap_h: true,
-                size_pixels: font_sz,
-                ..Default::default()
-            }),
-        }]);
+        add_font(&mut context, win, settings.font_size);
 
         Self {
             context,
@@ -46,7 +50,11 @@ impl UI {
             renderer: None,
             last_frame: Instant::now(),
             last_cursor: None,
+            font_size: settings.font_size,
+            rebuild_font: false,
             state: UIState::default(),
+            console: Console::new(),
+            menu: Menu::new(settings.clone()),
         }
     }
 
@@ -76,6 +84,13 @@ impl UI {
             Some(r) => r,
         };
 
+        if self.rebuild_font {
+            self.context.fonts().clear();
+            add_font(&mut self.context, win, self.font_size);
+            renderer.reload_font_texture(&mut self.context, device, queue);
+            self.rebuild_font = false;
+        }
+
         let now = Instant::now();
         self.context.io_mut().update_delta_time(now - self.last_frame);
         self.last_frame = now;
@@ -85,7 +100,12 @@ impl UI {
             .unwrap();
 
         let gui = self.context.new_frame();
-        self.state.generate(gui);
+        // There's no game to show over in the main menu
+        if !self.menu.is_main() {
+            self.state.generate(gui);
+            self.console.generate(gui);
+        }
+        self.menu.generate(gui);
 
         if self.last_cursor != gui.mouse_cursor() {
             self.last_cursor = gui.mouse_cursor();
@@ -111,6 +131,19 @@ impl UI {
             .unwrap();
     }
 
+    /// Changes the font size, from the next frame on
+    pub fn set_font_size(&mut self, font_size: f32) {
+        if font_size != self.font_size {
+            self.font_size = font_size;
+            self.rebuild_font = true;
+        }
+    }
+
+    /// Whether the mouse is over the UI, so clicks are meant for it
+    pub fn wants_mouse(&self) -> bool {
+        self.context.io().want_capture_mouse
+    }
+
     pub fn handle_event(&mut self, win: &Window, e: &Event<()>) {
         self.platform.handle_event(
             self.context.io_mut(), 
@@ -119,3 +152,20 @@ impl UI {
         );
     }
 }
+
+/// Adds the default font at the given size, scaled up to stay sharp on high
+/// DPI screens
+fn add_font(context: &mut imgui::Context, win: &Window, font_size: f32) {
+    let dpi_factor = win.scale_factor();
+    let font_sz = (font_size as f64 * dpi_factor) as f32;
+    context.io_mut().font_global_scale = (1.0 / dpi_factor) as f32;
+
+    context.fonts().add_font(&[FontSource::DefaultFontData {
+        config: Some(imgui::FontConfig {
+            oversample_h: 1,
+            pixel_snap_h: true,
+            size_pixels: font_sz,
+            ..Default::default()
+        }),
+    }]);
+}
@@ -0,0 +1,88 @@
+use crate::{rendering::resource_pack::list_packs, user_settings::{FONT_SZ_RANGE, FOV_RANGE, MOUSE_SENSITIVITY_RANGE, RENDER_DIST_RANGE, SHADOW_RESOLUTIONS, UserSettings}};
+
+/// Sliders and pickers for every setting, changing them as they're dragged
+pub struct SettingsScreen {
+    /// The settings as they are in the game right now
+    settings: UserSettings,
+    /// Whether any setting changed since `take_changed` was last called
+    changed: bool,
+    /// Resource packs that can be picked, as of when this was made
+    packs: Vec<String>,
+}
+
+impl SettingsScreen {
+    pub fn new(settings: UserSettings) -> Self {
+        Self {
+            settings,
+            changed: false,
+            packs: list_packs(),
+        }
+    }
+
+    /// The settings, if they changed since this was last called
+    pub fn take_changed(&mut self) -> Option<UserSettings> {
+        std::mem::take(&mut self.changed).then(|| self.settings.clone())
+    }
+
+    /// Draws the settings into the current window. Returns whether the
+    /// player is done with them.
+    pub(super) fn generate(&mut self, gui: &imgui::Ui, button_size: [f32; 2]) -> bool {
+        let old = self.settings.clone();
+        let s = &mut self.settings;
+
+        let mut render_dist = s.render_dist as u32;
+        gui.slider_config(
+            "Render distance",
+            *RENDER_DIST_RANGE.start() as u32,
+            *RENDER_DIST_RANGE.end() as u32,
+        )
+            .display_format("%d chunks")
+            .build(&mut render_dist);
+        s.render_dist = render_dist as usize;
+
+        gui.slider_config("Field of view", *FOV_RANGE.start(), *FOV_RANGE.end())
+            .display_format("%.0f°")
+            .build(&mut s.fov);
+        gui.slider_config(
+            "Mouse sensitivity",
+            *MOUSE_SENSITIVITY_RANGE.start(),
+            *MOUSE_SENSITIVITY_RANGE.end(),
+        )
+            .display_format("%.4f")
+            .build(&mut s.mouse_sensitivity);
+
+        let labels = SHADOW_RESOLUTIONS.map(|r| format!("{} x {}", r, r));
+        let mut shadow_i = SHADOW_RESOLUTIONS.iter()
+            .position(|&r| r == s.shadow_res)
+            .unwrap_or_default();
+        if gui.combo_simple_string("Shadow resolution", &mut shadow_i, &labels) {
+            s.shadow_res = SHADOW_RESOLUTIONS[shadow_i];
+        }
+
+        gui.slider_config("Font size", *FONT_SZ_RANGE.start(), *FONT_SZ_RANGE.end())
+            .display_format("%.0f")
+            .build(&mut s.font_size);
+        gui.color_edit3("Sky color", &mut s.sky_color);
+
+        let mut pack_i = self.packs.iter()
+            .position(|p| *p == s.resource_pack)
+            .unwrap_or_default();
+        if !self.packs.is_empty() &&
+            gui.combo_simple_string("Resource pack", &mut pack_i, &self.packs) {
+            s.resource_pack = self.packs[pack_i].clone();
+        }
+
+        if gui.button("Reset to defaults") {
+            *s = UserSettings::default();
+        }
+
+        // Typed in values can end up out of range
+        *s = std::mem::take(s).validated();
+        if *s != old {
+            self.changed = true;
+        }
+
+        gui.separator();
+        gui.button_with_size("Done", button_size)
+    }
+}
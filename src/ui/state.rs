@@ -1,8 +1,88 @@
-use crate::{physics::Entity, world::{GameWorld, ThreeDimPos, block::BlockType}};
+use cgmath::{Point3, Vector3};
+use wgpu::AdapterInfo;
+use crate::{physics::Entity, settings::{MAX_HEALTH, MAX_HUNGER}, world::{Coordinate, GameWorld, ThreeDimPos, WorldPos, block_entity::ItemStack, chunk::{cords_to_chunk, cords_to_local}, player::GameMode}};
+
+/// Color drawn over the scene when the camera is underwater
+const UNDERWATER_TINT: [f32; 4] = [0.0, 0.15, 0.6, 0.45];
+/// Color flashed over the scene when the player is hurt
+const HURT_TINT: [f32; 4] = [0.8, 0.0, 0.0, 0.3];
+/// Slots per row in inventory windows
+const SLOTS_PER_ROW: usize = 9;
+const SLOT_SIZE: [f32; 2] = [90.0, 40.0];
+const METER_SIZE: [f32; 2] = [200.0, 0.0];
+/// Frames shown in the debug screen's graphs
+const FRAME_HISTORY: usize = 240;
+const GRAPH_SIZE: [f32; 2] = [300.0, 50.0];
+
+/// Something done to a slot of the open container
+#[derive(Copy, Clone, Debug)]
+pub enum SlotClick {
+    /// Put one of the held block in
+    Store(usize),
+    /// Take one out, and hold it
+    Take(usize),
+}
 
 pub struct UIState {
     position: ThreeDimPos,
     facing: String,
+    /// Name of the block the player places, and how many they have in
+    /// survival
+    held: String,
+    underwater: bool,
+    /// Whether the player was just hurt
+    hurt: bool,
+    game_mode: GameMode,
+    health: f32,
+    hunger: f32,
+    saturation: f32,
+    /// How far through a bite the player is, if they're eating
+    eating: Option<f32>,
+    /// How far through breaking a block the player is, if they're breaking
+    /// one
+    breaking: Option<f32>,
+    /// Whether the debug screen is showing
+    show_debug: bool,
+    /// How long recent frames took, in ms, as a ring buffer
+    frame_times: Vec<f32>,
+    /// Where the next frame goes in `frame_times`
+    frame_index: usize,
+    /// How long the latest physics tick took, in ms
+    tick_time: f32,
+    loaded_chunks: usize,
+    /// Blocks generated into chunks that aren't loaded yet, like the parts of
+    /// trees that hang over a chunk border
+    pending_blocks: usize,
+    /// Vertices and indices drawn in the last frame
+    vertices: usize,
+    indices: usize,
+    /// Which GPU is being drawn with
+    gpu: String,
+    precise_position: Point3<f32>,
+    velocity: Vector3<f32>,
+    /// The chunk the player is in, and where in it
+    chunk: WorldPos,
+    in_chunk: (usize, Coordinate, usize),
+    /// Which way the camera points
+    direction: Vector3<f32>,
+    /// Height of the generated terrain at the player's column
+    terrain_height: Coordinate,
+    /// Light given off by the block the player is in
+    block_light: u8,
+    daylight: f32,
+    /// Chunk section meshes drawn in the last frame
+    chunks_drawn: usize,
+    /// Chunk section meshes skipped in the last frame for being off screen
+    chunks_culled: usize,
+    /// Chunk section meshes skipped in the last frame for being hidden
+    /// behind terrain
+    chunks_occluded: usize,
+    /// Memory used by loaded block data, in bytes
+    block_memory: usize,
+    /// The slots of the container the player has open, if any
+    container: Option<Vec<Option<ItemStack>>>,
+    /// The last slot clicked, waiting to be handled
+    slot_click: Option<SlotClick>,
 }
 
 impl UIState {
@@ -10,10 +90,59 @@ impl UIState {
         Self {
             position: (0,0,0),
             facing: String::default(),
+            held: String::new(),
+            underwater: false,
+            hurt: false,
+            game_mode: GameMode::Survival,
+            health: 0.0,
+            hunger: 0.0,
+            saturation: 0.0,
+            eating: None,
+            breaking: None,
+            show_debug: false,
+            frame_times: vec![0.0; FRAME_HISTORY],
+            frame_index: 0,
+            tick_time: 0.0,
+            loaded_chunks: 0,
+            pending_blocks: 0,
+            vertices: 0,
+            indices: 0,
+            gpu: String::default(),
+            precise_position: Point3::new(0.0, 0.0, 0.0),
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+            chunk: (0, 0),
+            in_chunk: (0, 0, 0),
+            direction: Vector3::new(0.0, 0.0, 0.0),
+            terrain_height: 0,
+            block_light: 0,
+            daylight: 0.0,
+            chunks_drawn: 0,
+            chunks_culled: 0,
+            chunks_occluded: 0,
+            block_memory: 0,
+            container: None,
+            slot_click: None,
         }
     }
 
     pub(super) fn generate(&mut self, gui: &mut imgui::Ui) {
+        self.frame_times[self.frame_index] = gui.io().delta_time * 1000.0;
+        self.frame_index = (self.frame_index + 1) % FRAME_HISTORY;
+
+        if self.underwater {
+            gui.get_background_draw_list()
+                .add_rect([0.0, 0.0], gui.io().display_size, UNDERWATER_TINT)
+                .filled(true)
+                .build();
+        }
+
+        if self.hurt {
+            gui.get_background_draw_list()
+                .add_rect([0.0, 0.0], gui.io().display_size, HURT_TINT)
+                .filled(true)
+                .build();
+        }
+
         gui.window("Overlay")
             .position([10.0, 10.0], imgui::Condition::Appearing)
             .size([1.,1.], imgui::Condition::Once) // So it draws
@@ -26,14 +155,217 @@ impl UIState {
                 gui.spacing();
                 gui.text(format!("Position: {:?}", self.position));
                 gui.text(format!("Facing {}", self.facing));
+                gui.text(format!("Holding: {}", self.held));
+                gui.text(format!("Mode: {:?}", self.game_mode));
+                if self.game_mode == GameMode::Survival {
+                    meter(gui, "Health", self.health, MAX_HEALTH);
+                    meter(gui, "Hunger", self.hunger, MAX_HUNGER);
+                    meter(gui, "Saturation", self.saturation, MAX_HUNGER);
+                    if let Some(progress) = self.eating {
+                        imgui::ProgressBar::new(progress)
+                            .overlay_text("Eating")
+                            .size(METER_SIZE)
+                            .build(gui);
+                    }
+                    if let Some(progress) = self.breaking {
+                        imgui::ProgressBar::new(progress)
+                            .overlay_text("Breaking")
+                            .size(METER_SIZE)
+                            .build(gui);
+                    }
+                }
             });
+
+        if self.show_debug {
+            self.generate_debug(gui);
+        }
+
+        if let Some(slots) = &self.container {
+            let display_size = gui.io().display_size;
+            gui.window("Container")
+                .position(
+                    [display_size[0] / 2.0, display_size[1] / 2.0],
+                    imgui::Condition::Appearing,
+                )
+                .position_pivot([0.5, 0.5])
+                .always_auto_resize(true)
+                .collapsible(false)
+                .build(|| {
+                    gui.text("Left click to put in the held block, right click to take one out");
+                    for (i, slot) in slots.iter().enumerate() {
+                        if i % SLOTS_PER_ROW != 0 {
+                            gui.same_line();
+                        }
+
+                        let label = match slot {
+                            Some(stack) => format!("{} x{}##{}", stack.block.name(), stack.count, i),
+                            None => format!("##{}", i),
+                        };
+                        if gui.button_with_size(label, SLOT_SIZE) {
+                            self.slot_click = Some(SlotClick::Store(i));
+                        }
+                        if gui.is_item_clicked_with_button(imgui::MouseButton::Right) {
+                            self.slot_click = Some(SlotClick::Take(i));
+                        }
+                    }
+                });
+        }
+    }
+
+    /// Draws the debug screen, with performance and world stats
+    fn generate_debug(&self, gui: &imgui::Ui) {
+        let display_size = gui.io().display_size;
+        gui.window("Debug")
+            .position([display_size[0] - 10.0, 10.0], imgui::Condition::Appearing)
+            .position_pivot([1.0, 0.0])
+            .size([1.,1.], imgui::Condition::Once) // So it draws
+            .no_decoration()
+            .always_auto_resize(true)
+            .build(|| {
+                let frame_time = gui.io().delta_time * 1000.0;
+                gui.text(format!("FPS: {:.0} ({:.2} ms)", gui.io().framerate, frame_time));
+                let fps: Vec<f32> = self.frame_times.iter()
+                    .map(|t| if *t > 0.0 { 1000.0 / t } else { 0.0 })
+                    .collect();
+                gui.plot_lines("FPS", &fps)
+                    .values_offset(self.frame_index)
+                    .scale_min(0.0)
+                    .graph_size(GRAPH_SIZE)
+                    .build();
+                gui.plot_lines("Frame time (ms)", &self.frame_times)
+                    .values_offset(self.frame_index)
+                    .scale_min(0.0)
+                    .graph_size(GRAPH_SIZE)
+                    .build();
+                gui.text(format!("Tick time: {:.2} ms", self.tick_time));
+
+                gui.separator();
+                gui.text(format!(
+                    "Chunks: {} loaded, {} blocks generated into unloaded ones",
+                    self.loaded_chunks, self.pending_blocks,
+                ));
+                gui.text(format!(
+                    "Sections: {} drawn, {} culled, {} occluded",
+                    self.chunks_drawn, self.chunks_culled, self.chunks_occluded,
+                ));
+                gui.text(format!("Geometry: {} vertices, {} indices", self.vertices, self.indices));
+                gui.text(format!("Block data: {} KiB", self.block_memory / 1024));
+                gui.text(format!("GPU: {}", self.gpu));
+
+                gui.separator();
+                let p = self.precise_position;
+                let v = self.velocity;
+                gui.text(format!("XYZ: {:.3} / {:.3} / {:.3}", p.x, p.y, p.z));
+                gui.text(format!("Velocity: {:.2} / {:.2} / {:.2}", v.x, v.y, v.z));
+                gui.text(format!("Chunk: {:?}, at {:?} in it", self.chunk, self.in_chunk));
+                let d = self.direction;
+                gui.text(format!(
+                    "Direction: {:.2} / {:.2} / {:.2} (towards {})",
+                    d.x, d.y, d.z, horizontal_axis(d),
+                ));
+                gui.text(format!("Terrain height: {}", self.terrain_height));
+                gui.text(format!(
+                    "Light: {} from blocks, {:.0}% daylight",
+                    self.block_light, self.daylight * 100.0,
+                ));
+            });
+    }
+
+    pub fn toggle_debug(&mut self) {
+        self.show_debug = !self.show_debug;
+    }
+
+    pub fn set_gpu(&mut self, info: &AdapterInfo) {
+        self.gpu = format!("{} ({:?}, {} {})", info.name, info.backend, info.driver, info.driver_info);
+    }
+
+    /// The slot clicked since this was last called, if any
+    pub fn take_slot_click(&mut self) -> Option<SlotClick> {
+        self.slot_click.take()
     }
 
     pub fn update(&mut self, world: &GameWorld) {
         self.position = world.player().get_world_pos();
-        self.facing = match world.facing() {
-            Some(b) => format!("{:?}", b),
-            None => "nothing :(".to_string(),
-        }
+        self.facing = match (world.targeted_mob(), world.facing().map(|b| b.kind)) {
+            (Some(mob), _) => format!(
+                "{:?} (health {:.0})", mob.kind(), mob.health.current(),
+            ),
+            (None, Some(b)) => format!(
+                "{} (hardness {}, light {}, drops {})",
+                b.name(),
+                b.hardness(),
+                b.light(),
+                match b.drops() {
+                    [] => "nothing".to_string(),
+                    drops => drops.iter().map(|d| d.name()).collect::<Vec<_>>().join(", "),
+                },
+            ),
+            (None, None) => "nothing :(".to_string(),
+        };
+        let player = world.player();
+        self.held = match player.game_mode() {
+            GameMode::Survival => format!("{} ({})", player.held.name(), player.inventory.count(player.held)),
+            _ => player.held.name().to_string(),
+        };
+        self.underwater = world.camera_in_fluid();
+        self.hurt = world.player().is_hurt();
+        self.game_mode = world.player().game_mode();
+        self.health = world.player().health();
+        self.hunger = world.player().hunger().food();
+        self.saturation = world.player().hunger().saturation();
+        self.eating = world.player().eat_progress();
+        self.breaking = world.break_progress();
+        self.block_memory = world.block_memory();
+        self.tick_time = world.tick_time().as_secs_f32() * 1000.0;
+        self.loaded_chunks = world.loaded_chunks();
+        self.pending_blocks = world.pending_blocks();
+
+        let player = world.player();
+        let (x, y, z) = self.position;
+        let (local_x, local_z) = cords_to_local((x, z));
+        self.precise_position = player.get_precise_pos();
+        self.velocity = player.get_velocity();
+        self.chunk = cords_to_chunk((x, z));
+        self.in_chunk = (local_x, y, local_z);
+        self.direction = player.facing;
+        self.terrain_height = world.terrain_height(x, z);
+        self.block_light = world.get_block(self.position).map_or(0, |b| b.kind.light());
+        self.daylight = world.time().daylight();
+        self.container = world.open_container().map(|i| i.slots().to_vec());
     }
+
+    pub fn update_render_stats(
+        &mut self,
+        drawn: usize,
+        culled: usize,
+        occluded: usize,
+        vertices: usize,
+        indices: usize,
+    ) {
+        self.chunks_drawn = drawn;
+        self.chunks_culled = culled;
+        self.chunks_occluded = occluded;
+        self.vertices = vertices;
+        self.indices = indices;
+    }
+}
+
+/// The horizontal axis the given direction points along the most, like "-Z"
+fn horizontal_axis(direction: Vector3<f32>) -> &'static str {
+    if direction.x.abs() > direction.z.abs() {
+        if direction.x > 0.0 { "+X" } else { "-X" }
+    } else if direction.z > 0.0 {
+        "+Z"
+    } else {
+        "-Z"
+    }
+}
+
+/// Draws a bar filled to show how much of something there is out of the
+/// given maximum
+fn meter(gui: &imgui::Ui, label: &str, value: f32, max: f32) {
+    imgui::ProgressBar::new(value / max)
+        .overlay_text(format!("{}: {:.0}/{:.0}", label, value.max(0.0), max))
+        .size(METER_SIZE)
+        .build(gui);
 }
@@ -0,0 +1,167 @@
+use std::{fs, ops::RangeInclusive, path::Path};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use crate::{settings::{DEFAULT_FONT_SZ, DEFAULT_FOV, DEFAULT_MOUSE_SENSITIVITY, DEFAULT_RENDER_DIST, DEFAULT_RESOURCE_PACK, DEFAULT_SHADOW_RES, DEFAULT_SKY_COLOR, USER_SETTINGS_FILE}};
+
+/// Render distances that can be picked, in chunks. Far terrain needs at
+/// least a couple chunks around the player to be loaded.
+pub const RENDER_DIST_RANGE: RangeInclusive<usize> = 3..=32;
+/// Fields of view that can be picked, in degrees
+pub const FOV_RANGE: RangeInclusive<f32> = 30.0..=110.0;
+pub const MOUSE_SENSITIVITY_RANGE: RangeInclusive<f32> = 0.001..=0.03;
+pub const FONT_SZ_RANGE: RangeInclusive<f32> = 10.0..=36.0;
+/// Shadow map resolutions that can be picked, from lowest to highest
+pub const SHADOW_RESOLUTIONS: [u32; 4] = [1024, 2048, 4096, 8192];
+
+/// Settings the player can change while playing, kept in
+/// `USER_SETTINGS_FILE` between runs. Missing settings are left at their
+/// defaults.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserSettings {
+    /// The number of chunks to load away from the player
+    #[serde(rename = "render_distance")]
+    pub render_dist: usize,
+    /// Vertical field of view, in degrees
+    pub fov: f32,
+    /// Radians turned per pixel the mouse moves
+    pub mouse_sensitivity: f32,
+    /// Width and height of the shadow map, in pixels
+    #[serde(rename = "shadow_resolution")]
+    pub shadow_res: u32,
+    pub font_size: f32,
+    /// Color of the sky straight up during the day
+    pub sky_color: [f32; 3],
+    /// Directory name of the resource pack to draw with, within
+    /// `RESOURCE_PACK_DIR`
+    pub resource_pack: String,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            render_dist: DEFAULT_RENDER_DIST,
+            fov: DEFAULT_FOV,
+            mouse_sensitivity: DEFAULT_MOUSE_SENSITIVITY,
+            shadow_res: DEFAULT_SHADOW_RES,
+            font_size: DEFAULT_FONT_SZ,
+            sky_color: DEFAULT_SKY_COLOR,
+            resource_pack: DEFAULT_RESOURCE_PACK.to_string(),
+        }
+    }
+}
+
+impl UserSettings {
+    /// Reads the settings file. Anything missing is left at its default, and
+    /// a file that can't be read at all is logged and ignored.
+    pub fn load() -> Self {
+        let path = Path::new(USER_SETTINGS_FILE);
+        if !path.exists() {
+            return Self::default();
+        }
+
+        let settings = fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read {}", USER_SETTINGS_FILE))
+            .and_then(|text| Self::parse(&text));
+        match settings {
+            Ok(settings) => settings,
+            Err(e) => {
+                log::warn!("{:#}, using the default settings", e);
+                Self::default()
+            },
+        }
+    }
+
+    /// Reads settings from the text of a settings file, bringing them into
+    /// range
+    fn parse(text: &str) -> anyhow::Result<Self> {
+        let settings: Self = toml::from_str(text)
+            .with_context(|| format!("Couldn't parse {}", USER_SETTINGS_FILE))?;
+        Ok(settings.validated())
+    }
+
+    /// Brings every setting into the range it can be picked from. Numbers
+    /// that aren't finite go back to their defaults.
+    pub fn validated(self) -> Self {
+        let clamp = |v: f32, range: RangeInclusive<f32>, default: f32| {
+            let v = if v.is_finite() { v } else { default };
+            v.clamp(*range.start(), *range.end())
+        };
+
+        // The highest resolution that isn't above the one asked for
+        let shadow_res = SHADOW_RESOLUTIONS.iter()
+            .copied()
+            .rfind(|&r| r <= self.shadow_res)
+            .unwrap_or(SHADOW_RESOLUTIONS[0]);
+
+        Self {
+            render_dist: self.render_dist
+                .clamp(*RENDER_DIST_RANGE.start(), *RENDER_DIST_RANGE.end()),
+            fov: clamp(self.fov, FOV_RANGE, DEFAULT_FOV),
+            mouse_sensitivity: clamp(
+                self.mouse_sensitivity,
+                MOUSE_SENSITIVITY_RANGE,
+                DEFAULT_MOUSE_SENSITIVITY,
+            ),
+            shadow_res,
+            font_size: clamp(self.font_size, FONT_SZ_RANGE, DEFAULT_FONT_SZ),
+            sky_color: std::array::from_fn(|i| {
+                clamp(self.sky_color[i], 0.0..=1.0, DEFAULT_SKY_COLOR[i])
+            }),
+            resource_pack: self.resource_pack,
+        }
+    }
+
+    /// Writes the settings file
+    pub fn save(&self) -> anyhow::Result<()> {
+        let text = toml::to_string(self).context("Couldn't write the settings")?;
+        fs::write(USER_SETTINGS_FILE, text)
+            .with_context(|| format!("Couldn't write {}", USER_SETTINGS_FILE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_round_trip() {
+        let settings = UserSettings {
+            render_dist: 12,
+            fov: 90.0,
+            mouse_sensitivity: 0.01,
+            shadow_res: 2048,
+            font_size: 24.0,
+            sky_color: [0.5, 0.25, 1.0],
+            resource_pack: "other".to_string(),
+        };
+        let text = toml::to_string(&settings).unwrap();
+        assert_eq!(UserSettings::parse(&text).unwrap(), settings);
+    }
+
+    #[test]
+    fn out_of_range_settings_are_clamped() {
+        let settings = UserSettings::parse(r#"
+            render_distance = 1000
+            shadow_resolution = 3000
+            fov = nan
+            mouse_sensitivity = inf
+            font_size = 2
+            sky_color = [2.0, nan, -1.0]
+        "#).unwrap();
+        let defaults = UserSettings::default();
+
+        assert_eq!(settings.render_dist, *RENDER_DIST_RANGE.end());
+        assert_eq!(settings.fov, defaults.fov);
+        assert_eq!(settings.mouse_sensitivity, defaults.mouse_sensitivity);
+        assert_eq!(settings.shadow_res, 2048);
+        assert_eq!(settings.font_size, *FONT_SZ_RANGE.start());
+        assert_eq!(settings.sky_color, [1.0, defaults.sky_color[1], 0.0]);
+    }
+
+    #[test]
+    fn wrong_types_are_rejected() {
+        assert!(UserSettings::parse(r#"fov = "wide""#).is_err());
+        assert!(UserSettings::parse("sky_color = [0.5, 0.5]").is_err());
+    }
+}
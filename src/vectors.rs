@@ -1,8 +1,8 @@
-use cgmath::{Point3, Vector2, Vector3, num_traits::ToPrimitive};
+use cgmath::{Point3, Vector2, Vector3};
 
-use crate::world::ThreeDimPos;
+use crate::world::{Coordinate, ThreeDimPos};
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Dimension { X, Y, Z }
 
 pub fn xyz_to_xz(v: Vector3<f32>) -> Vector2<f32> {
@@ -19,8 +19,8 @@ pub fn replace_xz(v_xyz: Vector3<f32>, v_xz: Vector2<f32>) -> Vector3<f32> {
 
 pub fn point_to_pos(p: Point3<f32>) -> ThreeDimPos {
     (
-        p.x.floor().to_i32().unwrap(),
-        p.y.floor().to_u8().expect("Y coordinate out of bounds"),
-        p.z.floor().to_i32().unwrap(),
+        p.x.floor() as Coordinate,
+        p.y.floor() as Coordinate,
+        p.z.floor() as Coordinate,
     )
 }
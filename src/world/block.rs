@@ -1,102 +1,485 @@
-use std::ops::{Deref, DerefMut};
+use std::{collections::HashMap, ops::{Deref, DerefMut}, path::Path, sync::OnceLock};
 
-use crate::world::chunk::Chunk;
+use anyhow::{Context, bail};
+use serde::Deserialize;
 
-/// The various block types
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub enum BlockType {
-    Air,
-    Water,
-    Dirt,
-    Grass,
-    Sand,
-    Stone,
-    Log,
-    Leaves,
-}
+use crate::{world::{chunk::Chunk, shape::Shape, state::{Block, StateProperty}}};
+
+/// A block type, as its numeric id in the block registry
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BlockType(pub(super) u16);
 
 /// One side of a block
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum BlockSide {
     Front, Back,
     Left, Right,
     Top, Bottom,
 }
 
-impl BlockType {
-    /// Returns the texture coords (x,y) of this block on the texture image
-    ///
-    /// Returns None if has no texture.
-    pub fn texture(&self, side: BlockSide) -> Option<(u8, u8)> {
+impl BlockSide {
+    pub const ALL: [BlockSide; 6] = [
+        Self::Front, Self::Back,
+        Self::Left, Self::Right,
+        Self::Top, Self::Bottom,
+    ];
+
+    /// The sides around a block, but not above or below it
+    pub const HORIZONTAL: [BlockSide; 4] = [
+        Self::Front, Self::Back,
+        Self::Left, Self::Right,
+    ];
+
+    /// The side facing the other way
+    pub fn opposite(&self) -> Self {
         match self {
-            Self::Air => None,
-
-            Self::Water => Some((3,0)),
-
-            Self::Dirt => Some((0,0)),
-            Self::Grass => match side {
-                BlockSide::Top => Some((2,0)),
-                BlockSide::Bottom => Some((0,0)),
-                _ => Some((1,0)),
-            },
-            Self::Sand => Some((4,0)),
-            Self::Stone => Some((5,0)),
-            Self::Log => match side {
-                BlockSide::Top | BlockSide::Bottom => Some((7,0)),
-                _ => Some((6,0)),
-            },
-            Self::Leaves => Some((8,0)),
+            Self::Front => Self::Back,
+            Self::Back => Self::Front,
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
+            Self::Top => Self::Bottom,
+            Self::Bottom => Self::Top,
+        }
+    }
+
+    /// The (X, Y, Z) direction this side faces
+    pub fn offset(&self) -> (i32, i32, i32) {
+        match self {
+            Self::Front => (0, 0, -1),
+            Self::Back => (0, 0, 1),
+            Self::Left => (-1, 0, 0),
+            Self::Right => (1, 0, 0),
+            Self::Top => (0, 1, 0),
+            Self::Bottom => (0, -1, 0),
+        }
+    }
+
+    /// The outward facing normal of this side, for lighting
+    pub fn normal(&self) -> [f32; 3] {
+        let (x, y, z) = self.offset();
+        [x as f32, y as f32, z as f32]
+    }
+
+    /// The side this one ends up as after the given number of quarter turns
+    /// around the Y axis, going front, left, back, right. Top and bottom stay
+    /// put.
+    pub fn turn_y(&self, turns: u8) -> Self {
+        let mut out = *self;
+        for _ in 0..turns % 4 {
+            out = match out {
+                Self::Front => Self::Left,
+                Self::Left => Self::Back,
+                Self::Back => Self::Right,
+                Self::Right => Self::Front,
+                vertical => vertical,
+            };
         }
+        out
+    }
+}
+
+impl BlockType {
+    /// Air is always id 0, so new storage can start out empty without
+    /// consulting the registry
+    pub const AIR: BlockType = BlockType(0);
+
+    /// Every registered block type, in id order
+    pub fn all() -> impl Iterator<Item = BlockType> {
+        (0..registry().blocks.len() as u16).map(BlockType)
+    }
+
+    /// Looks up a block type by its name in the registry
+    pub fn from_name(name: &str) -> Option<Self> {
+        registry().by_name.get(name).copied()
+    }
+
+    fn properties(&self) -> &'static BlockProperties {
+        &registry().blocks[self.0 as usize]
+    }
+
+    /// The name used for this block in data files
+    pub fn name(&self) -> &'static str {
+        &self.properties().name
     }
 
-    /// Returns false for air and water, true otherwise.
+    /// The name of the texture the registry gives the given side of this
+    /// block, which resource packs may override. None if it isn't drawn.
+    pub fn texture_name(&self, side: BlockSide) -> Option<&'static str> {
+        self.properties().textures[side as usize].as_deref()
+    }
+
+    pub fn shape(&self) -> Shape {
+        self.properties().shape
+    }
+
+    /// Whether entities collide with this block
     pub fn is_solid(&self) -> bool {
-        match *self {
-            Self::Air | Self::Water => false,
-            _ => true,
+        self.properties().solid
+    }
+
+    /// Whether the player can target this block
+    pub fn is_targetable(&self) -> bool {
+        self.properties().targetable
+    }
+
+    /// Whether neighbouring faces can be seen through this block
+    pub fn is_transparent(&self) -> bool {
+        self.properties().transparent
+    }
+
+    /// Whether this block completely hides the faces of its neighbours that
+    /// touch it
+    pub fn occludes(&self) -> bool {
+        !self.is_transparent() && self.shape() == Shape::Cube
+    }
+
+    /// Returns true for blocks that entities can swim in.
+    pub fn is_fluid(&self) -> bool {
+        self.properties().fluid
+    }
+
+    /// Light given off, from 0 to `MAX_LIGHT`
+    pub fn light(&self) -> u8 {
+        self.properties().light
+    }
+
+    /// Seconds it takes to break the block in survival. Negative if it can't
+    /// be broken.
+    pub fn hardness(&self) -> f32 {
+        self.properties().hardness
+    }
+
+    /// The blocks left behind when this block is broken
+    pub fn drops(&self) -> &'static [BlockType] {
+        &self.properties().drops
+    }
+
+    /// What happens when the player uses this block, if anything
+    pub fn interaction(&self) -> Option<Interaction> {
+        self.properties().interaction
+    }
+
+    /// What eating this block restores, if it can be eaten
+    pub fn food(&self) -> Option<Food> {
+        self.properties().food
+    }
+
+    /// Whether blocks of this type keep track of the given state property.
+    /// Others always have it at its default.
+    pub fn has_state(&self, property: StateProperty) -> bool {
+        self.properties().states.contains(&property)
+    }
+}
+
+/// The highest light level a block can give off
+pub const MAX_LIGHT: u8 = 15;
+
+/// Slots in containers that don't say how many they have
+const DEFAULT_SLOTS: usize = 27;
+
+/// What happens when the player uses a block
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Interaction {
+    /// Opens or closes it, along with the other half of two block tall blocks
+    Toggle,
+    /// Opens up its inventory, which has the given number of slots
+    Container(usize),
+}
+
+/// What eating a block restores
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Food {
+    pub hunger: f32,
+    pub saturation: f32,
+}
+
+/// Everything the registry knows about a block type
+struct BlockProperties {
+    name: String,
+    /// Texture names, indexed by `BlockSide`
+    textures: [Option<String>; 6],
+    shape: Shape,
+    solid: bool,
+    targetable: bool,
+    transparent: bool,
+    fluid: bool,
+    light: u8,
+    hardness: f32,
+    drops: Vec<BlockType>,
+    states: Vec<StateProperty>,
+    interaction: Option<Interaction>,
+    food: Option<Food>,
+}
+
+/// Every block type, loaded from a data file
+struct BlockRegistry {
+    /// Indexed by id
+    blocks: Vec<BlockProperties>,
+    by_name: HashMap<String, BlockType>,
+}
+
+static REGISTRY: OnceLock<BlockRegistry> = OnceLock::new();
+
+fn registry() -> &'static BlockRegistry {
+    REGISTRY.get().expect("Block registry used before being loaded")
+}
+
+/// Loads the block registry from the given data file. Does nothing if it's
+/// already loaded.
+pub fn load_registry(path: &str) -> anyhow::Result<()> {
+    if REGISTRY.get().is_some() {
+        return Ok(());
+    }
+
+    let registry = BlockRegistry::load(Path::new(path))
+        .with_context(|| format!("Couldn't load block registry from {}", path))?;
+    // Someone else beating us to it is fine, it's the same file
+    let _ = REGISTRY.set(registry);
+    Ok(())
+}
+
+/// The `texture` keys of a block's table, in the block registry or a resource
+/// pack
+#[derive(Default, Debug, Deserialize)]
+pub struct FaceTextures {
+    texture: Option<String>,
+    texture_side: Option<String>,
+    texture_top: Option<String>,
+    texture_bottom: Option<String>,
+    texture_front: Option<String>,
+    texture_back: Option<String>,
+    texture_left: Option<String>,
+    texture_right: Option<String>,
+}
+
+impl FaceTextures {
+    /// The texture on the given side of the block. `texture_side` covers the
+    /// four horizontal sides and `texture` all of them, with the more specific
+    /// keys winning.
+    pub fn get(&self, side: BlockSide) -> Option<&str> {
+        let specific = match side {
+            BlockSide::Top => &self.texture_top,
+            BlockSide::Bottom => &self.texture_bottom,
+            BlockSide::Front => &self.texture_front,
+            BlockSide::Back => &self.texture_back,
+            BlockSide::Left => &self.texture_left,
+            BlockSide::Right => &self.texture_right,
+        };
+        let horizontal = match side {
+            BlockSide::Top | BlockSide::Bottom => &None,
+            _ => &self.texture_side,
+        };
+
+        specific.as_deref()
+            .or(horizontal.as_deref())
+            .or(self.texture.as_deref())
+    }
+}
+
+/// The block registry file, as written
+#[derive(Deserialize)]
+struct RegistryFile {
+    blocks: HashMap<String, BlockEntry>,
+}
+
+/// A `[blocks.<name>]` table in the block registry file. Documented at the top
+/// of the file.
+#[derive(Deserialize)]
+struct BlockEntry {
+    id: u16,
+    #[serde(flatten)]
+    textures: FaceTextures,
+    shape: Option<String>,
+    solid: Option<bool>,
+    targetable: Option<bool>,
+    #[serde(default)]
+    transparent: bool,
+    #[serde(default)]
+    fluid: bool,
+    #[serde(default)]
+    light: u8,
+    hardness: Option<f32>,
+    drops: Option<Vec<String>>,
+    #[serde(default)]
+    states: Vec<String>,
+    interaction: Option<String>,
+    slots: Option<usize>,
+    food: Option<f32>,
+    saturation: Option<f32>,
+}
+
+impl BlockRegistry {
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read {}", path.display()))?;
+        let file: RegistryFile = toml::from_str(&text)
+            .with_context(|| format!("Couldn't parse {}", path.display()))?;
+
+        let mut entries: Vec<_> = file.blocks.into_iter().collect();
+        entries.sort_by_key(|(_, entry)| entry.id);
+
+        let mut by_name = HashMap::new();
+        for (i, (name, entry)) in entries.iter().enumerate() {
+            if entry.id as usize != i {
+                bail!("Block ids must count up from 0 without gaps or repeats, but \"{}\" is {}", name, entry.id);
+            }
+            by_name.insert(name.clone(), BlockType(entry.id));
+        }
+        if entries.first().is_none_or(|(name, _)| name != "air") {
+            bail!("Block 0 must be \"air\"");
+        }
+
+        let mut blocks = Vec::with_capacity(entries.len());
+        for (name, entry) in entries {
+            let context = || format!("Block \"{}\"", name);
+
+            let textures = BlockSide::ALL.map(|side| {
+                entry.textures.get(side).map(str::to_string)
+            });
+
+            let shape = match &entry.shape {
+                Some(shape) => Shape::from_name(shape)
+                    .context("shape must be \"cube\", \"slab\", \"stairs\", \"fence\", \"cross\", \"door\" or \"trapdoor\"")
+                    .with_context(context)?,
+                None => Shape::Cube,
+            };
+
+            if entry.light > MAX_LIGHT {
+                bail!("{}: light must be from 0 to {}", context(), MAX_LIGHT);
+            }
+
+            // Blocks drop themselves unless they say otherwise
+            let drops = match &entry.drops {
+                Some(drops) => drops.iter()
+                    .map(|drop| by_name.get(drop).copied()
+                        .with_context(|| format!("Unknown block \"{}\" in drops", drop)))
+                    .collect::<anyhow::Result<_>>()
+                    .with_context(context)?,
+                None => vec![by_name[&name]],
+            };
+
+            let states = entry.states.iter()
+                .map(|state| StateProperty::from_name(state)
+                    .context("states must be \"facing\", \"axis\", \"open\", \"half\" or \"waterlogged\""))
+                .collect::<anyhow::Result<_>>()
+                .with_context(context)?;
+
+            let interaction = match entry.interaction.as_deref() {
+                Some("toggle") => Some(Interaction::Toggle),
+                Some("container") => match entry.slots {
+                    Some(0) => bail!("{}: slots must be at least 1", context()),
+                    slots => Some(Interaction::Container(slots.unwrap_or(DEFAULT_SLOTS))),
+                },
+                Some(_) => bail!("{}: interaction must be \"toggle\" or \"container\"", context()),
+                None => None,
+            };
+
+            if [entry.food, entry.saturation].into_iter().flatten().any(|n| n < 0.0) {
+                bail!("{}: food and saturation can't be negative", context());
+            }
+            let food = match (entry.food, entry.saturation) {
+                (Some(hunger), saturation) => Some(Food {
+                    hunger,
+                    saturation: saturation.unwrap_or(0.0),
+                }),
+                (None, Some(_)) => bail!("{}: saturation needs food too", context()),
+                (None, None) => None,
+            };
+
+            let solid = entry.solid.unwrap_or(true);
+            blocks.push(BlockProperties {
+                name,
+                textures,
+                shape,
+                solid,
+                // Things you can walk into can usually be targeted too
+                targetable: entry.targetable.unwrap_or(solid),
+                transparent: entry.transparent,
+                fluid: entry.fluid,
+                light: entry.light,
+                hardness: entry.hardness.unwrap_or(1.0),
+                drops,
+                states,
+                interaction,
+                food,
+            });
         }
+
+        Ok(Self { blocks, by_name })
     }
 }
 
 /// A "smart pointer" to a block, such that when it goes out of scope, the 
-/// chunk mesh is updated.
+/// block is written back to the chunk as an edit (see `Chunk::edit`).
 pub struct BlockRef<'a> {
     block: (usize, usize, usize),
     chunk: &'a mut Chunk,
-    original_type: BlockType,
+    original: Block,
+    current: Block,
 }
 
 impl<'a> BlockRef<'a> {
     pub fn new(block: (usize, usize, usize), chunk: &'a mut Chunk) -> Self {
-        let mut out = Self {
+        let (x, y, z) = block;
+        let original = chunk.get(x, y, z);
+
+        Self {
             block,
             chunk,
-            original_type: BlockType::Air,
-        };
-
-        out.original_type = *out;
-        out
+            original,
+            current: original,
+        }
     }
 }
 
 impl Drop for BlockRef<'_> {
     fn drop(&mut self) {
-        if self.original_type != **self {
-            self.chunk.update_mesh();
+        if self.original != self.current {
+            let (x, y, z) = self.block;
+            self.chunk.edit(x, y, z, self.current);
         }
     }
 }
 
 impl Deref for BlockRef<'_> {
-    type Target = BlockType;
+    type Target = Block;
 
     fn deref(&self) -> &Self::Target {
-        &self.chunk.blocks[self.block.0][self.block.2][self.block.1]
+        &self.current
     }
 }
 
 impl DerefMut for BlockRef<'_> {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.chunk.blocks[self.block.0][self.block.2][self.block.1]
+        &mut self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::settings::BLOCK_REGISTRY;
+    use super::*;
+
+    #[test]
+    fn loads_registry() {
+        load_registry(BLOCK_REGISTRY).unwrap();
+
+        assert_eq!(BlockType::from_name("air"), Some(BlockType::AIR));
+        assert!(!BlockType::AIR.is_solid() && BlockType::AIR.is_transparent());
+        assert_eq!(BlockType::AIR.texture_name(BlockSide::Top), None);
+
+        let grass = BlockType::from_name("grass").unwrap();
+        assert_eq!(grass.texture_name(BlockSide::Top), Some("grass_top"));
+        assert_eq!(grass.texture_name(BlockSide::Left), Some("grass_side"));
+        assert_eq!(grass.texture_name(BlockSide::Bottom), Some("dirt"));
+        assert_eq!(grass.drops(), [BlockType::from_name("dirt").unwrap()]);
+
+        let stone = BlockType::from_name("stone").unwrap();
+        assert_eq!(stone.drops(), [stone]);
+        assert!(BlockType::from_name("water").unwrap().is_fluid());
+
+        let log = BlockType::from_name("log").unwrap();
+        assert!(log.has_state(StateProperty::Axis));
+        assert!(!log.has_state(StateProperty::Facing));
     }
 }
@@ -0,0 +1,163 @@
+use crate::world::block::{BlockType, Interaction};
+
+/// The most of one block that fits in an inventory slot
+pub const MAX_STACK: u8 = 64;
+
+/// A number of the same block, in an inventory slot
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ItemStack {
+    pub block: BlockType,
+    pub count: u8,
+}
+
+/// A fixed number of slots that can each hold a stack of blocks
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Inventory {
+    slots: Vec<Option<ItemStack>>,
+}
+
+impl Inventory {
+    /// An empty inventory with the given number of slots
+    pub fn new(size: usize) -> Self {
+        Self { slots: vec![None; size] }
+    }
+
+    /// An inventory holding exactly the given slots
+    pub fn from_slots(slots: Vec<Option<ItemStack>>) -> Self {
+        Self { slots }
+    }
+
+    pub fn slots(&self) -> &[Option<ItemStack>] {
+        &self.slots
+    }
+
+    /// Whether there's nothing in any slot
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(Option::is_none)
+    }
+
+    /// Adds one of the given block to the given slot. Returns false if the
+    /// slot doesn't exist, holds something else or is full.
+    pub fn insert_one(&mut self, slot: usize, block: BlockType) -> bool {
+        let Some(slot) = self.slots.get_mut(slot) else {
+            return false;
+        };
+
+        match slot {
+            None => {
+                *slot = Some(ItemStack { block, count: 1 });
+                true
+            },
+            Some(stack) if stack.block == block && stack.count < MAX_STACK => {
+                stack.count += 1;
+                true
+            },
+            Some(_) => false,
+        }
+    }
+
+    /// Adds one of the given block, onto a stack of it with room if there is
+    /// one, otherwise into the first empty slot. Returns false if there's no
+    /// room.
+    pub fn add(&mut self, block: BlockType) -> bool {
+        let slot = self.slots.iter()
+            .position(|s| s.is_some_and(|s| s.block == block && s.count < MAX_STACK))
+            .or_else(|| self.slots.iter().position(Option::is_none));
+        slot.is_some_and(|slot| self.insert_one(slot, block))
+    }
+
+    /// Takes out one of the given block, from the last slot holding it.
+    /// Returns false if there isn't one.
+    pub fn remove(&mut self, block: BlockType) -> bool {
+        let slot = self.slots.iter().rposition(|s| s.is_some_and(|s| s.block == block));
+        slot.and_then(|slot| self.take_one(slot)).is_some()
+    }
+
+    /// How many of the given block there are, across every slot
+    pub fn count(&self, block: BlockType) -> u32 {
+        self.slots.iter()
+            .flatten()
+            .filter(|s| s.block == block)
+            .map(|s| s.count as u32)
+            .sum()
+    }
+
+    /// Takes one block out of the given slot, if there's anything in it
+    pub fn take_one(&mut self, slot: usize) -> Option<BlockType> {
+        let slot = self.slots.get_mut(slot)?;
+        let stack = slot.as_mut()?;
+        let block = stack.block;
+
+        stack.count -= 1;
+        if stack.count == 0 {
+            *slot = None;
+        }
+        Some(block)
+    }
+}
+
+/// Extra data kept for a single block, beyond its type and state
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BlockEntity {
+    /// Things stored in a container block
+    Container(Inventory),
+}
+
+impl BlockEntity {
+    /// The data a newly placed block of the given type starts out with, if
+    /// it has any
+    pub fn for_block(block: BlockType) -> Option<Self> {
+        match block.interaction() {
+            Some(Interaction::Container(slots)) => Some(Self::Container(Inventory::new(slots))),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stacks_fill_and_empty() {
+        let stone = BlockType(5);
+        let dirt = BlockType(2);
+        let mut inventory = Inventory::new(2);
+
+        for _ in 0..MAX_STACK {
+            assert!(inventory.insert_one(0, stone));
+        }
+        assert!(!inventory.insert_one(0, stone));
+        assert!(!inventory.insert_one(0, dirt));
+        assert!(!inventory.insert_one(2, dirt));
+        assert_eq!(inventory.slots()[0], Some(ItemStack { block: stone, count: MAX_STACK }));
+
+        for _ in 0..MAX_STACK {
+            assert_eq!(inventory.take_one(0), Some(stone));
+        }
+        assert_eq!(inventory.take_one(0), None);
+        assert_eq!(inventory.slots()[0], None);
+    }
+
+    #[test]
+    fn adds_onto_stacks_first() {
+        let stone = BlockType(5);
+        let dirt = BlockType(2);
+        let mut inventory = Inventory::new(3);
+
+        assert!(inventory.add(stone));
+        assert!(inventory.add(dirt));
+        for _ in 1..MAX_STACK + 1 {
+            assert!(inventory.add(stone));
+        }
+        assert_eq!(inventory.slots()[0], Some(ItemStack { block: stone, count: MAX_STACK }));
+        assert_eq!(inventory.slots()[2], Some(ItemStack { block: stone, count: 1 }));
+        assert!(!inventory.add(BlockType(7)));
+        assert_eq!(inventory.count(stone), MAX_STACK as u32 + 1);
+
+        assert!(inventory.remove(stone));
+        assert_eq!(inventory.slots()[2], None);
+        assert!(inventory.remove(dirt));
+        assert!(!inventory.remove(dirt));
+    }
+}
@@ -1,11 +1,14 @@
 use std::collections::HashMap;
 
-use crate::{rendering::{mesh::Mesh, textures::tex_cords_to_lin, vertex::{NORMAL_BACK, NORMAL_DOWN, NORMAL_FRONT, NORMAL_LEFT, NORMAL_RIGHT, NORMAL_UP, Vertex}}, settings::CHUNK_SIZE, world::{Coordinate, ThreeDimPos, WorldPos, block::{BlockSide, BlockType}, generation::{sample_elevation, sample_tree}}};
+use crate::{physics::collision::{FULL_BOX, LocalBox}, rendering::{mesh::Mesh, vertex::{NORMAL_UP, Vertex}}, settings::{CHUNK_SIZE, SECTION_HEIGHT, WORLD_MAX_Y, WORLD_MIN_Y}, world::{Coordinate, ThreeDimPos, WorldPos, block::{BlockSide, BlockType}, generation::{Generator, SEA_LEVEL, TERRAIN_BLOCKS}, shape::{ModelContext, Shape, box_face, cross_quads, on_block_edge}, state::Block, block_entity::BlockEntity, storage::BlockStorage, visibility::SectionVisibility}};
 
 const X: usize = CHUNK_SIZE;
-const Y: usize = 256;
+const Y: usize = (WORLD_MAX_Y - WORLD_MIN_Y) as usize;
 const Z: usize = CHUNK_SIZE;
 
+/// The number of sections stacked up in a chunk
+pub const SECTIONS: usize = Y / SECTION_HEIGHT;
+
 /// Calculates the coordinats of the chunk that the given coordinates fall into
 pub fn cords_to_chunk(position: WorldPos) -> WorldPos {
     fn cord_to_chunk(p: Coordinate) -> Coordinate {
@@ -25,24 +28,102 @@ pub fn cords_to_local(position: WorldPos) -> (usize, usize) {
     (local_x, local_z)
 }
 
-/// An individual chunk containing block data and its own 3D mesh.
-pub struct Chunk {
-    pub(super) blocks: [[[BlockType; Y]; Z]; X],
+/// The sections whose meshes depend on blocks at the given height: its own,
+/// plus the one above or below if the height is on a section boundary
+pub fn sections_touching(y: usize) -> Vec<usize> {
+    let section_i = y / SECTION_HEIGHT;
+    let mut out = vec![section_i];
+
+    if y.is_multiple_of(SECTION_HEIGHT) && section_i > 0 {
+        out.push(section_i - 1);
+    }
+    if y % SECTION_HEIGHT == SECTION_HEIGHT - 1 && section_i < SECTIONS - 1 {
+        out.push(section_i + 1);
+    }
+
+    out
+}
+
+/// Index of a block within a section's storage
+fn section_index(x: usize, y: usize, z: usize) -> usize {
+    (x * Z + z) * SECTION_HEIGHT + y
+}
+
+/// Calculates the chunk-local height of the given world Y coordinate. None if
+/// it is outside of the world.
+pub fn y_to_local(y: Coordinate) -> Option<usize> {
+    if (WORLD_MIN_Y..WORLD_MAX_Y).contains(&y) {
+        Some((y - WORLD_MIN_Y) as usize)
+    } else {
+        None
+    }
+}
+
+/// A `SECTION_HEIGHT` tall slice of a chunk, with its own blocks and mesh
+pub struct Section {
+    pub(super) blocks: BlockStorage,
+    pub(super) mesh: Mesh,
+    /// Which faces of the section can see each other, for occlusion culling
+    pub(super) visibility: SectionVisibility,
+    /// Whether the mesh is out of date with the blocks
+    dirty: bool,
+}
+
+impl Section {
+    fn new() -> Self {
+        Self {
+            blocks: BlockStorage::new(Block::AIR),
+            mesh: Mesh::new(),
+            visibility: SectionVisibility::OPEN,
+            dirty: true,
+        }
+    }
+
+    /// Whether the section has nothing but air in it
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_uniform(Block::AIR)
+    }
+
+    fn get(&self, x: usize, y: usize, z: usize) -> Block {
+        self.blocks.get(section_index(x, y, z))
+    }
+
+    fn set(&mut self, x: usize, y: usize, z: usize, block: Block) {
+        self.blocks.set(section_index(x, y, z), block);
+    }
 
+    /// Drops block data that is no longer needed
+    fn shrink(&mut self) {
+        self.blocks.compact();
+    }
+}
+
+/// An individual chunk containing block data and its own 3D meshes.
+pub struct Chunk {
     /// The world (block) position of the starting corner of the chunk
     pos: WorldPos,
     /// If a face of a block within this chunk should be highlighted, this
     /// contains the chunk-local block coordinate and the face of such block.
     pub highlighted: Option<(usize, usize, usize, BlockSide)>,
 
-    pub(super) mesh: Mesh,
+    /// The chunk's sections, from the bottom up
+    pub(super) sections: [Section; SECTIONS],
+    /// Extra data for the blocks that have it, by chunk-local coordinates
+    pub(super) block_entities: HashMap<(usize, usize, usize), BlockEntity>,
+    /// Whether the chunk has been edited since it was generated or saved
+    modified: bool,
+    /// Changes to blocks on the chunk's borders, as in `take_border_changes`
+    border_changes: Vec<(BlockSide, usize)>,
 }
 
 impl Chunk {
-    /// Creates a new chunk, starting at world coordinates X and Z. Errors if
-    /// X or Z are not divisible by `CHUNK_SIZE`.
-    pub fn new(chunk_pos: WorldPos, scratch: &mut HashMap<ThreeDimPos, BlockType>) ->
-        anyhow::Result<Self> {
+    /// Generates a new chunk, starting at world coordinates X and Z. Errors
+    /// if X or Z are not divisible by `CHUNK_SIZE`.
+    pub fn new(
+        chunk_pos: WorldPos,
+        generator: &Generator,
+        scratch: &mut HashMap<ThreeDimPos, BlockType>,
+    ) -> anyhow::Result<Self> {
 
         let (chunk_x, chunk_z) = chunk_pos;
 
@@ -51,38 +132,52 @@ impl Chunk {
                 chunk_x, chunk_z, CHUNK_SIZE);
         }
 
-        let mut blocks = [[[BlockType::Air; Y]; Z]; X];
+        let mut out = Self {
+            pos: (chunk_x, chunk_z),
+            highlighted: None,
+            sections: std::array::from_fn(|_| Section::new()),
+            block_entities: HashMap::new(),
+            modified: false,
+            border_changes: vec![],
+        };
 
+        let blocks = &*TERRAIN_BLOCKS;
         for x in 0..X {
             let w_x = (x as Coordinate) + chunk_x;
             for z in 0..Z {
                 let w_z = (z as Coordinate) + chunk_z;
 
-                let elevation = sample_elevation(w_x, w_z);
-                let tree = elevation >= 64 && sample_tree(w_x, w_z);
+                let elevation = generator.sample_elevation(w_x, w_z);
+                let tree = elevation >= SEA_LEVEL && generator.sample_tree(w_x, w_z);
+                let plant = if elevation > SEA_LEVEL && !tree {
+                    generator.sample_plant(w_x, w_z)
+                } else {
+                    None
+                };
 
                 for y in 0..Y {
-                    let pos_3d = (w_x, y as u8, w_z);
-                    let scratch_block = scratch.remove(&pos_3d);
-
-                    if scratch_block.is_some() {
-                        blocks[x][z][y] = scratch_block.unwrap();
+                    let w_y = y as Coordinate + WORLD_MIN_Y;
+                    let pos_3d = (w_x, w_y, w_z);
+                    if let Some(scratch_block) = scratch.remove(&pos_3d) {
+                        out.set(x, y, z, scratch_block.into());
                         continue;
                     }
 
-                    if y < elevation - 3 {
-                        blocks[x][z][y] = BlockType::Stone
-                    } else if y < elevation {
-                        blocks[x][z][y] = BlockType::Dirt
-                    } else if y == elevation && y <= 64 {
-                        blocks[x][z][y] = BlockType::Sand;
-                    } else if y == elevation {
-                        blocks[x][z][y] = BlockType::Grass
-                    } else if y > elevation && y <= 64 {
-                        blocks[x][z][y] = BlockType::Water;
-                    } else if y < elevation + 5 && tree {
-                        blocks[x][z][y] = BlockType::Log;
-                    } else if y == elevation + 5 && tree {
+                    if w_y < elevation - 3 {
+                        out.set(x, y, z, blocks.stone.into());
+                    } else if w_y < elevation {
+                        out.set(x, y, z, blocks.dirt.into());
+                    } else if w_y == elevation && w_y <= SEA_LEVEL {
+                        out.set(x, y, z, blocks.sand.into());
+                    } else if w_y == elevation {
+                        out.set(x, y, z, blocks.grass.into());
+                    } else if w_y > elevation && w_y <= SEA_LEVEL {
+                        out.set(x, y, z, blocks.water.into());
+                    } else if w_y < elevation + 5 && tree {
+                        out.set(x, y, z, blocks.log.into());
+                    } else if w_y == elevation + 1 && let Some(plant) = plant {
+                        out.set(x, y, z, plant.into());
+                    } else if w_y == elevation + 5 && tree {
                         const LEAVES_DIM: isize = 3;
                         let start_x = x as isize - LEAVES_DIM;
                         let start_z = z as isize - LEAVES_DIM;
@@ -95,15 +190,17 @@ impl Chunk {
                                     if leaf_x >= 0 && leaf_x < X as isize &&
                                        leaf_y < Y &&
                                        leaf_z >= 0 && leaf_z < Z as isize {
-                                        blocks[leaf_x as usize][leaf_z as usize][leaf_y] =
-                                            BlockType::Leaves;
+                                        out.set(
+                                            leaf_x as usize, leaf_y, leaf_z as usize,
+                                            blocks.leaves.into(),
+                                        );
                                     } else {
                                         let pos = (
                                             chunk_x + leaf_x as Coordinate,
-                                            leaf_y as u8,
+                                            leaf_y as Coordinate + WORLD_MIN_Y,
                                             chunk_z + leaf_z as Coordinate,
                                         );
-                                        scratch.insert(pos, BlockType::Leaves);
+                                        scratch.insert(pos, blocks.leaves);
                                     }
                                 }
                             }
@@ -113,246 +210,394 @@ impl Chunk {
             }
         }
 
-        let mut out = Self {
-            blocks,
-            pos: (chunk_x, chunk_z),
+        Ok(out)
+    }
+
+    /// Puts back a saved chunk, from its sections' block storage (bottom up)
+    /// and its block entities. None if there's the wrong number of sections.
+    pub(super) fn from_saved(
+        pos: WorldPos,
+        sections: Vec<BlockStorage>,
+        block_entities: HashMap<(usize, usize, usize), BlockEntity>,
+    ) -> Option<Self> {
+        let sections: Vec<Section> = sections.into_iter()
+            .map(|blocks| Section { blocks, ..Section::new() })
+            .collect();
+
+        Some(Self {
+            pos,
             highlighted: None,
-            mesh: Mesh::new(),
-        };
-        out.generate_mesh();
+            sections: sections.try_into().ok()?,
+            block_entities,
+            modified: false,
+            border_changes: vec![],
+        })
+    }
 
-        Ok(out)
+    /// The world position of the chunk's starting corner
+    pub fn pos(&self) -> WorldPos {
+        self.pos
     }
 
-    fn add_side(&mut self, x: usize, y: usize, z: usize, side: BlockSide) {
-        // Cull sides that face other blocks
-        if let Some((facing_x, facing_y, facing_z)) = match side {
-            // TODO: Check blocks in adjacent chunks
-            BlockSide::Bottom  if y > 0      => Some((x, y - 1, z)),
-            BlockSide::Top   if y < Y - 1  => Some((x, y + 1, z)),
-            BlockSide::Left   if x > 0      => Some((x - 1, y, z)),
-            BlockSide::Right  if x < X - 1  => Some((x + 1, y, z)),
-            BlockSide::Front if z > 0      => Some((x, y, z - 1)),
-            BlockSide::Back    if z < Z - 1  => Some((x, y, z + 1)),
-            _ => None,
-        } {
-            let facing = self.blocks[facing_x][facing_z][facing_y];
-            if facing.is_solid() || self.blocks[x][z][y] == facing {
-                return;
+    /// Whether the chunk has been edited since it was generated or saved
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    /// Notes that the chunk has been saved as it is now
+    pub fn mark_saved(&mut self) {
+        self.modified = false;
+    }
+
+    /// The block next to the given one. Blocks outside of the chunk are
+    /// looked up in `outside`, by world position.
+    fn neighbour(
+        &self,
+        x: usize, y: usize, z: usize,
+        side: BlockSide,
+        outside: &impl Fn(ThreeDimPos) -> Option<Block>,
+    ) -> Option<Block> {
+        let (d_x, d_y, d_z) = side.offset();
+        let n_x = x as Coordinate + d_x;
+        let n_y = y as Coordinate + d_y;
+        let n_z = z as Coordinate + d_z;
+
+        let in_chunk = (0..X as Coordinate).contains(&n_x) &&
+            (0..Y as Coordinate).contains(&n_y) &&
+            (0..Z as Coordinate).contains(&n_z);
+        if in_chunk {
+            Some(self.get(n_x as usize, n_y as usize, n_z as usize))
+        } else {
+            outside((n_x + self.pos.0, n_y + WORLD_MIN_Y, n_z + self.pos.1))
+        }
+    }
+
+    /// Adds the model of the given block to its section's mesh
+    fn add_block(
+        &mut self,
+        x: usize, y: usize, z: usize,
+        textures: &impl Fn(BlockType, BlockSide) -> Option<u32>,
+        outside: &impl Fn(ThreeDimPos) -> Option<Block>,
+    ) {
+        let block = self.get(x, y, z);
+        match block.kind.shape() {
+            Shape::Cube => {
+                for side in BlockSide::ALL {
+                    self.add_side((x, y, z), block, side, FULL_BOX, textures, outside);
+                }
+            },
+            Shape::Cross => self.add_cross(x, y, z, textures),
+            shape => {
+                let ctx = ModelContext::new(block, |side| self.neighbour(x, y, z, side, outside));
+                for local in shape.model_boxes(ctx).iter() {
+                    for side in BlockSide::ALL {
+                        self.add_side((x, y, z), block, side, *local, textures, outside);
+                    }
+                }
+            },
+        }
+
+        // The water around the model
+        if block.state.is_waterlogged() {
+            let water = Block::new(TERRAIN_BLOCKS.water);
+            for side in BlockSide::ALL {
+                self.add_side((x, y, z), water, side, FULL_BOX, textures, outside);
             }
-        } else if let BlockSide::Bottom = side {
+        }
+    }
+
+    /// Adds one side of a box within the given block, drawn as `current`,
+    /// unless it's hidden
+    fn add_side(
+        &mut self,
+        (x, y, z): (usize, usize, usize),
+        current: Block,
+        side: BlockSide,
+        local: LocalBox,
+        textures: &impl Fn(BlockType, BlockSide) -> Option<u32>,
+        outside: &impl Fn(ThreeDimPos) -> Option<Block>,
+    ) {
+        let Some((texture, turned)) = current.texture(side, textures) else {
             return;
+        };
+
+        // Cull sides that face other blocks
+        if on_block_edge(side, local) {
+            match self.neighbour(x, y, z, side, outside) {
+                Some(facing) => {
+                    let cube = current.kind.shape() == Shape::Cube;
+                    let same_cube = cube && facing.kind == current.kind;
+                    // Fluids merge with the fluid in waterlogged blocks
+                    let same_fluid = cube && current.kind.is_fluid() &&
+                        facing.state.is_waterlogged();
+                    if facing.kind.occludes() || same_cube || same_fluid {
+                        return;
+                    }
+                },
+                None if side == BlockSide::Bottom => return,
+                None => {},
+            }
+        }
+
+        let mut corners = box_face(side, local);
+        if turned {
+            for (_, [u, v]) in &mut corners {
+                (*u, *v) = (1.0 - *v, *u);
+            }
         }
+        self.add_quad((x, y, z), corners, side.normal(), texture, false);
+    }
 
-        let x_f = (x as Coordinate + self.pos.0) as f32;
-        let z_f = (z as Coordinate + self.pos.1) as f32;
-        let y_f = y as f32;
-        let t_opt = self.blocks[x][z][y].texture(side);
+    /// Adds the crossed sprites of the given block
+    fn add_cross(
+        &mut self,
+        x: usize, y: usize, z: usize,
+        textures: &impl Fn(BlockType, BlockSide) -> Option<u32>,
+    ) {
+        let Some(texture) = textures(self.get(x, y, z).kind, BlockSide::Front) else {
+            return;
+        };
+
+        for corners in cross_quads() {
+            // Lit like the ground they stand on
+            self.add_quad((x, y, z), corners, NORMAL_UP, texture, true);
+        }
+    }
 
+    /// Adds a quad with the given block-local corners (as laid out by
+    /// `box_face`) to the mesh of the given block's section. Double sided
+    /// quads are visible from the back too.
+    fn add_quad(
+        &mut self,
+        (x, y, z): (usize, usize, usize),
+        corners: [([f32; 3], [f32; 2]); 4],
+        normal: [f32; 3],
+        texture: u32,
+        double_sided: bool,
+    ) {
         let block = [
             x as Coordinate + self.pos.0,
-            y as Coordinate,
+            y as Coordinate + WORLD_MIN_Y,
             z as Coordinate + self.pos.1,
         ];
+        let corner = block.map(|c| c as f32);
+
+        let verticies = corners.map(|(position, texture_cords)| Vertex {
+            position: [
+                corner[0] + position[0],
+                corner[1] + position[1],
+                corner[2] + position[2],
+            ],
+            texture_cords,
+            normal,
+            block,
+            texture,
+        });
+
+        let mesh = &mut self.sections[y / SECTION_HEIGHT].mesh;
+        let start_index = mesh.verticies.len() as u32;
+        mesh.verticies.extend(verticies);
+        mesh.indicies.extend([
+            start_index + 3, start_index + 2, start_index,
+            start_index + 3, start_index, start_index + 1
+        ]);
 
-        if let None = t_opt {
+        if double_sided {
+            mesh.indicies.extend([
+                start_index, start_index + 2, start_index + 3,
+                start_index + 1, start_index, start_index + 3,
+            ]);
+        }
+    }
+
+    /// Throws out the mesh of the given section and regenerates it, with
+    /// block faces textured by the given lookup. Blocks across the chunk's
+    /// borders are looked up in `outside`, by world position.
+    fn update_section_mesh(
+        &mut self,
+        section_i: usize,
+        textures: &impl Fn(BlockType, BlockSide) -> Option<u32>,
+        outside: &impl Fn(ThreeDimPos) -> Option<Block>,
+    ) {
+        let section = &mut self.sections[section_i];
+        section.shrink();
+        section.mesh = Mesh::new();
+        section.dirty = false;
+
+        if section.is_empty() {
+            section.visibility = SectionVisibility::OPEN;
             return;
         }
-        let (t_x, t_y) = t_opt.unwrap();
-
-        let verticies = match side {
-            BlockSide::Front => [
-                Vertex { // BL
-                    position: [x_f, y_f, z_f],
-                    texture_cords: tex_cords_to_lin(t_x+1, t_y+1),
-                    normal: NORMAL_FRONT,
-                    block,
-                },
-                Vertex { // TL
-                    position: [x_f, y_f + 1.0, z_f],
-                    texture_cords: tex_cords_to_lin(t_x+1, t_y),
-                    normal: NORMAL_FRONT,
-                    block,
-                },
-                Vertex { // BR
-                    position: [x_f + 1.0, y_f, z_f],
-                    texture_cords: tex_cords_to_lin(t_x, t_y+1),
-                    normal: NORMAL_FRONT,
-                    block,
-                },
-                Vertex { // TR
-                    position: [x_f + 1.0, y_f + 1.0, z_f],
-                    texture_cords: tex_cords_to_lin(t_x, t_y),
-                    normal: NORMAL_FRONT,
-                    block,
-                },
-            ],
 
-            BlockSide::Back => [
-                Vertex { // BL
-                    position: [x_f + 1.0, y_f, z_f + 1.0],
-                    texture_cords: tex_cords_to_lin(t_x+1, t_y+1),
-                    normal: NORMAL_BACK,
-                    block,
-                },
-                Vertex { // TL
-                    position: [x_f + 1.0, y_f + 1.0, z_f + 1.0],
-                    texture_cords: tex_cords_to_lin(t_x+1, t_y),
-                    normal: NORMAL_BACK,
-                    block,
-                },
-                Vertex { // BR
-                    position: [x_f, y_f, z_f + 1.0],
-                    texture_cords: tex_cords_to_lin(t_x, t_y+1),
-                    normal: NORMAL_BACK,
-                    block,
-                },
-                Vertex { // TR
-                    position: [x_f, y_f + 1.0, z_f + 1.0],
-                    texture_cords: tex_cords_to_lin(t_x, t_y),
-                    normal: NORMAL_BACK,
-                    block,
-                },
-            ],
+        section.visibility = SectionVisibility::compute(|x, y, z| {
+            !section.get(x, y, z).kind.occludes()
+        });
 
+        let base_y = section_i * SECTION_HEIGHT;
+        for x in 0..X {
+            for y in base_y..base_y + SECTION_HEIGHT {
+                for z in 0..Z {
+                    self.add_block(x, y, z, textures, outside);
+                }
+            }
+        }
+    }
 
-            BlockSide::Top => [
-                Vertex { // BL
-                    position: [x_f, y_f + 1.0, z_f],
-                    texture_cords: tex_cords_to_lin(t_x+1, t_y+1),
-                    normal: NORMAL_UP,
-                    block,
-                },
-                Vertex { // TL
-                    position: [x_f, y_f + 1.0, z_f + 1.0],
-                    texture_cords: tex_cords_to_lin(t_x+1, t_y),
-                    normal: NORMAL_UP,
-                    block,
-                },
-                Vertex { // BR
-                    position: [x_f + 1.0, y_f + 1.0, z_f],
-                    texture_cords: tex_cords_to_lin(t_x, t_y+1),
-                    normal: NORMAL_UP,
-                    block,
-                },
-                Vertex { // TR
-                    position: [x_f + 1.0, y_f + 1.0, z_f + 1.0],
-                    texture_cords: tex_cords_to_lin(t_x, t_y),
-                    normal: NORMAL_UP,
-                    block,
-                },
-            ],
+    /// Regenerates the meshes of the sections that changed since they were
+    /// last meshed, as in `update_section_mesh`
+    pub fn update_meshes(
+        &mut self,
+        textures: &impl Fn(BlockType, BlockSide) -> Option<u32>,
+        outside: &impl Fn(ThreeDimPos) -> Option<Block>,
+    ) {
+        for i in 0..SECTIONS {
+            if self.sections[i].dirty {
+                self.update_section_mesh(i, textures, outside);
+            }
+        }
+    }
 
-            BlockSide::Bottom => [
-                Vertex { // BL
-                    position: [x_f, y_f, z_f + 1.0],
-                    texture_cords: tex_cords_to_lin(t_x+1, t_y+1),
-                    normal: NORMAL_DOWN,
-                    block,
-                },
-                Vertex { // TL
-                    position: [x_f, y_f, z_f],
-                    texture_cords: tex_cords_to_lin(t_x+1, t_y),
-                    normal: NORMAL_DOWN,
-                    block,
-                },
-                Vertex { // BR
-                    position: [x_f + 1.0, y_f, z_f + 1.0],
-                    texture_cords: tex_cords_to_lin(t_x, t_y+1),
-                    normal: NORMAL_DOWN,
-                    block,
-                },
-                Vertex { // TR
-                    position: [x_f + 1.0, y_f, z_f],
-                    texture_cords: tex_cords_to_lin(t_x, t_y),
-                    normal: NORMAL_DOWN,
-                    block,
-                },
-            ],
+    /// Whether any section's mesh is out of date
+    pub fn needs_meshing(&self) -> bool {
+        self.sections.iter().any(|s| s.dirty)
+    }
 
-            BlockSide::Left => [
-                Vertex { // BL
-                    position: [x_f, y_f, z_f + 1.0],
-                    texture_cords: tex_cords_to_lin(t_x+1, t_y+1),
-                    normal: NORMAL_LEFT,
-                    block,
-                },
-                Vertex { // TL
-                    position: [x_f, y_f + 1.0, z_f + 1.0],
-                    texture_cords: tex_cords_to_lin(t_x+1, t_y),
-                    normal: NORMAL_LEFT,
-                    block,
-                },
-                Vertex { // BR
-                    position: [x_f, y_f, z_f],
-                    texture_cords: tex_cords_to_lin(t_x, t_y+1),
-                    normal: NORMAL_LEFT,
-                    block,
-                },
-                Vertex { // TR
-                    position: [x_f, y_f + 1.0, z_f],
-                    texture_cords: tex_cords_to_lin(t_x, t_y),
-                    normal: NORMAL_LEFT,
-                    block,
-                },
-            ],
+    /// Has every mesh regenerated by the next `update_meshes`
+    pub fn mark_all_dirty(&mut self) {
+        for section in &mut self.sections {
+            section.dirty = true;
+        }
+    }
 
-            BlockSide::Right => [
-                Vertex { // BL
-                    position: [x_f + 1.0, y_f, z_f],
-                    texture_cords: tex_cords_to_lin(t_x+1, t_y+1),
-                    normal: NORMAL_RIGHT,
-                    block,
-                },
-                Vertex { // TL
-                    position: [x_f + 1.0, y_f + 1.0, z_f],
-                    texture_cords: tex_cords_to_lin(t_x+1, t_y),
-                    normal: NORMAL_RIGHT,
-                    block,
-                },
-                Vertex { // BR
-                    position: [x_f + 1.0, y_f, z_f + 1.0],
-                    texture_cords: tex_cords_to_lin(t_x, t_y+1),
-                    normal: NORMAL_RIGHT,
-                    block,
-                },
-                Vertex { // TR
-                    position: [x_f + 1.0, y_f + 1.0, z_f + 1.0],
-                    texture_cords: tex_cords_to_lin(t_x, t_y),
-                    normal: NORMAL_RIGHT,
-                    block,
-                },
-            ],
-        };
+    /// Has the given section's mesh regenerated by the next `update_meshes`
+    pub fn mark_dirty(&mut self, section_i: usize) {
+        self.sections[section_i].dirty = true;
+    }
 
-        let start_index = self.mesh.verticies.len() as u32;
-        let indicies = [
-            start_index + 3, start_index + 2, start_index,
-            start_index + 3, start_index, start_index + 1
+    /// Has the meshes affected by a change to the block at the given
+    /// chunk-local height regenerated by the next `update_meshes`
+    pub fn mark_dirty_at(&mut self, y: usize) {
+        for section_i in sections_touching(y) {
+            self.mark_dirty(section_i);
+        }
+    }
+
+    /// Has the meshes affected by a change to the block at the given
+    /// chunk-local coordinates regenerated, noting which neighbouring chunks
+    /// need theirs regenerated too
+    pub fn mark_changed(&mut self, x: usize, y: usize, z: usize) {
+        self.mark_dirty_at(y);
+
+        let borders = [
+            (BlockSide::Left, x == 0),
+            (BlockSide::Right, x == X - 1),
+            (BlockSide::Front, z == 0),
+            (BlockSide::Back, z == Z - 1),
         ];
+        for (side, on_border) in borders {
+            if on_border {
+                self.border_changes.push((side, y));
+            }
+        }
+    }
 
-        self.mesh.verticies.extend(verticies);
-        self.mesh.indicies.extend(indicies);
+    /// The changes to blocks on the chunk's borders since this was last
+    /// called, as the side of the chunk they were on and their chunk-local
+    /// height
+    pub fn take_border_changes(&mut self) -> Vec<(BlockSide, usize)> {
+        std::mem::take(&mut self.border_changes)
     }
 
-    fn generate_mesh(&mut self) {
-        for x in 0..X {
-            for y in 0..Y {
-                for z in 0..Z {
-                    self.add_side(x, y, z, BlockSide::Front);
-                    self.add_side(x, y, z, BlockSide::Back);
-                    self.add_side(x, y, z, BlockSide::Top);
-                    self.add_side(x, y, z, BlockSide::Bottom);
-                    self.add_side(x, y, z, BlockSide::Left);
-                    self.add_side(x, y, z, BlockSide::Right);
-                }
+    /// Approximate memory used by the chunk's block data, in bytes
+    pub fn block_memory(&self) -> usize {
+        self.sections.iter().map(|s| s.blocks.heap_size()).sum()
+    }
+
+    /// Gets the block at the given chunk-local coordinates
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Block {
+        self.sections[y / SECTION_HEIGHT].get(x, y % SECTION_HEIGHT, z)
+    }
+
+    /// Sets the block at the given chunk-local coordinates. Doesn't update
+    /// the mesh.
+    pub fn set(&mut self, x: usize, y: usize, z: usize, block: Block) {
+        self.sections[y / SECTION_HEIGHT].set(x, y % SECTION_HEIGHT, z, block);
+    }
+
+    /// Changes the block at the given chunk-local coordinates as an edit to
+    /// the world. Unlike `set`, block entities follow the new block, the
+    /// chunk gets saved, and the affected meshes get updated. Containers with
+    /// items in them are left alone, returning false.
+    pub fn edit(&mut self, x: usize, y: usize, z: usize, block: Block) -> bool {
+        if self.get(x, y, z).kind != block.kind {
+            if self.holds_items(x, y, z) {
+                return false;
+            }
+            self.block_entities.remove(&(x, y, z));
+            if let Some(entity) = BlockEntity::for_block(block.kind) {
+                self.block_entities.insert((x, y, z), entity);
             }
         }
+
+        self.set(x, y, z, block);
+        self.modified = true;
+        self.mark_changed(x, y, z);
+        true
+    }
+
+    /// Whether the block at the given chunk-local coordinates is a container
+    /// with items in it
+    pub fn holds_items(&self, x: usize, y: usize, z: usize) -> bool {
+        match self.block_entities.get(&(x, y, z)) {
+            Some(BlockEntity::Container(inventory)) => !inventory.is_empty(),
+            None => false,
+        }
+    }
+
+    /// The extra data for the block at the given chunk-local coordinates
+    pub fn block_entity_mut(&mut self, x: usize, y: usize, z: usize) -> Option<&mut BlockEntity> {
+        let entity = self.block_entities.get_mut(&(x, y, z))?;
+        // Assume whoever asked is about to change it
+        self.modified = true;
+        Some(entity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{settings::BLOCK_REGISTRY, world::block::load_registry};
+    use super::*;
+
+    /// Vertices in the mesh of the section holding the given height
+    fn mesh_size(chunk: &mut Chunk, y: usize, outside: impl Fn(ThreeDimPos) -> Option<Block>) -> usize {
+        chunk.mark_all_dirty();
+        chunk.update_meshes(&|_, _| Some(0), &outside);
+        chunk.sections[y / SECTION_HEIGHT].mesh.verticies.len()
     }
 
-    /// Throws out any existing mesh and regenerates it
-    pub fn update_mesh(&mut self) {
-        self.mesh = Mesh::new();
-        self.generate_mesh();
+    #[test]
+    fn fence_connects_across_border() {
+        load_registry(BLOCK_REGISTRY).unwrap();
+        let fence = Block::new(BlockType::from_name("fence").unwrap());
+
+        let sections = (0..SECTIONS).map(|_| BlockStorage::new(Block::AIR)).collect();
+        let mut chunk = Chunk::from_saved((0, 0), sections, HashMap::new()).unwrap();
+        chunk.set(X - 1, 100, 5, fence);
+
+        let alone = mesh_size(&mut chunk, 100, |_| None);
+        let beside = (X as Coordinate, 100 + WORLD_MIN_Y, 5);
+        let connected = mesh_size(&mut chunk, 100, |pos| (pos == beside).then_some(fence));
+        assert!(connected > alone);
+    }
+
+    #[test]
+    fn border_changes_are_noted() {
+        let sections = (0..SECTIONS).map(|_| BlockStorage::new(Block::AIR)).collect();
+        let mut chunk = Chunk::from_saved((0, 0), sections, HashMap::new()).unwrap();
+
+        chunk.mark_changed(0, 40, Z - 1);
+        chunk.mark_changed(5, 41, 5);
+        assert_eq!(chunk.take_border_changes(), [(BlockSide::Left, 40), (BlockSide::Back, 40)]);
+        assert!(chunk.take_border_changes().is_empty());
     }
 }
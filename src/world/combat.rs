@@ -0,0 +1,110 @@
+use cgmath::{InnerSpace, Point3, Vector2};
+use crate::{physics::Entity, settings::{HURT_COOLDOWN, HURT_FLASH_TIME, KNOCKBACK_SPEED, KNOCKBACK_UP, PHYSICS_TICK_RATE}};
+
+/// How much more damage something can take, and how recently it was hurt
+#[derive(Copy, Clone, Debug)]
+pub struct Health {
+    current: f32,
+    max: f32,
+    /// Seconds since last being hurt
+    since_hurt: f32,
+}
+
+impl Health {
+    /// Full health, out of the given maximum
+    pub fn new(max: f32) -> Self {
+        Self {
+            current: max,
+            max,
+            since_hurt: f32::INFINITY,
+        }
+    }
+
+    pub fn current(&self) -> f32 {
+        self.current
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.current >= self.max
+    }
+
+    /// Restores the given amount of health, up to the maximum
+    pub fn heal(&mut self, amount: f32) {
+        self.current = (self.current + amount).min(self.max);
+    }
+
+    /// Moves time on by a physics tick
+    pub fn tick(&mut self) {
+        self.since_hurt += 1.0 / PHYSICS_TICK_RATE;
+    }
+
+    /// Takes the given amount of damage, unless it was hurt too recently.
+    /// Returns whether it was hurt.
+    pub fn hurt(&mut self, amount: f32) -> bool {
+        if self.since_hurt < HURT_COOLDOWN {
+            return false;
+        }
+
+        self.current -= amount;
+        self.since_hurt = 0.0;
+        true
+    }
+
+    /// Takes the given amount of damage no matter what, without counting as
+    /// being hurt, for damage taken a bit at a time
+    pub fn drain(&mut self, amount: f32) {
+        self.current -= amount;
+    }
+
+    /// Back to full health
+    pub fn reset(&mut self) {
+        *self = Self::new(self.max);
+    }
+
+    /// Whether it was hurt recently enough to flash red, and to still be
+    /// reeling from the knockback
+    pub fn is_flashing(&self) -> bool {
+        self.since_hurt < HURT_FLASH_TIME
+    }
+}
+
+/// Knocks the given entity away from the given point, and up a bit
+pub fn knock_back(entity: &mut impl Entity, from: Point3<f32>) {
+    let pos = entity.get_precise_pos();
+    let away = Vector2::new(pos.x - from.x, pos.z - from.z);
+    let away = if away.magnitude2() > 1.0e-6 { away.normalize() } else { Vector2::new(0.0, 0.0) };
+
+    let mut velocity = entity.get_velocity();
+    velocity.x = away.x * KNOCKBACK_SPEED;
+    velocity.y = velocity.y.max(KNOCKBACK_UP);
+    velocity.z = away.y * KNOCKBACK_SPEED;
+    entity.set_velocity(velocity);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hurting_has_a_cooldown() {
+        let mut health = Health::new(20.0);
+        assert!(health.hurt(5.0));
+        assert!(health.is_flashing());
+        assert!(!health.hurt(5.0));
+        assert_eq!(health.current(), 15.0);
+
+        for _ in 0..(HURT_COOLDOWN * PHYSICS_TICK_RATE) as usize + 1 {
+            health.tick();
+        }
+        assert!(!health.is_flashing());
+        assert!(health.hurt(15.0));
+        assert!(health.is_dead());
+
+        health.reset();
+        assert_eq!(health.current(), 20.0);
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use cgmath::{InnerSpace, MetricSpace, Point2, Vector3};
+use crate::{rendering::{mesh::Mesh, vertex::{NO_BLOCK, NORMAL_UP, Vertex}}, settings::{CHUNK_SIZE, FAR_BASE_STEP, FAR_TERRAIN_DIST, FAR_TILE_SIZE, FAR_TILES_PER_FRAME}, world::{Coordinate, WorldPos, block::{BlockSide, BlockType}, chunk::cords_to_chunk, generation::{Generator, SEA_LEVEL, TERRAIN_BLOCKS}}};
+
+/// The circle (center, radius) around the given position that is always
+/// covered by loaded chunks, with the given render distance. Far terrain
+/// isn't drawn inside of it.
+pub fn loaded_circle(pos: WorldPos, render_dist: usize) -> (Point2<f32>, f32) {
+    let (chunk_x, chunk_z) = cords_to_chunk(pos);
+    let half_chunk = CHUNK_SIZE as f32 / 2.0;
+    let center = Point2::new(chunk_x as f32 + half_chunk, chunk_z as f32 + half_chunk);
+
+    // Chunks are loaded by the distance of their corner from the player's
+    // chunk, so stay a chunk's diagonal inside of that
+    let radius = ((render_dist - 2) * CHUNK_SIZE) as f32;
+    (center, radius)
+}
+
+/// A square of far terrain
+struct FarTile {
+    /// Blocks between heightmap samples
+    step: usize,
+    mesh: Mesh,
+}
+
+/// Coarse heightmap meshes of the terrain past the loaded chunks, built
+/// straight from the terrain generator at lower resolutions further away.
+pub struct FarTerrain {
+    /// Tiles by the world position of their starting corner
+    tiles: HashMap<WorldPos, FarTile>,
+}
+
+impl FarTerrain {
+    pub fn new() -> Self {
+        Self {
+            tiles: HashMap::new(),
+        }
+    }
+
+    /// Drops tiles that are out of range and builds (or rebuilds, at a new
+    /// resolution) up to `FAR_TILES_PER_FRAME` of the nearest ones that need
+    /// it, textured by the given lookup
+    pub fn update(
+        &mut self,
+        pos: WorldPos,
+        render_dist: usize,
+        generator: &Generator,
+        textures: &impl Fn(BlockType, BlockSide) -> Option<u32>,
+    ) {
+        const TILE: Coordinate = FAR_TILE_SIZE as Coordinate;
+        const TILE_RANGE: Coordinate = (FAR_TERRAIN_DIST / FAR_TILE_SIZE) as Coordinate;
+
+        let (center, hole_radius) = loaded_circle(pos, render_dist);
+        let center_tile = (pos.0.div_euclid(TILE), pos.1.div_euclid(TILE));
+
+        let mut wanted = HashMap::new();
+        for t_x in -TILE_RANGE..=TILE_RANGE {
+            for t_z in -TILE_RANGE..=TILE_RANGE {
+                let tile_x = (center_tile.0 + t_x) * TILE;
+                let tile_z = (center_tile.1 + t_z) * TILE;
+
+                let half_tile = FAR_TILE_SIZE as f32 / 2.0;
+                let tile_center =
+                    Point2::new(tile_x as f32 + half_tile, tile_z as f32 + half_tile);
+                let dist = tile_center.distance(center);
+                if dist - half_tile > FAR_TERRAIN_DIST as f32 {
+                    continue;
+                }
+
+                // Entirely covered by loaded chunks
+                if dist + half_tile * 2.0_f32.sqrt() < hole_radius {
+                    continue;
+                }
+
+                let ring = t_x.abs().max(t_z.abs()).max(1) as usize;
+                let step = (FAR_BASE_STEP << ring.ilog2()).min(FAR_TILE_SIZE / 4);
+                wanted.insert((tile_x, tile_z), (step, dist));
+            }
+        }
+
+        self.tiles.retain(|pos, _| wanted.contains_key(pos));
+
+        let mut to_build: Vec<(WorldPos, usize, f32)> = wanted.into_iter()
+            .filter(|(pos, (step, _))| {
+                self.tiles.get(pos).is_none_or(|tile| tile.step != *step)
+            })
+            .map(|(pos, (step, dist))| (pos, step, dist))
+            .collect();
+        to_build.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+        for (pos, step, _) in to_build.into_iter().take(FAR_TILES_PER_FRAME) {
+            self.tiles.insert(pos, FarTile {
+                step,
+                mesh: build_tile(pos, step, generator, textures),
+            });
+        }
+    }
+
+    /// Drops every tile, so they're all built again
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+    }
+
+    pub fn get_meshes_mut(&mut self) -> impl Iterator<Item = &mut Mesh> {
+        self.tiles.values_mut().map(|t| &mut t.mesh)
+    }
+}
+
+/// The height of the far terrain surface and the block it's made of
+fn sample_surface(generator: &Generator, x: Coordinate, z: Coordinate) -> (f32, BlockType) {
+    let elevation = generator.sample_elevation(x, z);
+    let blocks = &*TERRAIN_BLOCKS;
+    let block = if elevation < SEA_LEVEL {
+        blocks.water
+    } else if elevation == SEA_LEVEL {
+        blocks.sand
+    } else {
+        blocks.grass
+    };
+
+    // A block lower than the real surface, so loaded chunks win where the
+    // two overlap
+    (elevation.max(SEA_LEVEL) as f32, block)
+}
+
+/// Builds the heightmap mesh for the tile starting at the given corner
+fn build_tile(
+    pos: WorldPos,
+    step: usize,
+    generator: &Generator,
+    textures: &impl Fn(BlockType, BlockSide) -> Option<u32>,
+) -> Mesh {
+    let samples = FAR_TILE_SIZE / step + 1;
+    let mut surface = Vec::with_capacity(samples * samples);
+    for i in 0..samples {
+        for j in 0..samples {
+            surface.push(sample_surface(
+                generator,
+                pos.0 + (i * step) as Coordinate,
+                pos.1 + (j * step) as Coordinate,
+            ));
+        }
+    }
+
+    let point = |i: usize, j: usize| {
+        let (height, _) = surface[i * samples + j];
+        [
+            (pos.0 + (i * step) as Coordinate) as f32,
+            height,
+            (pos.1 + (j * step) as Coordinate) as f32,
+        ]
+    };
+
+    let mut mesh = Mesh::new();
+    for i in 0..samples - 1 {
+        for j in 0..samples - 1 {
+            let (_, block) = surface[i * samples + j];
+            // Left out, like chunk faces, if the registry doesn't draw it
+            let Some(texture) = textures(block, BlockSide::Top) else {
+                continue;
+            };
+
+            let corners = [point(i, j), point(i, j + 1), point(i + 1, j), point(i + 1, j + 1)];
+            let step_f = step as f32;
+            let normal = Vector3::new(
+                corners[0][1] - corners[2][1],
+                step_f,
+                corners[0][1] - corners[1][1],
+            ).normalize();
+
+            add_quad(&mut mesh, corners, normal.into(), texture, false);
+        }
+    }
+
+    // Skirts hanging down from the edges, to hide cracks against tiles of a
+    // different resolution
+    let skirt = (step * 2) as f32;
+    let last = samples - 1;
+    let edges = [
+        (0..last).map(|k| (0, k)).collect::<Vec<_>>(),
+        (0..last).map(|k| (last, k)).collect(),
+        (0..last).map(|k| (k, 0)).collect(),
+        (0..last).map(|k| (k, last)).collect(),
+    ];
+    for (n, edge) in edges.into_iter().enumerate() {
+        for (i, j) in edge {
+            let (next_i, next_j) = if n < 2 { (i, j + 1) } else { (i + 1, j) };
+            let (_, block) = surface[i * samples + j];
+            let Some(texture) = textures(block, BlockSide::Top) else {
+                continue;
+            };
+
+            let top_a = point(i, j);
+            let top_b = point(next_i, next_j);
+            let bottom_a = [top_a[0], top_a[1] - skirt, top_a[2]];
+            let bottom_b = [top_b[0], top_b[1] - skirt, top_b[2]];
+
+            add_quad(
+                &mut mesh,
+                [bottom_a, top_a, bottom_b, top_b],
+                NORMAL_UP,
+                texture,
+                true,
+            );
+        }
+    }
+
+    mesh
+}
+
+/// Adds a quad, laid out like a block's top face. Double sided quads are
+/// visible from the back too.
+fn add_quad(
+    mesh: &mut Mesh,
+    corners: [[f32; 3]; 4],
+    normal: [f32; 3],
+    texture: u32,
+    double_sided: bool,
+) {
+    let start_index = mesh.verticies.len() as u32;
+    mesh.verticies.extend(corners.map(|position| Vertex {
+        position,
+        // Tiled once per block, like real terrain
+        texture_cords: [position[0], position[2]],
+        normal,
+        block: NO_BLOCK,
+        texture,
+    }));
+
+    mesh.indicies.extend([
+        start_index + 3, start_index + 2, start_index,
+        start_index + 3, start_index, start_index + 1,
+    ]);
+
+    if double_sided {
+        mesh.indicies.extend([
+            start_index, start_index + 2, start_index + 3,
+            start_index + 1, start_index, start_index + 3,
+        ]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{settings::BLOCK_REGISTRY, world::block::load_registry};
+    use super::*;
+
+    #[test]
+    fn untextured_surfaces_are_left_out() {
+        load_registry(BLOCK_REGISTRY).unwrap();
+        let generator = Generator::new(613);
+
+        let mesh = build_tile((0, 0), FAR_TILE_SIZE, &generator, &|_, _| None);
+        assert!(mesh.verticies.is_empty());
+        let mesh = build_tile((0, 0), FAR_TILE_SIZE, &generator, &|_, _| Some(1));
+        assert!(!mesh.verticies.is_empty());
+    }
+}
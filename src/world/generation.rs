@@ -1,30 +1,103 @@
-use std::hash::{DefaultHasher, Hash, Hasher};
+use std::{hash::{DefaultHasher, Hash, Hasher}, sync::LazyLock};
 use noise::{NoiseFn, Perlin};
 use rand::{Rng, SeedableRng, rngs::StdRng};
-use crate::{settings::SEED, world::Coordinate};
+use crate::world::{Coordinate, block::BlockType};
 
 const FREQ: f64 = 0.01;
 
-pub fn sample_elevation(x: Coordinate, y: Coordinate) -> usize {
-    let sampler = Perlin::new(SEED);
+/// The height that oceans and lakes fill up to
+pub const SEA_LEVEL: Coordinate = 64;
 
-    let f_x = x as f64 * FREQ;
-    let f_y = y as f64 * FREQ;
+/// The blocks terrain is built from, looked up in the block registry
+pub struct TerrainBlocks {
+    pub stone: BlockType,
+    pub dirt: BlockType,
+    pub sand: BlockType,
+    pub grass: BlockType,
+    pub water: BlockType,
+    pub log: BlockType,
+    pub leaves: BlockType,
+    pub tall_grass: BlockType,
+    pub flower: BlockType,
+    pub melon: BlockType,
+}
+
+pub static TERRAIN_BLOCKS: LazyLock<TerrainBlocks> = LazyLock::new(|| {
+    let block = |name| BlockType::from_name(name).unwrap_or_else(|| {
+        panic!("Block registry has no \"{}\" block, which terrain generation needs", name)
+    });
 
-    let elev = 70.0 + 
-        sampler.get([f_x * 0.5, f_y * 0.5, 2.0]) * 30.0 +
-        sampler.get([f_x, f_y, 0.0]) * 20.0 +
-        sampler.get([f_x * 5.0, f_y * 5.0, 1.0]) * 5.0;
+    TerrainBlocks {
+        stone: block("stone"),
+        dirt: block("dirt"),
+        sand: block("sand"),
+        grass: block("grass"),
+        water: block("water"),
+        log: block("log"),
+        leaves: block("leaves"),
+        tall_grass: block("tall_grass"),
+        flower: block("flower"),
+        melon: block("melon"),
+    }
+});
 
-    elev.round() as usize
+/// Samples a world's terrain from its seed. Kept around for the whole
+/// world, as far terrain samples elevation a lot and setting up a sampler
+/// isn't free.
+pub struct Generator {
+    seed: u32,
+    sampler: Perlin,
 }
 
-pub fn sample_tree(x: Coordinate, y: Coordinate) -> bool {
-    let mut hasher = DefaultHasher::new();
-    SEED.hash(&mut hasher);
-    x.hash(&mut hasher);
-    y.hash(&mut hasher);
+impl Generator {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            seed,
+            sampler: Perlin::new(seed),
+        }
+    }
+
+    pub fn sample_elevation(&self, x: Coordinate, y: Coordinate) -> Coordinate {
+        let sampler = &self.sampler;
+
+        let f_x = x as f64 * FREQ;
+        let f_y = y as f64 * FREQ;
+
+        let elev = 70.0 + 
+            sampler.get([f_x * 0.5, f_y * 0.5, 2.0]) * 30.0 +
+            sampler.get([f_x, f_y, 0.0]) * 20.0 +
+            sampler.get([f_x * 5.0, f_y * 5.0, 1.0]) * 5.0;
+
+        elev.round() as Coordinate
+    }
+
+    /// A random number generator unique to the given position (and `salt`)
+    fn position_rng(&self, x: Coordinate, y: Coordinate, salt: impl Hash) -> StdRng {
+        let mut hasher = DefaultHasher::new();
+        self.seed.hash(&mut hasher);
+        x.hash(&mut hasher);
+        y.hash(&mut hasher);
+        salt.hash(&mut hasher);
+
+        StdRng::seed_from_u64(hasher.finish())
+    }
+
+    pub fn sample_tree(&self, x: Coordinate, y: Coordinate) -> bool {
+        self.position_rng(x, y, ()).random::<f32>() < 0.008
+    }
 
-    let mut rng = StdRng::seed_from_u64(hasher.finish());
-    rng.random::<f32>() < 0.008
+    /// The plant growing on the grass at the given position, if any. Melons
+    /// are rare, but are the only food found in the wild.
+    pub fn sample_plant(&self, x: Coordinate, y: Coordinate) -> Option<BlockType> {
+        let roll = self.position_rng(x, y, "plant").random::<f32>();
+        if roll < 0.001 {
+            Some(TERRAIN_BLOCKS.melon)
+        } else if roll < 0.005 {
+            Some(TERRAIN_BLOCKS.flower)
+        } else if roll < 0.08 {
+            Some(TERRAIN_BLOCKS.tall_grass)
+        } else {
+            None
+        }
+    }
 }
@@ -0,0 +1,135 @@
+use crate::{settings::{EXHAUSTION_PER_POINT, HUNGER_INTERVAL, MAX_HUNGER, PHYSICS_TICK_RATE, REGEN_EXHAUSTION, REGEN_HUNGER, SPRINT_HUNGER, START_SATURATION}, world::combat::Health};
+
+/// How fed something is. Activity builds up exhaustion, which eats into
+/// saturation first and then food. Being well fed heals, and starving hurts.
+#[derive(Copy, Clone, Debug)]
+pub struct Hunger {
+    /// From 0 to `MAX_HUNGER`
+    food: f32,
+    /// Extra food kept in reserve, never more than `food`
+    saturation: f32,
+    /// Builds up to `EXHAUSTION_PER_POINT`, and then uses up a point
+    exhaustion: f32,
+    /// Seconds towards the next time hunger heals or hurts
+    timer: f32,
+}
+
+impl Hunger {
+    pub fn new() -> Self {
+        Self {
+            food: MAX_HUNGER,
+            saturation: START_SATURATION,
+            exhaustion: 0.0,
+            timer: 0.0,
+        }
+    }
+
+    pub fn food(&self) -> f32 {
+        self.food
+    }
+
+    pub fn saturation(&self) -> f32 {
+        self.saturation
+    }
+
+    /// Whether there's no room to eat anything
+    pub fn is_full(&self) -> bool {
+        self.food >= MAX_HUNGER
+    }
+
+    /// Whether there's enough food left to sprint on
+    pub fn can_sprint(&self) -> bool {
+        self.food > SPRINT_HUNGER
+    }
+
+    /// Adds exhaustion from some activity
+    pub fn exhaust(&mut self, amount: f32) {
+        self.exhaustion += amount;
+
+        while self.exhaustion >= EXHAUSTION_PER_POINT {
+            self.exhaustion -= EXHAUSTION_PER_POINT;
+            if self.saturation > 0.0 {
+                self.saturation = (self.saturation - 1.0).max(0.0);
+            } else {
+                self.food = (self.food - 1.0).max(0.0);
+            }
+        }
+    }
+
+    /// Eats something restoring the given food and saturation
+    pub fn eat(&mut self, food: f32, saturation: f32) {
+        self.food = (self.food + food).min(MAX_HUNGER);
+        self.saturation = (self.saturation + saturation).min(self.food);
+    }
+
+    /// Moves time on by a physics tick, healing or hurting the given health
+    /// every so often depending on how fed it is
+    pub fn tick(&mut self, health: &mut Health) {
+        let healing = self.food >= REGEN_HUNGER && !health.is_full();
+        let starving = self.food <= 0.0;
+        if !healing && !starving {
+            self.timer = 0.0;
+            return;
+        }
+
+        self.timer += 1.0 / PHYSICS_TICK_RATE;
+        if self.timer < HUNGER_INTERVAL {
+            return;
+        }
+        self.timer = 0.0;
+
+        if healing {
+            health.heal(1.0);
+            self.exhaust(REGEN_EXHAUSTION);
+        } else {
+            health.drain(1.0);
+        }
+    }
+}
+
+impl Default for Hunger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(hunger: &mut Hunger, health: &mut Health, seconds: f32) {
+        for _ in 0..(seconds * PHYSICS_TICK_RATE) as usize + 1 {
+            hunger.tick(health);
+        }
+    }
+
+    #[test]
+    fn exhaustion_uses_saturation_then_food() {
+        let mut hunger = Hunger::new();
+        hunger.exhaust(EXHAUSTION_PER_POINT * START_SATURATION);
+        assert_eq!((hunger.food(), hunger.saturation()), (MAX_HUNGER, 0.0));
+
+        hunger.exhaust(EXHAUSTION_PER_POINT * 3.5);
+        assert_eq!(hunger.food(), MAX_HUNGER - 3.0);
+        assert!(!hunger.is_full());
+
+        hunger.eat(10.0, 10.0);
+        assert!(hunger.is_full());
+        assert_eq!(hunger.saturation(), 10.0);
+    }
+
+    #[test]
+    fn fed_heals_and_starving_hurts() {
+        let mut hunger = Hunger::new();
+        let mut health = Health::new(20.0);
+        health.drain(5.0);
+
+        seconds(&mut hunger, &mut health, HUNGER_INTERVAL);
+        assert_eq!(health.current(), 16.0);
+
+        hunger.exhaust(EXHAUSTION_PER_POINT * (START_SATURATION + MAX_HUNGER));
+        assert_eq!(hunger.food(), 0.0);
+        seconds(&mut hunger, &mut health, HUNGER_INTERVAL);
+        assert_eq!(health.current(), 15.0);
+    }
+}
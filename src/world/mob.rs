@@ -0,0 +1,424 @@
+use std::{collections::VecDeque, ops::Range};
+use cgmath::{InnerSpace, MetricSpace, Point3, Vector2, Vector3};
+use rand::Rng;
+use crate::{physics::{AABB, Entity, RawEntity, collision::{Bounds, FULL_BOX, overlaps}}, rendering::{mesh::Mesh, vertex::{ENTITY_BLOCK, HURT_ENTITY_BLOCK, Vertex}}, settings::{GRAVITY_A, JUMP_SPEED, MAX_HOSTILE_MOBS, MAX_PASSIVE_MOBS, MOB_CHASE_DIST, PHYSICS_TICK_RATE, SWIM_SPEED}, world::{GameWorld, ThreeDimPos, block::BlockSide, combat::{Health, knock_back}, generation::TERRAIN_BLOCKS, pathfinding::{cell_feet, find_path}, player::GameMode, shape::box_face, state::Block}};
+
+const PIG_AABB: AABB = AABB::new(0.9, 0.9, 0.9, Point3::new(0.45, 0.0, 0.45)).unwrap();
+const ZOMBIE_AABB: AABB = AABB::new(0.6, 1.95, 0.6, Point3::new(0.3, 0.0, 0.3)).unwrap();
+
+/// How far away from where they are wandering mobs head to, in blocks
+const WANDER_RANGE: i32 = 8;
+/// Seconds wandering mobs wait between picking somewhere new to go
+const WANDER_WAIT: Range<f32> = 2.0..7.0;
+/// Chance a wandering mob actually goes anywhere, rather than standing still
+const WANDER_CHANCE: f64 = 0.6;
+/// Seconds between chasing mobs finding a new path to the player
+const REPATH_INTERVAL: f32 = 1.0;
+/// Seconds a mob tries to reach the next cell of its path before giving up on
+/// it
+const STUCK_TIME: f32 = 2.0;
+/// How close to the middle of a cell a mob has to get to count as there
+const ARRIVE_DIST: f32 = 0.3;
+/// How far past the edges of their bounding box mobs can hit things
+const MOB_ATTACK_REACH: f32 = 0.4;
+/// Seconds between a mob's attacks
+const MOB_ATTACK_COOLDOWN: f32 = 1.0;
+
+/// A box in a mob's model, with its front facing `BlockSide::Front`
+struct ModelPart {
+    /// Opposite corners, relative to the middle of the mob's feet
+    min: [f32; 3],
+    max: [f32; 3],
+    texture: &'static str,
+    /// Texture for the front of the box instead, if it differs
+    face: Option<&'static str>,
+}
+
+const PIG_MODEL: &[ModelPart] = &[
+    ModelPart { min: [-0.3, 0.35, -0.5], max: [0.3, 0.85, 0.5], texture: "pig", face: None },
+    ModelPart { min: [-0.25, 0.45, -0.85], max: [0.25, 0.95, -0.5], texture: "pig", face: Some("pig_face") },
+    ModelPart { min: [-0.3, 0.0, -0.5], max: [-0.1, 0.35, -0.3], texture: "pig", face: None },
+    ModelPart { min: [0.1, 0.0, -0.5], max: [0.3, 0.35, -0.3], texture: "pig", face: None },
+    ModelPart { min: [-0.3, 0.0, 0.3], max: [-0.1, 0.35, 0.5], texture: "pig", face: None },
+    ModelPart { min: [0.1, 0.0, 0.3], max: [0.3, 0.35, 0.5], texture: "pig", face: None },
+];
+
+const ZOMBIE_MODEL: &[ModelPart] = &[
+    ModelPart { min: [-0.25, 0.0, -0.125], max: [0.25, 0.75, 0.125], texture: "zombie_pants", face: None },
+    ModelPart { min: [-0.25, 0.75, -0.125], max: [0.25, 1.45, 0.125], texture: "zombie_shirt", face: None },
+    // Arms held out in front
+    ModelPart { min: [-0.375, 1.2, -0.7], max: [-0.25, 1.35, -0.05], texture: "zombie", face: None },
+    ModelPart { min: [0.25, 1.2, -0.7], max: [0.375, 1.35, -0.05], texture: "zombie", face: None },
+    ModelPart { min: [-0.25, 1.45, -0.25], max: [0.25, 1.95, 0.25], texture: "zombie", face: Some("zombie_face") },
+];
+
+/// The kinds of mob there are
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MobKind {
+    /// Wanders around grassland during the day
+    Pig,
+    /// Comes out at night and chases the player
+    Zombie,
+}
+
+impl MobKind {
+    pub const ALL: [MobKind; 2] = [Self::Pig, Self::Zombie];
+
+    /// The mob's bounding box, with its origin at the middle of its feet
+    pub fn aabb(&self) -> AABB {
+        match self {
+            Self::Pig => PIG_AABB,
+            Self::Zombie => ZOMBIE_AABB,
+        }
+    }
+
+    /// Whether the mob goes after the player
+    pub fn is_hostile(&self) -> bool {
+        match self {
+            Self::Pig => false,
+            Self::Zombie => true,
+        }
+    }
+
+    fn max_health(&self) -> f32 {
+        match self {
+            Self::Pig => 10.0,
+            Self::Zombie => 20.0,
+        }
+    }
+
+    /// Damage done by the mob's attacks. None if it doesn't attack.
+    fn attack_damage(&self) -> Option<f32> {
+        match self {
+            Self::Pig => None,
+            Self::Zombie => Some(3.0),
+        }
+    }
+
+    /// Walking speed, in m/s
+    fn speed(&self) -> f32 {
+        match self {
+            Self::Pig => 2.5,
+            Self::Zombie => 3.5,
+        }
+    }
+
+    /// The most of this kind of mob (and others just as hostile) around at
+    /// once
+    pub fn cap(&self) -> usize {
+        if self.is_hostile() { MAX_HOSTILE_MOBS } else { MAX_PASSIVE_MOBS }
+    }
+
+    /// Whether the mob can spawn standing on the given block at the given
+    /// time of day. Passive mobs spawn on grass during the day, and hostile
+    /// ones on anything solid at night.
+    pub fn can_spawn_on(&self, ground: Block, night: bool) -> bool {
+        if self.is_hostile() {
+            night && ground.kind.is_solid()
+        } else {
+            !night && ground.kind == TERRAIN_BLOCKS.grass
+        }
+    }
+
+    fn model(&self) -> &'static [ModelPart] {
+        match self {
+            Self::Pig => PIG_MODEL,
+            Self::Zombie => ZOMBIE_MODEL,
+        }
+    }
+}
+
+/// A creature that walks around on its own
+pub struct Mob {
+    /// The inner physics entity, with its origin at the mob's feet
+    pub entity: RawEntity,
+    kind: MobKind,
+    pub health: Health,
+    /// Seconds until the mob can attack again
+    attack_timer: f32,
+    /// Which way the mob is facing, along the ground
+    facing: Vector2<f32>,
+    /// Cells left to walk through, next first
+    path: VecDeque<ThreeDimPos>,
+    /// Seconds until the mob next decides where to go
+    think_timer: f32,
+    /// Seconds since the mob last got closer to the next cell of its path
+    stuck_time: f32,
+    /// The closest the mob has got to the next cell of its path
+    best_dist: f32,
+    /// Where the mob was before the latest tick, for interpolating between
+    /// ticks when rendering
+    last_pos: Point3<f32>,
+}
+
+impl Mob {
+    /// A mob standing with its feet at the given position
+    pub fn new(kind: MobKind, feet: Point3<f32>) -> Self {
+        let mut entity = RawEntity::new(feet, kind.aabb());
+        entity.set_acceleration(GRAVITY_A);
+
+        Self {
+            entity,
+            kind,
+            health: Health::new(kind.max_health()),
+            attack_timer: 0.0,
+            facing: Vector2::new(0.0, -1.0),
+            path: VecDeque::new(),
+            think_timer: 0.0,
+            stuck_time: 0.0,
+            best_dist: f32::INFINITY,
+            last_pos: feet,
+        }
+    }
+
+    pub fn kind(&self) -> MobKind {
+        self.kind
+    }
+
+    /// Hurts the mob, knocking it away from the given point. Returns whether
+    /// it was hurt, rather than having been hurt too recently.
+    pub fn hurt(&mut self, amount: f32, from: Point3<f32>) -> bool {
+        if !self.health.hurt(amount) {
+            return false;
+        }
+
+        knock_back(self, from);
+        true
+    }
+
+    /// Hits whatever is in the given bounds, if it's in reach and the mob
+    /// attacks and is ready to. Returns the damage done.
+    pub fn attack(&mut self, target: Bounds) -> Option<f32> {
+        let damage = self.kind.attack_damage()?;
+        if self.attack_timer > 0.0 {
+            return None;
+        }
+
+        let (min, max) = self.entity.bounds();
+        let reach = Vector3::new(MOB_ATTACK_REACH, MOB_ATTACK_REACH, MOB_ATTACK_REACH);
+        if !overlaps((min - reach, max + reach), target) {
+            return None;
+        }
+
+        self.attack_timer = MOB_ATTACK_COOLDOWN;
+        Some(damage)
+    }
+
+    /// Where the mob wants to go, if anywhere
+    fn pick_goal(&mut self, world: &GameWorld) -> Option<ThreeDimPos> {
+        let mut rng = rand::rng();
+        let player = world.player();
+        let player_pos = player.get_precise_pos();
+        let player_feet = Point3::new(player_pos.x, player.entity.bounds().0.y, player_pos.z);
+
+        let chasing = self.kind.is_hostile() &&
+            player.game_mode() == GameMode::Survival &&
+            player_feet.distance(self.entity.get_precise_pos()) < MOB_CHASE_DIST;
+        if chasing {
+            self.think_timer = REPATH_INTERVAL;
+            return Some(standing_cell(player_feet));
+        }
+
+        self.think_timer = rng.random_range(WANDER_WAIT);
+        if !rng.random_bool(WANDER_CHANCE) {
+            return None;
+        }
+
+        let (x, y, z) = standing_cell(self.entity.get_precise_pos());
+        Some((
+            x + rng.random_range(-WANDER_RANGE..=WANDER_RANGE),
+            y + rng.random_range(-2..=2),
+            z + rng.random_range(-WANDER_RANGE..=WANDER_RANGE),
+        ))
+    }
+
+    /// Works out a new path to wherever the mob wants to go
+    fn think(&mut self, world: &GameWorld) {
+        let goal = self.pick_goal(world);
+        let start = standing_cell(self.entity.get_precise_pos());
+
+        self.path = goal
+            .and_then(|goal| find_path(world, &self.kind.aabb(), start, goal))
+            .map(VecDeque::from)
+            .unwrap_or_default();
+        self.stuck_time = 0.0;
+        self.best_dist = f32::INFINITY;
+    }
+
+    /// Heads towards the next cell in the path, jumping up onto it if it's
+    /// higher
+    fn follow_path(&mut self) {
+        let pos = self.entity.get_precise_pos();
+        let mut velocity = self.entity.get_velocity();
+
+        while let Some(next) = self.path.front() {
+            let target = cell_feet(*next);
+            let arrived = Vector2::new(target.x - pos.x, target.z - pos.z).magnitude() < ARRIVE_DIST &&
+                (target.y - pos.y).abs() < 0.5;
+            if !arrived {
+                break;
+            }
+
+            self.path.pop_front();
+            self.stuck_time = 0.0;
+            self.best_dist = f32::INFINITY;
+        }
+
+        // Let knockback carry the mob for a moment
+        if self.health.is_flashing() {
+            return;
+        }
+
+        match self.path.front() {
+            Some(next) => {
+                let target = cell_feet(*next);
+                let to_target = Vector2::new(target.x - pos.x, target.z - pos.z);
+                let dist = to_target.magnitude();
+
+                if dist > 1.0e-3 {
+                    self.facing = to_target / dist;
+                    let walk = self.facing * self.kind.speed();
+                    velocity.x = walk.x;
+                    velocity.z = walk.y;
+                }
+
+                if target.y > pos.y + 0.5 && self.entity.is_on_ground() {
+                    velocity.y = JUMP_SPEED;
+                }
+
+                // Give up on cells the mob can't seem to reach
+                if dist < self.best_dist - 0.05 {
+                    self.best_dist = dist;
+                    self.stuck_time = 0.0;
+                } else {
+                    self.stuck_time += 1.0 / PHYSICS_TICK_RATE;
+                    if self.stuck_time > STUCK_TIME {
+                        self.path.clear();
+                        self.think_timer = 0.0;
+                    }
+                }
+            },
+            None => {
+                velocity.x = 0.0;
+                velocity.z = 0.0;
+            },
+        }
+
+        // Paths avoid water, but mobs that end up in it anyway keep afloat
+        if self.entity.get_submersion() > 0.3 {
+            velocity.y = velocity.y.max(SWIM_SPEED * 0.5);
+        }
+
+        self.entity.set_velocity(velocity);
+    }
+
+    /// Adds the mob's model to the given mesh, at its position interpolated
+    /// `alpha` of the way through the latest tick. `texture` gives the
+    /// texture array layer of each texture name.
+    pub fn add_to_mesh(&self, mesh: &mut Mesh, alpha: f32, texture: &impl Fn(&str) -> u32) {
+        let current = self.entity.get_precise_pos();
+        let origin = self.last_pos + (current - self.last_pos) * alpha.clamp(0.0, 1.0);
+
+        // Model space front (-Z) turns to face `facing`, and right (+X) to
+        // its right
+        let forward = self.facing;
+        let right = Vector2::new(-forward.y, forward.x);
+        let turn = |[x, y, z]: [f32; 3]| -> [f32; 3] {
+            [right.x * x - forward.x * z, y, right.y * x - forward.y * z]
+        };
+
+        let block = if self.health.is_flashing() { HURT_ENTITY_BLOCK } else { ENTITY_BLOCK };
+
+        for part in self.kind.model() {
+            for side in BlockSide::ALL {
+                let texture = match (side, part.face) {
+                    (BlockSide::Front, Some(face)) => texture(face),
+                    _ => texture(part.texture),
+                };
+                let normal = turn(side.normal());
+
+                let verticies = box_face(side, FULL_BOX).map(|(corner, texture_cords)| {
+                    let local = [0, 1, 2].map(|i| part.min[i] + corner[i] * (part.max[i] - part.min[i]));
+                    let [x, y, z] = turn(local);
+                    Vertex {
+                        position: [origin.x + x, origin.y + y, origin.z + z],
+                        texture_cords,
+                        normal,
+                        block,
+                        texture,
+                    }
+                });
+
+                let start_index = mesh.verticies.len() as u32;
+                mesh.verticies.extend(verticies);
+                mesh.indicies.extend([
+                    start_index + 3, start_index + 2, start_index,
+                    start_index + 3, start_index, start_index + 1,
+                ]);
+            }
+        }
+    }
+}
+
+/// The cell something with its feet at the given position is standing in
+fn standing_cell(feet: Point3<f32>) -> ThreeDimPos {
+    // Nudged up, so float error in standing on a block doesn't put it in the
+    // block below
+    (feet.x.floor() as i32, (feet.y + 0.01).floor() as i32, feet.z.floor() as i32)
+}
+
+impl Entity for Mob {
+    fn tick(&mut self, world: &GameWorld) {
+        self.last_pos = self.entity.get_precise_pos();
+        self.health.tick();
+        self.attack_timer -= 1.0 / PHYSICS_TICK_RATE;
+
+        self.think_timer -= 1.0 / PHYSICS_TICK_RATE;
+        if self.think_timer <= 0.0 {
+            self.think(world);
+        }
+
+        self.follow_path();
+        self.entity.tick(world);
+    }
+
+    fn get_precise_pos(&self) -> Point3<f32> {
+        self.entity.get_precise_pos()
+    }
+
+    fn set_pos(&mut self, p: Point3<f32>) {
+        self.entity.set_pos(p);
+    }
+
+    fn get_velocity(&self) -> Vector3<f32> {
+        self.entity.get_velocity()
+    }
+
+    fn set_velocity(&mut self, v: Vector3<f32>) {
+        self.entity.set_velocity(v);
+    }
+
+    fn set_acceleration(&mut self, a: Vector3<f32>) {
+        self.entity.set_acceleration(a);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{rendering::resource_pack::{MISSING_TEXTURE, ResourcePack}, settings::BLOCK_REGISTRY, world::block::load_registry};
+    use super::*;
+
+    #[test]
+    fn default_pack_covers_every_mob() {
+        load_registry(BLOCK_REGISTRY).unwrap();
+        let pack = ResourcePack::load("default").unwrap();
+
+        for kind in MobKind::ALL {
+            for part in kind.model() {
+                for texture in std::iter::once(part.texture).chain(part.face) {
+                    assert_ne!(pack.texture(texture), MISSING_TEXTURE, "{:?} {}", kind, texture);
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,7 @@
-use std::{cmp::min, collections::{HashMap, HashSet}, mem::take, time::{Duration, Instant}};
-use cgmath::{InnerSpace, MetricSpace, Point2, Point3, Vector3, Zero, num_traits::{Signed, abs, real::Real}};
-use crate::{physics::Entity, rendering::mesh::Mesh, settings::{CHUNK_SIZE, MAX_HIGHLIGHT_DIST, PHYSICS_TICK_RATE, PLAYER_AABB, RENDER_DIST}, vectors::point_to_pos, world::{block::{BlockRef, BlockSide, BlockType}, chunk::{Chunk, cords_to_chunk, cords_to_local}, generation::sample_elevation, player::Player}};
+use std::{collections::HashMap, f32::consts::TAU, mem::take, path::Path, time::{Duration, Instant}};
+use cgmath::{InnerSpace, MetricSpace, Point2, Point3, Vector3, Zero, num_traits::{Signed, abs}};
+use rand::Rng;
+use crate::{physics::{Entity, collision::{Bounds, box_bounds, overlaps, ray_hits}}, rendering::mesh::Mesh, settings::{ATTACK_DAMAGE, ATTACK_REACH, CHUNK_SIZE, FAR_TERRAIN, MAX_HIGHLIGHT_DIST, MAX_TICKS_PER_FRAME, MOB_DESPAWN_DIST, MOB_SPAWN_CHANCE, MOB_SPAWN_MAX_DIST, MOB_SPAWN_MIN_DIST, PHYSICS_TICK_RATE, SECTION_HEIGHT, WORLD_MAX_Y, WORLD_MIN_Y}, vectors::point_to_pos, world::{block::{BlockRef, BlockSide, BlockType, Interaction}, block_entity::{BlockEntity, Inventory}, generation::{Generator, TERRAIN_BLOCKS}, save::{WorldInfo, load_chunk, save_chunk}, far_terrain::{FarTerrain, loaded_circle}, chunk::{Chunk, SECTIONS, cords_to_chunk, cords_to_local, y_to_local}, player::{GameMode, Player}, shape::ModelContext, state::{Block, StateProperty}, mob::{Mob, MobKind}, pathfinding::{cell_feet, is_walkable}, time::WorldTime, visibility::visible_sections}};
 
 /// World chunks, which contain block data
 pub mod chunk;
@@ -9,17 +10,47 @@ pub mod block;
 /// World generation
 mod generation;
 /// The player
-mod player;
-
-/// A lateral coordinate (X or Z)
+pub mod player;
+/// Which parts of chunks can be seen through, for occlusion culling
+mod visibility;
+/// Compact block storage for chunk sections
+mod storage;
+/// Low detail terrain past the loaded chunks
+pub mod far_terrain;
+/// Block models other than plain cubes
+pub mod shape;
+/// Per-block state, like which way a block faces
+pub mod state;
+/// Extra data kept for individual blocks, like chest contents
+pub mod block_entity;
+/// Saving worlds and their chunks to disk and loading them back
+pub mod save;
+/// The time of day
+pub mod time;
+/// Creatures that walk around on their own
+pub mod mob;
+/// Finding paths for mobs over the block grid
+mod pathfinding;
+/// Health, damage and knockback
+pub mod combat;
+/// Hunger, and healing or starving because of it
+pub mod hunger;
+
+/// A block coordinate (X, Y or Z)
 pub type Coordinate = i32;
 /// A lateral (X, Z) position in the world space
 pub type WorldPos = (Coordinate, Coordinate);
 /// A 3D (X, Y, Z) position in the world space
-pub type ThreeDimPos = (Coordinate, u8, Coordinate);
+pub type ThreeDimPos = (Coordinate, Coordinate, Coordinate);
+
+const TICK_DURATION: Duration =
+    Duration::new(0, ((1.0 / PHYSICS_TICK_RATE) * 1.0e9) as u32);
 
 /// Holds state of the game world itself. Blocks, entities, whatever.
 pub struct GameWorld {
+    /// Which world this is, and where it's saved
+    info: WorldInfo,
+    generator: Generator,
     /// Currently loaded in chunks
     chunks: HashMap<WorldPos, Chunk>,
     /// Blocks generated into adjacent chunks that are yet to be put into a
@@ -27,37 +58,136 @@ pub struct GameWorld {
     block_scratch: HashMap<ThreeDimPos, BlockType>,
     /// The player
     player: Player,
-    /// The last time a physics tick was calculated. Used for enforcing the tick
-    /// rate
-    last_tick: Instant,
-    /// The highlighted block, if there is one
-    highlight: Option<ThreeDimPos>,
+    /// The last time `do_tick` was called. Used for enforcing the tick rate
+    last_update: Instant,
+    /// Elapsed time not yet simulated by a physics tick
+    tick_accumulator: Duration,
+    /// How long the latest physics tick took to compute
+    tick_time: Duration,
+    /// Where the player was before the latest tick, for interpolating
+    /// between ticks when rendering
+    last_player_pos: Point3<f32>,
+    /// The highlighted block and the side of it being looked at, if there
+    /// is one
+    highlight: Option<(ThreeDimPos, BlockSide)>,
+    /// The index of the mob the player is looking at, if it's in reach and
+    /// nearer than the highlighted block would be. There's no highlighted
+    /// block while there's a targeted mob.
+    targeted_mob: Option<usize>,
+    /// The container block whose inventory the player has open, if any
+    open_container: Option<ThreeDimPos>,
+    /// The block a survival player is breaking, and the seconds they've spent
+    /// on it
+    break_progress: Option<(ThreeDimPos, f32)>,
+    /// Coarse terrain drawn past the loaded chunks
+    far_terrain: FarTerrain,
+    /// The number of chunks loaded away from the player
+    render_dist: usize,
+    /// Every mob around the player
+    mobs: Vec<Mob>,
+    /// The mobs, as of the last call to `update_mob_mesh`
+    mob_mesh: Mesh,
+    time: WorldTime,
 }
 
 impl GameWorld {
-    pub fn new() -> Self {
+    /// Opens the given saved world, with the player where they were left,
+    /// loading chunks out to the given render distance
+    pub fn open(info: WorldInfo, render_dist: usize) -> Self {
+        let generator = Generator::new(info.seed);
+        let spawn_y = (generator.sample_elevation(0, 0) + 2) as f32;
+        let mut player = Player::new(Point3::new(0.0, spawn_y, 0.0));
+        if let Some(pos) = info.player {
+            player.set_pos(pos);
+        }
+        player.inventory = info.inventory.clone();
+        let last_player_pos = player.get_precise_pos();
+        let time = WorldTime::from_ticks(info.time);
+
         Self {
+            info,
+            generator,
             chunks: HashMap::new(),
             block_scratch: HashMap::new(),
-            player: Player::new(),
-            last_tick: Instant::now(),
+            player,
+            last_update: Instant::now(),
+            tick_accumulator: Duration::ZERO,
+            tick_time: Duration::ZERO,
+            last_player_pos,
             highlight: None,
+            targeted_mob: None,
+            open_container: None,
+            break_progress: None,
+            far_terrain: FarTerrain::new(),
+            render_dist,
+            mobs: vec![],
+            mob_mesh: Mesh::new(),
+            time,
         }
     }
 
-    /// Returns the various meshses to be rendered
-    pub fn get_meshes_mut(&mut self) -> Box<[&mut Mesh]> {
+    /// Returns the various meshses to be rendered, as (chunk meshes, far
+    /// terrain meshes)
+    pub fn get_meshes_mut(&mut self) -> (Box<[&mut Mesh]>, Box<[&mut Mesh]>) {
         let mut meshes = vec![];
-        for (_, chunk) in &mut self.chunks {
-            meshes.push(&mut chunk.mesh);
+        for chunk in self.chunks.values_mut() {
+            for section in &mut chunk.sections {
+                // Nothing to draw for all-air sections
+                if !section.is_empty() {
+                    meshes.push(&mut section.mesh);
+                }
+            }
+        }
+        if !self.mob_mesh.verticies.is_empty() {
+            meshes.push(&mut self.mob_mesh);
+        }
+
+        (meshes.into(), self.far_terrain.get_meshes_mut().collect())
+    }
+
+    /// Has every mesh rebuilt by the next `update_meshes`, for when block
+    /// textures change
+    pub fn rebuild_meshes(&mut self) {
+        for chunk in self.chunks.values_mut() {
+            chunk.mark_all_dirty();
+        }
+        self.far_terrain.clear();
+    }
+
+    /// The circle around the player that loaded chunks always cover, as
+    /// (center, radius)
+    pub fn loaded_circle(&self) -> (Point2<f32>, f32) {
+        let (x, _, z) = self.player.get_world_pos();
+        loaded_circle((x, z), self.render_dist)
+    }
+
+    /// Marks every section mesh that can't be seen from the camera through
+    /// open space as occluded
+    pub fn update_occlusion(&mut self, camera: Point3<f32>) {
+        for chunk in self.chunks.values_mut() {
+            for section in &mut chunk.sections {
+                section.mesh.occluded = true;
+            }
         }
 
-        meshes.into()
+        let start_chunk = cords_to_chunk((
+            camera.x.floor() as Coordinate,
+            camera.z.floor() as Coordinate,
+        ));
+        let start_section = ((camera.y - WORLD_MIN_Y as f32).max(0.0) as usize
+            / SECTION_HEIGHT).min(SECTIONS - 1);
+
+        let visible = visible_sections((start_chunk, start_section), |chunk, section| {
+            self.chunks.get(&chunk).map(|c| c.sections[section].visibility)
+        });
+        for (chunk, section) in visible {
+            self.chunks.get_mut(&chunk).unwrap().sections[section].mesh.occluded = false;
+        }
     }
 
     pub fn update_chunks_to_player(&mut self) {
-        const RADIUS: isize = (RENDER_DIST * CHUNK_SIZE) as isize;
-        const RADIUS_SQ: f32 = (RADIUS * RADIUS) as f32;
+        let radius = (self.render_dist * CHUNK_SIZE) as isize;
+        let radius_sq = (radius * radius) as f32;
 
         let (player_x, _, player_z) = self.player.get_world_pos();
         let player_chunk = cords_to_chunk((player_x, player_z));
@@ -71,19 +201,21 @@ impl GameWorld {
                     Point2::new(this_chunk.0 as f32, this_chunk.1 as f32);
                 let dist_sq = player_chunk_pt.distance2(this_chunk_pt);
 
-                dist_sq >= RADIUS_SQ
+                dist_sq >= radius_sq
             })
             .cloned()
             .collect();
         for k in to_remove {
-            self.chunks.remove(&k);
+            let chunk = self.chunks.remove(&k).unwrap();
+            save_if_modified(&self.info.dir, &chunk);
+            self.mark_neighbours_dirty(k);
         }
 
         // Generate new chunks
-        let x_start = player_chunk.0 as isize - RADIUS + CHUNK_SIZE as isize;
-        let x_end = player_chunk.0 as isize + RADIUS;
-        let z_start = player_chunk.1 as isize - RADIUS + CHUNK_SIZE as isize;
-        let z_end = player_chunk.1 as isize + RADIUS;
+        let x_start = player_chunk.0 as isize - radius + CHUNK_SIZE as isize;
+        let x_end = player_chunk.0 as isize + radius;
+        let z_start = player_chunk.1 as isize - radius + CHUNK_SIZE as isize;
+        let z_end = player_chunk.1 as isize + radius;
         for x in (x_start..x_end).step_by(CHUNK_SIZE) {
             let f_x = x as f32;
             let c_x = x as Coordinate;
@@ -92,21 +224,27 @@ impl GameWorld {
                 let pt = Point2::new(f_x, f_z);
                 let dist_sq = player_chunk_pt.distance2(pt);
 
-                if dist_sq <= RADIUS_SQ {
+                if dist_sq <= radius_sq {
                     let c_z = z as Coordinate;
                     let pos = (c_x, c_z);
                     if !self.chunks.contains_key(&pos) {
-                        self.chunks.insert(
-                            pos,
-                            Chunk::new(pos, &mut self.block_scratch).unwrap()
-                        );
+                        // Edited chunks come back as they were left
+                        let saved = load_chunk(&self.info.dir, pos).unwrap_or_else(|e| {
+                            log::error!("{:#}, generating it again", e);
+                            None
+                        });
+                        let chunk = match saved {
+                            Some(chunk) => chunk,
+                            None => Chunk::new(pos, &self.generator, &mut self.block_scratch).unwrap(),
+                        };
+                        self.chunks.insert(pos, chunk);
+                        self.mark_neighbours_dirty(pos);
                     }
                 }
             }
         }
 
         // Update blocks written to the scratch
-        let mut dirty_chunks = HashSet::new();
         let removed_keys: Vec<ThreeDimPos> = self.block_scratch.iter()
             .filter_map(|(pos_3d, block)| {
                 let (x, y, z) = *pos_3d;
@@ -114,10 +252,12 @@ impl GameWorld {
                 let chunk_pos = cords_to_chunk(pos_2d);
 
                 if let Some(chunk) = self.chunks.get_mut(&chunk_pos) {
-                    dirty_chunks.insert(chunk_pos);
-
-                    let (local_x, local_z) = cords_to_local((x, z));
-                    chunk.blocks[local_x][local_z][y as usize] = *block;
+                    // Anything generated outside of the world is dropped
+                    if let Some(local_y) = y_to_local(y) {
+                        let (local_x, local_z) = cords_to_local((x, z));
+                        chunk.set(local_x, local_y, local_z, (*block).into());
+                        chunk.mark_changed(local_x, local_y, local_z);
+                    }
                     Some(pos_3d)
                 } else {
                     None
@@ -129,29 +269,73 @@ impl GameWorld {
         for key in removed_keys {
             self.block_scratch.remove(&key);
         }
+    }
+
+    /// Regenerates the chunk meshes that changed since they were last meshed,
+    /// and builds more far terrain. `textures` gives the texture array layer
+    /// of each block face, None for ones that aren't drawn.
+    pub fn update_meshes(&mut self, textures: impl Fn(BlockType, BlockSide) -> Option<u32>) {
+        // Blocks on chunk borders change the meshes across them too
+        let mut border_changes = vec![];
+        for chunk in self.chunks.values_mut() {
+            for (side, y) in chunk.take_border_changes() {
+                border_changes.push((neighbour_chunk(chunk.pos(), side), y));
+            }
+        }
+        for (pos, y) in border_changes {
+            if let Some(chunk) = self.chunks.get_mut(&pos) {
+                chunk.mark_dirty_at(y);
+            }
+        }
 
-        for chunk in dirty_chunks {
-            self.chunks.get_mut(&chunk).unwrap().update_mesh();
+        let to_mesh: Vec<WorldPos> = self.chunks.iter()
+            .filter(|(_, chunk)| chunk.needs_meshing())
+            .map(|(pos, _)| *pos)
+            .collect();
+        for pos in to_mesh {
+            // Out of the map while it's meshed, so it can look across its
+            // borders into the rest of the world
+            let mut chunk = self.chunks.remove(&pos).unwrap();
+            chunk.update_meshes(&textures, &|pos| self.get_block(pos));
+            self.chunks.insert(pos, chunk);
+        }
+
+        if FAR_TERRAIN {
+            let (x, _, z) = self.player.get_world_pos();
+            self.far_terrain.update((x, z), self.render_dist, &self.generator, &textures);
+        }
+    }
+
+    /// Has every mesh of the chunks next to the given one rebuilt, for when it
+    /// loads or unloads
+    fn mark_neighbours_dirty(&mut self, pos: WorldPos) {
+        for side in [BlockSide::Left, BlockSide::Right, BlockSide::Front, BlockSide::Back] {
+            if let Some(chunk) = self.chunks.get_mut(&neighbour_chunk(pos, side)) {
+                chunk.mark_all_dirty();
+            }
         }
     }
 
-    pub fn get_block(&self, pos: ThreeDimPos) -> Option<BlockType> {
-        let x = pos.0 as Coordinate;
-        let y = pos.1 as usize;
-        let z = pos.2 as Coordinate;
+    /// Gets the block at the given position. None if it isn't loaded or is
+    /// outside of the world's height.
+    pub fn get_block(&self, pos: ThreeDimPos) -> Option<Block> {
+        let (x, y, z) = pos;
+        let y = y_to_local(y)?;
 
         let chunk_pos = cords_to_chunk((x, z));
         let (local_x, local_z) = cords_to_local((x, z));
         match self.chunks.get(&chunk_pos) {
-            Some(chunk) => Some(chunk.blocks[local_x][local_z][y]),
+            Some(chunk) => Some(chunk.get(local_x, y, local_z)),
             None => None,
         }
     }
 
+    /// Gets a mutable reference to the block at the given position. None if
+    /// it isn't loaded or is outside of the world's height, which is what
+    /// enforces the build limit.
     pub fn get_block_mut<'a>(&'a mut self, pos: ThreeDimPos) -> Option<BlockRef<'a>> {
-        let x = pos.0 as Coordinate;
-        let y = pos.1 as usize;
-        let z = pos.2 as Coordinate;
+        let (x, y, z) = pos;
+        let y = y_to_local(y)?;
 
         let chunk_pos = cords_to_chunk((x, z));
         let (local_x, local_z) = cords_to_local((x, z));
@@ -164,6 +348,25 @@ impl GameWorld {
         }
     }
 
+    /// Sets every given position to the given block, as one edit. Nothing
+    /// changes unless they're all loaded. Each mesh the blocks touch is
+    /// rebuilt once, by the next `update_meshes`. Returns how many were left
+    /// alone for being containers with items in them.
+    pub fn edit_blocks(&mut self, positions: &[ThreeDimPos], block: Block) -> anyhow::Result<usize> {
+        if let Some(pos) = positions.iter().find(|p| self.get_block(**p).is_none()) {
+            anyhow::bail!("{:?} isn't loaded", pos);
+        }
+
+        let mut kept = 0;
+        for pos in positions {
+            let (chunk, (x, y, z)) = self.chunk_at_mut(*pos).unwrap();
+            if chunk.get(x, y, z) != block && !chunk.edit(x, y, z, block) {
+                kept += 1;
+            }
+        }
+        Ok(kept)
+    }
+
     pub fn player(&self) -> &Player {
         &self.player
     }
@@ -172,25 +375,148 @@ impl GameWorld {
         &mut self.player
     }
 
-    /// Executes a physics tick for all entities
+    /// Executes as many physics ticks for all entities as the time elapsed
+    /// since the last call calls for
     pub fn do_tick(&mut self) {
-        const TICK_DURATION: Duration =
-            Duration::new(0, ((1.0 / PHYSICS_TICK_RATE) * 1.0e9) as u32);
+        let now = Instant::now();
+        self.tick_accumulator += now - self.last_update;
+        self.last_update = now;
+
+        let mut ticks = 0;
+        while self.tick_accumulator >= TICK_DURATION {
+            // If we can't keep up, drop the backlog rather than spending ever
+            // longer catching up
+            if ticks >= MAX_TICKS_PER_FRAME {
+                self.tick_accumulator = Duration::ZERO;
+                break;
+            }
+
+            self.tick_accumulator -= TICK_DURATION;
+            ticks += 1;
+            let tick_start = Instant::now();
+
+            self.check_open_container();
+            self.time.tick();
+
+            self.last_player_pos = self.player.get_precise_pos();
+            let mut player = take(&mut self.player);
+            player.tick(self);
+            self.player = player;
+
+            self.tick_mobs();
+            // After the mobs, so the targeted mob's index stays valid
+            self.cast_highlight();
+            self.tick_breaking();
+
+            self.tick_time = tick_start.elapsed();
+        }
+    }
+
+    /// How long the latest physics tick took to compute
+    pub fn tick_time(&self) -> Duration {
+        self.tick_time
+    }
+
+    /// How far we are into the next tick, from 0 to 1
+    fn tick_progress(&self) -> f32 {
+        let alpha = self.tick_accumulator.as_secs_f32() / TICK_DURATION.as_secs_f32();
+        alpha.min(1.0)
+    }
 
-        if self.last_tick.elapsed() < TICK_DURATION {
+    /// The player position to render at, interpolated between the last two
+    /// ticks by how far we are into the next one
+    pub fn player_render_pos(&self) -> Point3<f32> {
+        let current = self.player.get_precise_pos();
+        self.last_player_pos + (current - self.last_player_pos) * self.tick_progress()
+    }
+
+    /// Moves the player's feet to the given point, loading the chunks around
+    /// it straight away so they don't fall through
+    pub fn teleport_player(&mut self, feet: Point3<f32>) {
+        self.player.teleport(feet);
+        self.last_player_pos = self.player.get_precise_pos();
+        self.update_chunks_to_player();
+    }
+
+    pub fn time(&self) -> &WorldTime {
+        &self.time
+    }
+
+    pub fn time_mut(&mut self) -> &mut WorldTime {
+        &mut self.time
+    }
+
+    /// Despawns mobs that are too far away, ticks the rest and maybe spawns a
+    /// new one
+    fn tick_mobs(&mut self) {
+        let player = self.player.get_precise_pos();
+        let mut mobs = take(&mut self.mobs);
+
+        // Mobs in unloaded chunks would fall forever, so go too
+        mobs.retain(|m| {
+            let pos = m.get_precise_pos();
+            pos.distance(player) <= MOB_DESPAWN_DIST &&
+                self.chunks.contains_key(&cords_to_chunk((pos.x.floor() as Coordinate, pos.z.floor() as Coordinate)))
+        });
+
+        let survival = self.player.game_mode() == GameMode::Survival;
+        for mob in &mut mobs {
+            mob.tick(self);
+
+            if survival && let Some(damage) = mob.attack(self.player.entity.bounds()) {
+                self.player.hurt(damage, mob.get_precise_pos());
+            }
+        }
+        self.mobs = mobs;
+
+        let mut rng = rand::rng();
+        if rng.random_bool(MOB_SPAWN_CHANCE) {
+            self.try_spawn_mob(&mut rng);
+        }
+    }
+
+    /// Tries to spawn a random kind of mob on the surface somewhere around the
+    /// player, as long as there aren't too many already and the ground and
+    /// time of day suit it
+    fn try_spawn_mob(&mut self, rng: &mut impl Rng) {
+        let kind = MobKind::ALL[rng.random_range(0..MobKind::ALL.len())];
+        let similar = self.mobs.iter().filter(|m| m.kind().is_hostile() == kind.is_hostile()).count();
+        if similar >= kind.cap() {
             return;
         }
-        self.last_tick = Instant::now();
 
-        self.cast_highlight();
+        let angle = rng.random_range(0.0..TAU);
+        let dist = rng.random_range(MOB_SPAWN_MIN_DIST..MOB_SPAWN_MAX_DIST);
+        let player = self.player.get_precise_pos();
+        let x = (player.x + angle.cos() * dist).floor() as Coordinate;
+        let z = (player.z + angle.sin() * dist).floor() as Coordinate;
+
+        // The highest block in the column is what it would stand on
+        let Some((ground_y, ground)) = (WORLD_MIN_Y..WORLD_MAX_Y).rev()
+            .filter_map(|y| Some((y, self.get_block((x, y, z))?)))
+            .find(|(_, b)| b.kind.is_solid())
+        else {
+            return;
+        };
 
-        let mut player = take(&mut self.player);
-        player.tick(self);
-        self.player = player;
+        let cell = (x, ground_y + 1, z);
+        if kind.can_spawn_on(ground, self.time.is_night()) && is_walkable(self, &kind.aabb(), cell) {
+            self.mobs.push(Mob::new(kind, cell_feet(cell)));
+        }
+    }
+
+    /// Rebuilds the mesh drawing every mob, at their positions for this frame.
+    /// `texture` gives the texture array layer of each texture name.
+    pub fn update_mob_mesh(&mut self, texture: impl Fn(&str) -> u32) {
+        let alpha = self.tick_progress();
+        self.mob_mesh = Mesh::new();
+        for mob in &self.mobs {
+            mob.add_to_mesh(&mut self.mob_mesh, alpha, &texture);
+        }
     }
 
-    /// Updates the currently highlighted block face per the provided ray,
-    /// starting from the player position
+    /// Updates the currently highlighted block face or targeted mob per the
+    /// way the player is facing
     fn cast_highlight(&mut self) {
         let ray = self.player.facing.normalize();
 
@@ -198,6 +524,27 @@ impl GameWorld {
             return;
         }
 
+        let origin = self.player.get_precise_pos();
+        let block = self.cast_block_ray(origin, ray);
+
+        // Whichever is nearer blocks the other
+        let block_t = block.map_or(f32::INFINITY, |(_, t)| t);
+        let mob = self.mobs.iter().enumerate()
+            .filter_map(|(i, m)| Some((i, ray_hits(origin, ray, m.entity.bounds())?)))
+            .filter(|(_, t)| *t <= ATTACK_REACH && *t < block_t)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+
+        self.targeted_mob = mob.map(|(i, _)| i);
+        self.highlight = match mob {
+            Some(_) => None,
+            None => block.map(|(highlight, _)| highlight),
+        };
+    }
+
+    /// Finds the first targetable block along the given ray, along with the
+    /// side of it that was hit and how far along the ray it is
+    fn cast_block_ray(&self, origin: Point3<f32>, ray: Vector3<f32>) -> Option<((ThreeDimPos, BlockSide), f32)> {
+
         let step_x = if ray.x.is_positive() { 1. } else { -1. };
         let step_y = if ray.y.is_positive() { 1. } else { -1. };
         let step_z = if ray.z.is_positive() { 1. } else { -1. };
@@ -206,7 +553,7 @@ impl GameWorld {
         let d_y = abs(1.0 / ray.y);
         let d_z = abs(1.0 / ray.z);
 
-        let mut pos = self.player.get_precise_pos();
+        let mut pos = origin;
 
         let x_next = pos.x.floor() + if ray.x.is_positive() { 1. } else { 0. };
         let y_next = pos.y.floor() + if ray.y.is_positive() { 1. } else { 0. };
@@ -219,14 +566,23 @@ impl GameWorld {
         let mut dist = 0;
         loop {
             let block = point_to_pos(pos);
-            if let Some(b) = self.get_block(block) && b.is_solid() {
-                self.highlight = Some(block);
-                return;
+            if let Some(b) = self.get_block(block) && b.kind.is_targetable() {
+                // Only count it if the ray goes through the block's actual
+                // shape, not just its cell
+                let hit = b.kind.shape().hit_boxes(self.model_context(block, b)).iter()
+                    .filter_map(|h| {
+                        let bounds = box_bounds(block, *h);
+                        Some((ray_hits(origin, ray, bounds)?, bounds))
+                    })
+                    .min_by(|a, b| a.0.total_cmp(&b.0));
+
+                if let Some((t, bounds)) = hit {
+                    return Some(((block, hit_side(origin + ray * t, bounds)), t));
+                }
             }
 
             if dist > MAX_HIGHLIGHT_DIST {
-                self.highlight = None;
-                return;
+                return None;
             }
 
             let min = t_max_x.min(t_max_y).min(t_max_z);
@@ -245,23 +601,420 @@ impl GameWorld {
         }
     }
 
+    /// What the model of the given block depends on, going by its neighbours
+    pub fn model_context(&self, pos: ThreeDimPos, block: Block) -> ModelContext {
+        ModelContext::new(block, |side| {
+            let (d_x, d_y, d_z) = side.offset();
+            self.get_block((pos.0 + d_x, pos.1 + d_y, pos.2 + d_z))
+        })
+    }
+
+    /// The mob the player is looking at, if it's in reach
+    pub fn targeted_mob(&self) -> Option<&Mob> {
+        self.mobs.get(self.targeted_mob?)
+    }
+
     pub fn get_highlight(&self) -> Option<ThreeDimPos> {
-        self.highlight
+        self.highlight.map(|(pos, _)| pos)
     }
 
-    pub fn destroy_block(&mut self) {
+    /// Breaks the targeted block, along with the other half of two block tall
+    /// blocks
+    fn destroy_block(&mut self) {
+        if self.player.game_mode() == GameMode::Spectator {
+            return;
+        }
+
         if let Some(pos) = self.get_highlight() {
-            let mut block = self.get_block_mut(pos).unwrap();
-            *block = BlockType::Air;
-            drop(block);
+            let block = self.get_block(pos).unwrap();
+            if block.kind.hardness() < 0.0 {
+                return;
+            }
+            // Containers have to be emptied first, so nothing is lost
+            let (chunk, (x, y, z)) = self.chunk_at(pos).unwrap();
+            if chunk.holds_items(x, y, z) {
+                return;
+            }
+
+            // Survival players get the drops, if they have room for them
+            if self.player.game_mode() == GameMode::Survival {
+                for drop in block.kind.drops() {
+                    self.player.inventory.add(*drop);
+                }
+            }
+
+            let other_half = self.other_half(pos, block);
+            for pos in std::iter::once(pos).chain(other_half) {
+                let mut block = self.get_block_mut(pos).unwrap();
+                // Waterlogged blocks leave their water behind
+                *block = if block.state.is_waterlogged() {
+                    Block::new(TERRAIN_BLOCKS.water)
+                } else {
+                    Block::AIR
+                };
+            }
             self.cast_highlight();
         }
     }
 
-    pub fn facing(&self) -> Option<BlockType> {
-        match self.highlight {
+    /// Starts breaking the targeted block. Creative players break it right
+    /// away, and survival players have to hold on for as long as its
+    /// hardness.
+    pub fn start_breaking(&mut self) {
+        if self.player.game_mode() == GameMode::Survival {
+            self.player.breaking = true;
+        } else {
+            self.destroy_block();
+        }
+    }
+
+    pub fn stop_breaking(&mut self) {
+        self.player.breaking = false;
+    }
+
+    /// How far through breaking the targeted block the player is, from 0 to
+    /// 1. None if they aren't breaking anything.
+    pub fn break_progress(&self) -> Option<f32> {
+        let (pos, seconds) = self.break_progress?;
+        let hardness = self.get_block(pos)?.kind.hardness();
+        Some((seconds / hardness).min(1.0))
+    }
+
+    /// Carries on breaking the targeted block for a tick, breaking it once
+    /// it's been long enough. Starts over if the player looks at another
+    /// block.
+    fn tick_breaking(&mut self) {
+        let target = self.get_highlight().filter(|pos| {
+            self.player.breaking &&
+                self.get_block(*pos).is_some_and(|b| b.kind.hardness() >= 0.0)
+        });
+        let Some(pos) = target else {
+            self.break_progress = None;
+            return;
+        };
+
+        let seconds = match self.break_progress {
+            Some((breaking, seconds)) if breaking == pos => seconds,
+            _ => 0.0,
+        } + 1.0 / PHYSICS_TICK_RATE;
+        if seconds >= self.get_block(pos).unwrap().kind.hardness() {
+            self.destroy_block();
+            self.break_progress = None;
+        } else {
+            self.break_progress = Some((pos, seconds));
+        }
+    }
+
+    /// Hits the targeted mob, if the player is ready to attack. Returns
+    /// whether there was a mob to hit.
+    pub fn attack(&mut self) -> bool {
+        if self.player.game_mode() == GameMode::Spectator {
+            return false;
+        }
+
+        let Some(i) = self.targeted_mob else {
+            return false;
+        };
+
+        if self.player.swing() {
+            let from = self.player.get_precise_pos();
+            let mob = &mut self.mobs[i];
+            mob.hurt(ATTACK_DAMAGE, from);
+
+            if mob.health.is_dead() {
+                self.mobs.remove(i);
+                self.cast_highlight();
+            }
+        }
+        true
+    }
+
+    /// Where the other half of the given two block tall block is, if it's
+    /// there
+    fn other_half(&self, pos: ThreeDimPos, block: Block) -> Option<ThreeDimPos> {
+        if !block.kind.has_state(StateProperty::Half) {
+            return None;
+        }
+
+        let d_y = if block.state.is_upper() { -1 } else { 1 };
+        let other = (pos.0, pos.1 + d_y, pos.2);
+        self.get_block(other)
+            .is_some_and(|b| b.kind == block.kind && b.state.is_upper() != block.state.is_upper())
+            .then_some(other)
+    }
+
+    /// Uses the highlighted block, like opening a door or chest. Returns
+    /// whether it did anything.
+    pub fn use_block(&mut self) -> bool {
+        if self.player.game_mode() == GameMode::Spectator {
+            return false;
+        }
+
+        let Some(pos) = self.get_highlight() else {
+            return false;
+        };
+        let block = self.get_block(pos).unwrap();
+
+        match block.kind.interaction() {
+            Some(Interaction::Toggle) => {
+                let other_half = self.other_half(pos, block);
+                for pos in std::iter::once(pos).chain(other_half) {
+                    let mut block = self.get_block_mut(pos).unwrap();
+                    block.state = block.state.with_open(!block.state.is_open());
+                }
+                self.cast_highlight();
+                true
+            },
+            Some(Interaction::Container(_)) => {
+                self.open_container = Some(pos);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// The inventory the player has open, if any
+    pub fn open_container(&self) -> Option<&Inventory> {
+        let pos = self.open_container?;
+        let (chunk, (x, y, z)) = self.chunk_at(pos)?;
+        match chunk.block_entities.get(&(x, y, z))? {
+            BlockEntity::Container(inventory) => Some(inventory),
+        }
+    }
+
+    fn open_container_mut(&mut self) -> Option<&mut Inventory> {
+        let pos = self.open_container?;
+        let (chunk, (x, y, z)) = self.chunk_at_mut(pos)?;
+        match chunk.block_entity_mut(x, y, z)? {
+            BlockEntity::Container(inventory) => Some(inventory),
+        }
+    }
+
+    pub fn close_container(&mut self) {
+        self.open_container = None;
+    }
+
+    /// Closes the open container if it was broken or the player walked away
+    /// from it
+    fn check_open_container(&mut self) {
+        let Some((x, y, z)) = self.open_container else {
+            return;
+        };
+
+        let center = Point3::new(x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5);
+        let too_far = self.player.get_precise_pos().distance(center) > MAX_HIGHLIGHT_DIST as f32;
+        if too_far || self.open_container().is_none() {
+            self.close_container();
+        }
+    }
+
+    /// Moves one of the player's held block into the given slot of the open
+    /// container
+    pub fn store_in_container(&mut self, slot: usize) {
+        let held = self.player.held;
+        if !self.player.has_held() {
+            return;
+        }
+        let stored = self.open_container_mut().is_some_and(|i| i.insert_one(slot, held));
+        if stored {
+            self.player.use_held();
+        }
+    }
+
+    /// Takes one block out of the given slot of the open container, which the
+    /// player then holds. Survival players need room for it.
+    pub fn take_from_container(&mut self, slot: usize) {
+        let Some(block) = self.open_container_mut().and_then(|i| i.take_one(slot)) else {
+            return;
+        };
+
+        let survival = self.player.game_mode() == GameMode::Survival;
+        if survival && !self.player.inventory.add(block) {
+            self.open_container_mut().unwrap().insert_one(slot, block);
+            return;
+        }
+        self.player.held = block;
+    }
+
+    /// The loaded chunk holding the given block, along with the block's
+    /// chunk-local coordinates
+    fn chunk_at(&self, (x, y, z): ThreeDimPos) -> Option<(&Chunk, (usize, usize, usize))> {
+        let y = y_to_local(y)?;
+        let (local_x, local_z) = cords_to_local((x, z));
+        let chunk = self.chunks.get(&cords_to_chunk((x, z)))?;
+        Some((chunk, (local_x, y, local_z)))
+    }
+
+    fn chunk_at_mut(&mut self, (x, y, z): ThreeDimPos) -> Option<(&mut Chunk, (usize, usize, usize))> {
+        let y = y_to_local(y)?;
+        let (local_x, local_z) = cords_to_local((x, z));
+        let chunk = self.chunks.get_mut(&cords_to_chunk((x, z)))?;
+        Some((chunk, (local_x, y, local_z)))
+    }
+
+    /// Saves every edited chunk, along with where the player is, what they're
+    /// carrying and the time of day
+    pub fn save(&mut self) {
+        for chunk in self.chunks.values_mut() {
+            if save_if_modified(&self.info.dir, chunk) {
+                chunk.mark_saved();
+            }
+        }
+
+        self.info.player = Some(self.player.get_precise_pos());
+        self.info.inventory = self.player.inventory.clone();
+        self.info.time = self.time.ticks();
+        if let Err(e) = self.info.save() {
+            log::error!("{:#}", e);
+        }
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.info.seed
+    }
+
+    /// Changes how many chunks are loaded away from the player. They're
+    /// loaded or unloaded to match on the next `update_chunks_to_player`.
+    pub fn set_render_dist(&mut self, render_dist: usize) {
+        self.render_dist = render_dist;
+    }
+
+    /// Forgets the time since the last tick, so that time spent paused isn't
+    /// caught up on
+    pub fn resume(&mut self) {
+        self.last_update = Instant::now();
+    }
+
+    /// Places the player's held block against the highlighted side, oriented
+    /// by where they clicked and which way they're looking
+    pub fn place_block(&mut self) {
+        if self.player.game_mode() == GameMode::Spectator {
+            return;
+        }
+
+        let Some((pos, side)) = self.highlight else {
+            return;
+        };
+        let (d_x, d_y, d_z) = side.offset();
+        let target = (pos.0 + d_x, pos.1 + d_y, pos.2 + d_z);
+
+        // Only replace things that can't be targeted, like air and water
+        let Some(replacing) = self.get_block(target) else {
+            return;
+        };
+        if replacing.kind.is_targetable() {
+            return;
+        }
+
+        let block = Block::placed(self.player.held, side, self.player.facing, replacing);
+        let mut placing = vec![(target, block)];
+
+        // Two block tall blocks need room for their upper half too
+        if block.kind.has_state(StateProperty::Half) {
+            let above = (target.0, target.1 + 1, target.2);
+            match self.get_block(above) {
+                Some(b) if !b.kind.is_targetable() => {
+                    placing.push((above, Block { state: block.state.with_upper(true), ..block }));
+                },
+                _ => return,
+            }
+        }
+
+        if block.kind.is_solid() {
+            // Don't place blocks on top of the player
+            let player = self.player.entity.bounds();
+            let blocked = placing.iter().any(|(pos, block)| {
+                block.kind.shape()
+                    .collision_boxes(self.model_context(*pos, *block))
+                    .iter()
+                    .any(|b| overlaps(box_bounds(*pos, *b), player))
+            });
+            if blocked {
+                return;
+            }
+        }
+
+        if !self.player.use_held() {
+            return;
+        }
+        for (pos, block) in placing {
+            *self.get_block_mut(pos).unwrap() = block;
+        }
+        self.cast_highlight();
+    }
+
+    pub fn loaded_chunks(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Blocks generated into chunks that aren't loaded yet, waiting to be
+    /// put in once they are
+    pub fn pending_blocks(&self) -> usize {
+        self.block_scratch.len()
+    }
+
+    /// The height of the generated terrain at the given column, before any
+    /// edits
+    pub fn terrain_height(&self, x: Coordinate, z: Coordinate) -> Coordinate {
+        self.generator.sample_elevation(x, z)
+    }
+
+    /// Approximate memory used by the block data of all loaded chunks, in
+    /// bytes
+    pub fn block_memory(&self) -> usize {
+        self.chunks.values().map(|c| c.block_memory()).sum()
+    }
+
+    /// Whether the player's eyes (and thus the camera) are inside of a fluid
+    pub fn camera_in_fluid(&self) -> bool {
+        self.get_block(self.player.get_world_pos())
+            .is_some_and(|b| b.is_fluid())
+    }
+
+    pub fn facing(&self) -> Option<Block> {
+        match self.get_highlight() {
             Some(p) => self.get_block(p),
             None => None,
         }
     }
 }
+
+/// Saves the chunk if it has been edited, logging any errors. Returns whether
+/// it was saved.
+fn save_if_modified(dir: &Path, chunk: &Chunk) -> bool {
+    if !chunk.is_modified() {
+        return false;
+    }
+
+    match save_chunk(dir, chunk) {
+        Ok(()) => true,
+        Err(e) => {
+            log::error!("{:#}", e);
+            false
+        },
+    }
+}
+
+/// The position of the chunk on the given side of the one at the given
+/// position
+fn neighbour_chunk(pos: WorldPos, side: BlockSide) -> WorldPos {
+    let (d_x, _, d_z) = side.offset();
+    let size = CHUNK_SIZE as Coordinate;
+    (pos.0 + d_x * size, pos.1 + d_z * size)
+}
+
+/// The side of the given bounds that a point on their surface lies on
+fn hit_side(point: Point3<f32>, (min, max): Bounds) -> BlockSide {
+    [
+        (point.x - min.x, BlockSide::Left),
+        (max.x - point.x, BlockSide::Right),
+        (point.y - min.y, BlockSide::Bottom),
+        (max.y - point.y, BlockSide::Top),
+        (point.z - min.z, BlockSide::Front),
+        (max.z - point.z, BlockSide::Back),
+    ]
+        .into_iter()
+        .min_by(|a, b| a.0.abs().total_cmp(&b.0.abs()))
+        .unwrap()
+        .1
+}
@@ -0,0 +1,220 @@
+use std::{cmp::Reverse, collections::{BinaryHeap, HashMap}};
+use cgmath::Point3;
+use crate::{physics::{AABB, collision::{Collider, collides_with_solid, is_supported}}, settings::{GRAVITY_A, JUMP_SPEED}, world::ThreeDimPos};
+
+/// The furthest down a path drops in one step
+const MAX_DROP: i32 = 3;
+/// The most cells looked at before giving up on reaching the goal
+const MAX_NODES: usize = 2000;
+
+/// Costs of each kind of move, for preferring flat paths
+const WALK_COST: u32 = 10;
+const JUMP_COST: u32 = 20;
+const DROP_COST_PER_BLOCK: u32 = 5;
+
+/// How many blocks up a jump gets an entity
+fn max_climb() -> i32 {
+    (JUMP_SPEED * JUMP_SPEED / (2.0 * -GRAVITY_A.y)).floor() as i32
+}
+
+/// Where an entity stands when in the given cell, being the middle of the
+/// bottom of it
+pub fn cell_feet((x, y, z): ThreeDimPos) -> Point3<f32> {
+    Point3::new(x as f32 + 0.5, y as f32, z as f32 + 0.5)
+}
+
+/// Whether an entity with the given box, with its origin at the bottom
+/// middle, fits in the given cell
+fn fits(world: &impl Collider, aabb: &AABB, cell: ThreeDimPos) -> bool {
+    !collides_with_solid(world, aabb.get_bounds(cell_feet(cell)))
+}
+
+/// Whether the entity can stand in the given cell, on something solid and out
+/// of any fluid
+pub fn is_walkable(world: &impl Collider, aabb: &AABB, cell: ThreeDimPos) -> bool {
+    fits(world, aabb, cell) &&
+        is_supported(world, aabb.get_bounds(cell_feet(cell))) &&
+        !world.is_fluid(cell.0, cell.1, cell.2)
+}
+
+/// Lower bound on the cost of getting between two cells
+fn heuristic(a: ThreeDimPos, b: ThreeDimPos) -> u32 {
+    WALK_COST * (a.0.abs_diff(b.0) + a.2.abs_diff(b.2)) + DROP_COST_PER_BLOCK * a.1.abs_diff(b.1)
+}
+
+/// The cells an entity can get to in one move from the given one, along with
+/// what it costs to get there
+fn neighbours(world: &impl Collider, aabb: &AABB, (x, y, z): ThreeDimPos) -> Vec<(ThreeDimPos, u32)> {
+    let mut out = vec![];
+
+    for (d_x, d_z) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+        let (n_x, n_z) = (x + d_x, z + d_z);
+
+        if fits(world, aabb, (n_x, y, n_z)) {
+            if is_walkable(world, aabb, (n_x, y, n_z)) {
+                out.push(((n_x, y, n_z), WALK_COST));
+                continue;
+            }
+
+            // Walk off the edge, and fall until landing on something
+            for drop in 1..=MAX_DROP {
+                let below = (n_x, y - drop, n_z);
+                if !fits(world, aabb, below) {
+                    break;
+                }
+                if is_walkable(world, aabb, below) {
+                    out.push((below, WALK_COST + DROP_COST_PER_BLOCK * drop as u32));
+                    break;
+                }
+            }
+        } else {
+            // Jump up onto whatever is in the way, if there's room overhead
+            for climb in 1..=max_climb() {
+                if !fits(world, aabb, (x, y + climb, z)) {
+                    break;
+                }
+                let above = (n_x, y + climb, n_z);
+                if is_walkable(world, aabb, above) {
+                    out.push((above, JUMP_COST * climb as u32));
+                    break;
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// Finds a path for an entity with the given bounding box (with its origin at
+/// the bottom middle) from one cell to another using A*, as the cells to walk
+/// through in order, not including the start. If the goal can't be reached
+/// (or is too far away to find quickly), leads as close to it as it can get
+/// instead. None if it can't get any closer than it already is.
+pub fn find_path(world: &impl Collider, aabb: &AABB, start: ThreeDimPos, goal: ThreeDimPos) -> Option<Vec<ThreeDimPos>> {
+    let mut open = BinaryHeap::new();
+    // Each cell reached, with the cheapest cost to get there and where from
+    let mut came_from: HashMap<ThreeDimPos, (u32, Option<ThreeDimPos>)> = HashMap::new();
+    let mut closest = (heuristic(start, goal), start);
+
+    open.push(Reverse((heuristic(start, goal), start)));
+    came_from.insert(start, (0, None));
+
+    let mut explored = 0;
+    while let Some(Reverse((_, current))) = open.pop() {
+        if current == goal {
+            closest = (0, current);
+            break;
+        }
+
+        explored += 1;
+        if explored > MAX_NODES {
+            break;
+        }
+
+        let cost = came_from[&current].0;
+        for (next, move_cost) in neighbours(world, aabb, current) {
+            let next_cost = cost + move_cost;
+            if came_from.get(&next).is_some_and(|(c, _)| *c <= next_cost) {
+                continue;
+            }
+
+            came_from.insert(next, (next_cost, Some(current)));
+            let remaining = heuristic(next, goal);
+            closest = closest.min((remaining, next));
+            open.push(Reverse((next_cost + remaining, next)));
+        }
+    }
+
+    let (_, end) = closest;
+    if end == start {
+        return None;
+    }
+
+    let mut path = vec![end];
+    while let Some((_, Some(previous))) = came_from.get(path.last().unwrap()) {
+        if *previous == start {
+            break;
+        }
+        path.push(*previous);
+    }
+    path.reverse();
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SMALL: AABB = AABB::new(0.6, 1.8, 0.6, Point3::new(0.3, 0.0, 0.3)).unwrap();
+
+    /// A floor at y = 0, with a wall along x = 5 that has a one block step in
+    /// it at z = 8, and a pit at x = -3
+    struct Course;
+
+    impl Collider for Course {
+        fn is_solid(&self, x: i32, y: i32, z: i32) -> bool {
+            match (x, y, z) {
+                (_, 0, _) => x != -3,
+                (-3, -2, _) => true,
+                (5, 1, 8) => true,
+                (5, _, _) => (1..=3).contains(&y) && z != 8,
+                _ => false,
+            }
+        }
+
+        fn is_fluid(&self, _x: i32, _y: i32, _z: i32) -> bool {
+            false
+        }
+    }
+
+    /// Whether each step of the path is a move `neighbours` allows
+    fn is_connected(start: ThreeDimPos, path: &[ThreeDimPos]) -> bool {
+        std::iter::once(&start).chain(path).zip(path)
+            .all(|(a, b)| neighbours(&Course, &SMALL, *a).iter().any(|(n, _)| n == b))
+    }
+
+    #[test]
+    fn paths_climb_steps_to_get_past_walls() {
+        let start = (0, 1, 0);
+        let goal = (8, 1, 0);
+        let path = find_path(&Course, &SMALL, start, goal).unwrap();
+
+        assert_eq!(path.last(), Some(&goal));
+        assert!(path.contains(&(5, 2, 8)), "{:?}", path);
+        assert!(is_connected(start, &path));
+    }
+
+    #[test]
+    fn paths_drop_into_pits_but_not_out() {
+        let start = (0, 1, 0);
+        let path = find_path(&Course, &SMALL, start, (-3, -1, 0)).unwrap();
+        assert_eq!(path, vec![(-1, 1, 0), (-2, 1, 0), (-3, -1, 0)]);
+
+        // Two blocks is too high to jump, so there's no way back out
+        let path = find_path(&Course, &SMALL, (-3, -1, 0), (-5, 1, 0));
+        assert_eq!(path, None);
+    }
+
+    #[test]
+    fn tall_entities_dont_fit_under_low_ceilings() {
+        /// A floor at y = 0, with a ceiling at y = 3 past x = 2
+        struct Tunnel;
+
+        impl Collider for Tunnel {
+            fn is_solid(&self, x: i32, y: i32, _z: i32) -> bool {
+                y == 0 || (y == 3 && x >= 2)
+            }
+
+            fn is_fluid(&self, _x: i32, _y: i32, _z: i32) -> bool {
+                false
+            }
+        }
+
+        const TALL: AABB = AABB::new(0.6, 2.5, 0.6, Point3::new(0.3, 0.0, 0.3)).unwrap();
+        let start = (0, 1, 0);
+        let goal = (4, 1, 0);
+
+        assert_eq!(find_path(&Tunnel, &SMALL, start, goal).unwrap().last(), Some(&goal));
+        assert_eq!(find_path(&Tunnel, &TALL, start, goal), Some(vec![(1, 1, 0)]));
+    }
+}
@@ -1,40 +1,321 @@
+use std::time::{Duration, Instant};
 use cgmath::{InnerSpace, Point3, Vector2, Vector3, Zero};
-use crate::{physics::{Entity, RawEntity}, settings::{GRAVITY_A, JUMP_SPEED, MOVE_SPEED, PLAYER_AABB, SPRINT_MULTIPLIER}, vectors::{replace_xz, xyz_to_xz}, world::{GameWorld, generation::sample_elevation}};
+use crate::{physics::{Entity, RawEntity}, settings::{ATTACK_COOLDOWN, DOUBLE_TAP_WINDOW, EAT_TIME, FLY_MULTIPLIER, FLY_VERTICAL_SPEED, GRAVITY_A, JUMP_EXHAUSTION, JUMP_SPEED, MAX_HEALTH, MOVE_SPEED, PHYSICS_TICK_RATE, PLAYER_AABB, PLAYER_SLOTS, PLAYER_STEP_HEIGHT, SNEAK_AABB, SNEAK_MULTIPLIER, SPRINT_EXHAUSTION, SPRINT_JUMP_EXHAUSTION, SPRINT_MULTIPLIER, SWIM_SPEED, VOID_DAMAGE, VOID_DEPTH, WATER_MOVE_MULTIPLIER, WORLD_MIN_Y}, vectors::{replace_xz, xyz_to_xz}, world::{GameWorld, block::BlockType, block_entity::Inventory, combat::{Health, knock_back}, hunger::Hunger}};
+
+/// How the player interacts with the world
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GameMode {
+    /// Regular physics
+    Survival,
+    /// Regular physics, but can fly by double tapping space
+    Creative,
+    /// Always flying, and passes through blocks
+    Spectator,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 3] = [Self::Survival, Self::Creative, Self::Spectator];
+
+    /// The mode after this one, for cycling through modes with a key
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Survival => Self::Creative,
+            Self::Creative => Self::Spectator,
+            Self::Spectator => Self::Survival,
+        }
+    }
+}
 
 pub struct Player {
     /// The inner physics entity determining position
     pub entity: RawEntity,
     pub facing: Vector3<f32>,
+    /// The block placed when using the place button
+    pub held: BlockType,
+    /// What a survival player has to place. Other modes place as much as
+    /// they like.
+    pub inventory: Inventory,
 
     pub w_pressed: bool,   
     pub a_pressed: bool,   
     pub s_pressed: bool,   
     pub d_pressed: bool,   
     pub jump: bool, 
+    /// Whether space is currently held down, for swimming and flying
+    pub space_pressed: bool,
     pub sprint: bool,
+    /// Whether the sneak key is held down
+    pub sneak: bool,
+    /// Whether the player is actually sneaking. Can lag behind `sneak` if
+    /// there's no room to stand back up.
+    sneaking: bool,
+
+    game_mode: GameMode,
+    /// Whether the player is flying (no gravity, with vertical controls)
+    flying: bool,
+    /// When space was last tapped, for detecting double taps
+    last_space_tap: Option<Instant>,
+
+    health: Health,
+    hunger: Hunger,
+    /// Whether the player is eating their held block, by holding right click
+    eating: bool,
+    /// Seconds spent eating the current bite
+    eat_progress: f32,
+    /// Whether the player is breaking the block they're facing, by holding
+    /// left click
+    pub breaking: bool,
+    /// Seconds until the player can attack again
+    attack_timer: f32,
+    /// Where the player goes back to when they die
+    spawn: Point3<f32>,
 }
 
 impl Player {
-    pub fn new() -> Self {
-        let y = (sample_elevation(0, 0) + 2) as f32;
-        let mut entity = RawEntity::new(Point3::new(0.0, y, 0.0), PLAYER_AABB);
+    /// A player at the given spawn point, where their eyes go
+    pub fn new(spawn: Point3<f32>) -> Self {
+        let mut entity = RawEntity::new(spawn, PLAYER_AABB);
         entity.set_acceleration(GRAVITY_A);
+        entity.set_step_height(PLAYER_STEP_HEIGHT);
 
         Self {
             entity,
             facing: Vector3::zero(),
+            held: placeable_blocks().next().unwrap_or(BlockType::AIR),
+            inventory: Inventory::new(PLAYER_SLOTS),
             w_pressed: false,
             a_pressed: false,
             s_pressed: false,
             d_pressed: false,
             jump: false,
+            space_pressed: false,
             sprint: false,
+            sneak: false,
+            sneaking: false,
+            game_mode: GameMode::Survival,
+            flying: false,
+            last_space_tap: None,
+            health: Health::new(MAX_HEALTH),
+            hunger: Hunger::new(),
+            eating: false,
+            eat_progress: 0.0,
+            breaking: false,
+            attack_timer: 0.0,
+            spawn,
         }
     }
+
+    pub fn health(&self) -> f32 {
+        self.health.current()
+    }
+
+    pub fn hunger(&self) -> &Hunger {
+        &self.hunger
+    }
+
+    /// How far through eating a bite the player is, from 0 to 1. None if
+    /// they aren't eating.
+    pub fn eat_progress(&self) -> Option<f32> {
+        self.eating.then_some(self.eat_progress / EAT_TIME)
+    }
+
+    /// Starts eating the held block, if it's food, the player has one and
+    /// they're hungry. Returns whether they started.
+    pub fn start_eating(&mut self) -> bool {
+        self.eating = self.held.food().is_some() &&
+            self.game_mode == GameMode::Survival &&
+            self.has_held() &&
+            !self.hunger.is_full();
+        self.eat_progress = 0.0;
+        self.eating
+    }
+
+    pub fn stop_eating(&mut self) {
+        self.eating = false;
+    }
+
+    /// Carries on eating for a tick, finishing a bite once it's been long
+    /// enough, which uses up one of the held block. Stops if the player
+    /// switches to something that isn't food, runs out or fills up.
+    fn eat(&mut self) {
+        let Some(food) = self.held.food().filter(|_| self.has_held()) else {
+            self.eating = false;
+            return;
+        };
+
+        self.eat_progress += 1.0 / PHYSICS_TICK_RATE;
+        if self.eat_progress >= EAT_TIME {
+            self.use_held();
+            self.hunger.eat(food.hunger, food.saturation);
+            self.eat_progress = 0.0;
+            self.eating = !self.hunger.is_full() && self.has_held();
+        }
+    }
+
+    /// Whether the player was hurt recently enough to flash red
+    pub fn is_hurt(&self) -> bool {
+        self.health.is_flashing()
+    }
+
+    /// Hurts the player, knocking them away from the given point. Only
+    /// survival players can be hurt. Returns whether they were.
+    pub fn hurt(&mut self, amount: f32, from: Point3<f32>) -> bool {
+        if self.game_mode != GameMode::Survival || !self.health.hurt(amount) {
+            return false;
+        }
+
+        knock_back(self, from);
+        if self.health.is_dead() {
+            self.respawn();
+        }
+        true
+    }
+
+    /// Starts an attack, if the last one was long enough ago. Returns whether
+    /// it did.
+    pub fn swing(&mut self) -> bool {
+        if self.attack_timer > 0.0 {
+            return false;
+        }
+
+        self.attack_timer = ATTACK_COOLDOWN;
+        true
+    }
+
+    /// Sends the player back to spawn with full health
+    fn respawn(&mut self) {
+        self.entity.set_pos(self.spawn);
+        self.entity.set_velocity(Vector3::zero());
+        self.health.reset();
+        self.hunger = Hunger::new();
+        self.eating = false;
+    }
+
+    /// Moves the player so that their feet are at the given point, stopping
+    /// them
+    pub fn teleport(&mut self, feet: Point3<f32>) {
+        let eye_height = self.get_precise_pos().y - self.entity.bounds().0.y;
+        self.entity.set_pos(feet + Vector3::new(0.0, eye_height, 0.0));
+        self.entity.set_velocity(Vector3::zero());
+    }
+
+    pub fn game_mode(&self) -> GameMode {
+        self.game_mode
+    }
+
+    pub fn set_game_mode(&mut self, mode: GameMode) {
+        self.game_mode = mode;
+        self.flying = mode == GameMode::Spectator;
+        self.entity.set_noclip(mode == GameMode::Spectator);
+    }
+
+    /// Whether the player has any of the held block to use. Only survival
+    /// players can run out.
+    pub fn has_held(&self) -> bool {
+        self.game_mode != GameMode::Survival || self.inventory.count(self.held) > 0
+    }
+
+    /// Uses up one of the held block, if the player is in survival. Returns
+    /// false if they don't have one.
+    pub fn use_held(&mut self) -> bool {
+        self.game_mode != GameMode::Survival || self.inventory.remove(self.held)
+    }
+
+    /// Switches the held block to the one `steps` further along in the
+    /// registry, wrapping around. Survival players only go through the
+    /// blocks they have, if they have any.
+    pub fn cycle_held(&mut self, steps: i32) {
+        let mut blocks: Vec<BlockType> = placeable_blocks().collect();
+        if self.game_mode == GameMode::Survival {
+            let carried: Vec<BlockType> = blocks.iter()
+                .copied()
+                .filter(|b| *b == self.held || self.inventory.count(*b) > 0)
+                .collect();
+            if carried.len() > 1 {
+                blocks = carried;
+            }
+        }
+        if blocks.is_empty() {
+            return;
+        }
+
+        let current = blocks.iter().position(|b| *b == self.held).unwrap_or(0);
+        let next = (current as i32 + steps).rem_euclid(blocks.len() as i32);
+        self.held = blocks[next as usize];
+    }
+
+    /// Lets go of every movement key, for when input goes somewhere else
+    pub fn release_controls(&mut self) {
+        self.w_pressed = false;
+        self.a_pressed = false;
+        self.s_pressed = false;
+        self.d_pressed = false;
+        self.jump = false;
+        self.space_pressed = false;
+        self.sprint = false;
+        self.sneak = false;
+        self.eating = false;
+        self.breaking = false;
+    }
+
+    /// Registers a (non-repeated) press of the space key. Double tapping
+    /// toggles flight in creative mode.
+    pub fn tap_space(&mut self) {
+        let now = Instant::now();
+        let double_tap = self.last_space_tap
+            .is_some_and(|t| now - t < Duration::from_secs_f32(DOUBLE_TAP_WINDOW));
+
+        if double_tap && self.game_mode == GameMode::Creative {
+            self.flying = !self.flying;
+            self.last_space_tap = None;
+        } else {
+            self.last_space_tap = Some(now);
+        }
+    }
+}
+
+/// The blocks the player can hold, being those that can be targeted once
+/// placed
+fn placeable_blocks() -> impl Iterator<Item = BlockType> {
+    BlockType::all().filter(|b| b.is_targetable())
 }
 
 impl Entity for Player {
     fn tick(&mut self, world: &GameWorld) { 
+        self.health.tick();
+        self.attack_timer -= 1.0 / PHYSICS_TICK_RATE;
+
+        let survival = self.game_mode == GameMode::Survival;
+        if self.eating {
+            self.eat();
+        }
+        // Hungry players are too weak to sprint
+        let sprinting = self.sprint && (!survival || self.hunger.can_sprint());
+        let mut exhaustion = 0.0;
+
+        // Creative players stop flying once they land
+        if self.flying && self.game_mode == GameMode::Creative &&
+            self.entity.is_on_ground() {
+
+            self.flying = false;
+        }
+
+        if self.flying {
+            self.entity.set_acceleration(Vector3::zero());
+        } else {
+            self.entity.set_acceleration(GRAVITY_A);
+        }
+
+        // While flying, the sneak key is for going down instead
+        let want_sneak = self.sneak && !self.flying;
+        if want_sneak != self.sneaking {
+            let bounding_box = if want_sneak { SNEAK_AABB } else { PLAYER_AABB };
+            if self.entity.set_bounding_box(world, bounding_box) {
+                self.sneaking = want_sneak;
+                self.entity.set_edge_guard(self.sneaking);
+            }
+        }
+
         let mut desired_x = 0.0;
         let mut desired_z = 0.0;
 
@@ -54,24 +335,88 @@ impl Entity for Player {
         let forward = xyz_to_xz(self.facing).normalize();
         let right = Vector2::new(-forward.y, forward.x).normalize();
 
-        let speed = if self.sprint {
+        let mut speed = if sprinting {
             MOVE_SPEED * SPRINT_MULTIPLIER
         } else { 
             MOVE_SPEED
         };
 
-        let new_xz = ((forward * desired_z) + (right * desired_x)) * speed;
-        let mut new_xyz = replace_xz(self.entity.get_velocity(), new_xz);
+        if self.sneaking {
+            speed *= SNEAK_MULTIPLIER;
+        }
+
+        if self.flying {
+            speed *= FLY_MULTIPLIER;
+        }
+
+        let submersion = self.entity.get_submersion();
+        if submersion > 0.0 && !self.flying {
+            speed *= WATER_MOVE_MULTIPLIER;
+        }
+
+        let mut new_xyz = self.entity.get_velocity();
+        // Knockback carries the player for a moment before they get control
+        // back
+        if !self.health.is_flashing() {
+            let new_xz = ((forward * desired_z) + (right * desired_x)) * speed;
+            new_xyz = replace_xz(new_xyz, new_xz);
+        }
         
-        if self.jump {
+        if self.flying {
             self.jump = false;
-            new_xyz.y = JUMP_SPEED;
+
+            let mut vertical = 0.0;
+            if self.space_pressed {
+                vertical += 1.0;
+            }
+            if self.sneak {
+                vertical -= 1.0;
+            }
+            new_xyz.y = vertical * FLY_VERTICAL_SPEED;
+        } else {
+            if self.jump {
+                self.jump = false;
+                // Can't jump off of water, but can still jump out of the
+                // shallows
+                if submersion < 0.5 {
+                    new_xyz.y = JUMP_SPEED;
+                    exhaustion += if sprinting { SPRINT_JUMP_EXHAUSTION } else { JUMP_EXHAUSTION };
+                }
+            }
+
+            if self.space_pressed && submersion > 0.0 {
+                new_xyz.y = new_xyz.y.max(SWIM_SPEED);
+            }
         }
 
         self.entity.set_velocity(new_xyz);
 
-
+        let before = self.entity.get_precise_pos();
         self.entity.tick(world);
+
+        if survival {
+            if sprinting && !self.flying {
+                let moved = self.entity.get_precise_pos() - before;
+                exhaustion += xyz_to_xz(moved).magnitude() * SPRINT_EXHAUSTION;
+            }
+            self.hunger.exhaust(exhaustion);
+            self.hunger.tick(&mut self.health);
+            if self.health.is_dead() {
+                self.respawn();
+            }
+        }
+
+        // Falling too far out of the world hurts, until the player dies and
+        // respawns
+        let void_y = WORLD_MIN_Y as f32 - VOID_DEPTH;
+        if self.game_mode != GameMode::Spectator &&
+            self.entity.get_precise_pos().y < void_y {
+
+            self.health.drain(VOID_DAMAGE / PHYSICS_TICK_RATE);
+            if self.health.is_dead() {
+                self.respawn();
+            }
+        }
     }
 
     fn get_precise_pos(&self) -> Point3<f32> {
@@ -97,6 +442,6 @@ impl Entity for Player {
 
 impl Default for Player {
     fn default() -> Self {
-        Self::new()
+        Self::new(Point3::new(0.0, 0.0, 0.0))
     }
 }
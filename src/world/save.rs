@@ -0,0 +1,447 @@
+use std::{collections::HashMap, fs, io::ErrorKind, path::{Path, PathBuf}};
+use anyhow::{Context, bail};
+use cgmath::Point3;
+use serde::{Deserialize, Serialize};
+use crate::{settings::{LEGACY_SEED, PLAYER_SLOTS, SAVES_DIR}, world::{WorldPos, block::BlockType, block_entity::{BlockEntity, Inventory, ItemStack, MAX_STACK}, chunk::{Chunk, SECTIONS}, state::{Block, BlockState}, storage::BlockStorage, time::WorldTime}};
+
+/// Start of every chunk file
+const MAGIC: &[u8; 4] = b"AZPC";
+/// Bumped whenever the format changes
+const VERSION: u8 = 1;
+
+/// Block entity kinds, as stored
+const CONTAINER: u8 = 0;
+
+/// The file in each world's directory describing it
+const WORLD_FILE: &str = "world.toml";
+
+/// A world's `WORLD_FILE`, as written
+#[derive(Serialize, Deserialize)]
+struct WorldFile {
+    name: String,
+    seed: u32,
+    time: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    player: Option<[f32; 3]>,
+    /// The slots of the player's inventory that have something in them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    inventory: Vec<SavedStack>,
+}
+
+/// A stack in the player's inventory, as written
+#[derive(Serialize, Deserialize)]
+struct SavedStack {
+    slot: usize,
+    block: String,
+    count: u8,
+}
+
+/// A world in the saves directory, and where it was left
+#[derive(Clone, Debug)]
+pub struct WorldInfo {
+    pub name: String,
+    pub seed: u32,
+    /// Where it's saved
+    pub dir: PathBuf,
+    /// Where the player's eyes were, None if they've never been in it
+    pub player: Option<Point3<f32>>,
+    /// Physics ticks since it was created, as in `WorldTime::ticks`
+    pub time: u64,
+    /// What the player was carrying
+    pub inventory: Inventory,
+}
+
+impl WorldInfo {
+    /// Reads the world saved in the given directory
+    fn load(dir: &Path) -> anyhow::Result<Self> {
+        let path = dir.join(WORLD_FILE);
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Couldn't read {}", path.display()))?;
+        let file: WorldFile = toml::from_str(&text)
+            .with_context(|| format!("Couldn't parse {}", path.display()))?;
+
+        Ok(Self {
+            name: file.name,
+            seed: file.seed,
+            dir: dir.to_path_buf(),
+            player: file.player.map(Point3::from),
+            time: file.time,
+            inventory: load_inventory(&file.inventory),
+        })
+    }
+
+    /// Writes the world's file, making its directory if needed
+    pub fn save(&self) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Couldn't create {}", self.dir.display()))?;
+
+        let file = WorldFile {
+            name: self.name.clone(),
+            seed: self.seed,
+            time: self.time,
+            player: self.player.map(Into::into),
+            inventory: self.inventory.slots().iter()
+                .enumerate()
+                .filter_map(|(slot, stack)| {
+                    let stack = stack.as_ref()?;
+                    Some(SavedStack { slot, block: stack.block.name().to_string(), count: stack.count })
+                })
+                .collect(),
+        };
+        let text = toml::to_string(&file).context("Couldn't write the world file")?;
+
+        let path = self.dir.join(WORLD_FILE);
+        fs::write(&path, text)
+            .with_context(|| format!("Couldn't write {}", path.display()))
+    }
+}
+
+/// The player's inventory, from its saved stacks. Stacks of blocks that
+/// aren't in the block registry any more, or in slots that don't exist, are
+/// logged and left out.
+fn load_inventory(saved: &[SavedStack]) -> Inventory {
+    let mut slots = vec![None; PLAYER_SLOTS];
+    for stack in saved {
+        let Some(block) = BlockType::from_name(&stack.block) else {
+            log::warn!("Carried block \"{}\" isn't in the block registry, leaving it out", stack.block);
+            continue;
+        };
+        match slots.get_mut(stack.slot) {
+            Some(slot) => *slot = Some(ItemStack { block, count: stack.count.clamp(1, MAX_STACK) }),
+            None => log::warn!("Inventory slot {} doesn't exist, leaving it out", stack.slot),
+        }
+    }
+    Inventory::from_slots(slots)
+}
+
+/// Every world in the saves directory, sorted by name. Worlds that can't be
+/// read are logged and left out.
+pub fn list_worlds() -> Vec<WorldInfo> {
+    let entries = match fs::read_dir(SAVES_DIR) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return vec![],
+        Err(e) => {
+            log::error!("Couldn't list worlds in {}: {}", SAVES_DIR, e);
+            return vec![];
+        },
+    };
+
+    let mut worlds: Vec<WorldInfo> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|dir| dir.is_dir())
+        .filter_map(|dir| {
+            // Worlds from before there was a world file only have chunks
+            if !dir.join(WORLD_FILE).exists() && dir.join("chunks").is_dir() {
+                let name = dir.file_name()?.to_string_lossy().into_owned();
+                let time = WorldTime::new().ticks();
+                return Some(WorldInfo {
+                    name,
+                    seed: LEGACY_SEED,
+                    dir,
+                    player: None,
+                    time,
+                    inventory: Inventory::new(PLAYER_SLOTS),
+                });
+            }
+
+            WorldInfo::load(&dir)
+                .inspect_err(|e| log::error!("{:#}", e))
+                .ok()
+        })
+        .collect();
+    worlds.sort_by(|a, b| a.name.cmp(&b.name));
+    worlds
+}
+
+/// Makes a new world in the saves directory, in a directory named after it
+pub fn create_world(name: &str, seed: u32) -> anyhow::Result<WorldInfo> {
+    let name = name.trim();
+    if name.is_empty() {
+        bail!("Worlds need a name");
+    }
+
+    let mut dir_name: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    let base = dir_name.clone();
+    let mut n = 1;
+    while Path::new(SAVES_DIR).join(&dir_name).exists() {
+        n += 1;
+        dir_name = format!("{}_{}", base, n);
+    }
+
+    let world = WorldInfo {
+        name: name.to_string(),
+        seed,
+        dir: Path::new(SAVES_DIR).join(dir_name),
+        player: None,
+        time: WorldTime::new().ticks(),
+        inventory: Inventory::new(PLAYER_SLOTS),
+    };
+    world.save()?;
+    Ok(world)
+}
+
+/// Deletes a world and everything saved in it
+pub fn delete_world(world: &WorldInfo) -> anyhow::Result<()> {
+    fs::remove_dir_all(&world.dir)
+        .with_context(|| format!("Couldn't delete {}", world.dir.display()))
+}
+
+fn chunk_path(dir: &Path, (x, z): WorldPos) -> PathBuf {
+    dir.join("chunks").join(format!("{}_{}.chunk", x, z))
+}
+
+/// Writes the chunk out to the given world's directory
+pub fn save_chunk(dir: &Path, chunk: &Chunk) -> anyhow::Result<()> {
+    let path = chunk_path(dir, chunk.pos());
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Couldn't create {}", dir.display()))?;
+    }
+
+    fs::write(&path, write_chunk(chunk))
+        .with_context(|| format!("Couldn't write {}", path.display()))
+}
+
+/// Loads the chunk at the given position from the given world's directory.
+/// None if it was never saved.
+pub fn load_chunk(dir: &Path, pos: WorldPos) -> anyhow::Result<Option<Chunk>> {
+    let path = chunk_path(dir, pos);
+    let bytes = match fs::read(&path) {
+        Ok(b) => b,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Couldn't read {}", path.display())),
+    };
+
+    read_chunk(pos, &bytes)
+        .with_context(|| format!("Couldn't load {}", path.display()))
+        .map(Some)
+}
+
+/// Blocks are saved by name rather than id, so saves survive changes to the
+/// block registry
+fn write_chunk(chunk: &Chunk) -> Vec<u8> {
+    let mut out = Writer(MAGIC.to_vec());
+    out.u8(VERSION);
+
+    out.u16(chunk.sections.len() as u16);
+    for section in &chunk.sections {
+        let (palette, bits, data) = section.blocks.raw();
+        out.u16(palette.len() as u16);
+        for block in palette {
+            out.block_type(block.kind);
+            out.u8(block.state.to_bits());
+        }
+        out.u8(bits as u8);
+        out.u32(data.len() as u32);
+        for word in data {
+            out.u64(*word);
+        }
+    }
+
+    out.u32(chunk.block_entities.len() as u32);
+    for ((x, y, z), entity) in &chunk.block_entities {
+        out.u8(*x as u8);
+        out.u16(*y as u16);
+        out.u8(*z as u8);
+
+        match entity {
+            BlockEntity::Container(inventory) => {
+                out.u8(CONTAINER);
+                out.u16(inventory.slots().len() as u16);
+                for slot in inventory.slots() {
+                    match slot {
+                        Some(stack) => {
+                            out.u8(stack.count);
+                            out.block_type(stack.block);
+                        },
+                        None => out.u8(0),
+                    }
+                }
+            },
+        }
+    }
+
+    out.0
+}
+
+fn read_chunk(pos: WorldPos, bytes: &[u8]) -> anyhow::Result<Chunk> {
+    let mut input = Reader(bytes);
+    if input.take(MAGIC.len())? != MAGIC {
+        bail!("Not a chunk file");
+    }
+    let version = input.u8()?;
+    if version != VERSION {
+        bail!("Unsupported chunk format version {}", version);
+    }
+
+    let section_count = input.u16()? as usize;
+    if section_count != SECTIONS {
+        bail!("Expected {} sections, but there are {}", SECTIONS, section_count);
+    }
+
+    let mut sections = Vec::with_capacity(section_count);
+    for i in 0..section_count {
+        let palette_len = input.u16()?;
+        let mut palette = Vec::with_capacity(palette_len as usize);
+        for _ in 0..palette_len {
+            let kind = input.block_type()?;
+            let state = BlockState::from_bits(input.u8()?);
+            palette.push(Block { kind, state });
+        }
+
+        let bits = input.u8()? as u32;
+        let words = input.u32()?;
+        let data = (0..words).map(|_| input.u64()).collect::<anyhow::Result<_>>()?;
+
+        let storage = BlockStorage::from_raw(palette, bits, data)
+            .with_context(|| format!("Section {} is corrupt", i))?;
+        sections.push(storage);
+    }
+
+    let mut block_entities = HashMap::new();
+    for _ in 0..input.u32()? {
+        let block_pos = (input.u8()? as usize, input.u16()? as usize, input.u8()? as usize);
+
+        let entity = match input.u8()? {
+            CONTAINER => {
+                let slots = (0..input.u16()?)
+                    .map(|_| match input.u8()? {
+                        0 => Ok(None),
+                        count => Ok(Some(ItemStack { count, block: input.block_type()? })),
+                    })
+                    .collect::<anyhow::Result<_>>()?;
+                BlockEntity::Container(Inventory::from_slots(slots))
+            },
+            kind => bail!("Unknown block entity kind {}", kind),
+        };
+        block_entities.insert(block_pos, entity);
+    }
+
+    Chunk::from_saved(pos, sections, block_entities)
+        .context("Wrong number of sections")
+}
+
+/// Appends little endian values to a buffer
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, v: u8) {
+        self.0.push(v);
+    }
+
+    fn u16(&mut self, v: u16) {
+        self.0.extend(v.to_le_bytes());
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.0.extend(v.to_le_bytes());
+    }
+
+    fn u64(&mut self, v: u64) {
+        self.0.extend(v.to_le_bytes());
+    }
+
+    fn block_type(&mut self, block: BlockType) {
+        let name = block.name().as_bytes();
+        self.u8(name.len() as u8);
+        self.0.extend(name);
+    }
+}
+
+/// Reads back what `Writer` wrote, erroring if it runs out
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> anyhow::Result<&[u8]> {
+        if self.0.len() < n {
+            bail!("Unexpected end of file");
+        }
+        let (out, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(out)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> anyhow::Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> anyhow::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    /// Blocks no longer in the registry come back as air
+    fn block_type(&mut self) -> anyhow::Result<BlockType> {
+        let len = self.u8()? as usize;
+        let name = std::str::from_utf8(self.take(len)?)?;
+        Ok(BlockType::from_name(name).unwrap_or_else(|| {
+            log::warn!("Saved block \"{}\" isn't in the block registry, loading it as air", name);
+            BlockType::AIR
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{settings::{BLOCK_REGISTRY, SECTION_HEIGHT}, world::{block::load_registry, generation::Generator}};
+    use super::*;
+
+    #[test]
+    fn chunks_round_trip() {
+        load_registry(BLOCK_REGISTRY).unwrap();
+        let chest = BlockType::from_name("chest").unwrap();
+        let stone = BlockType::from_name("stone").unwrap();
+
+        let mut chunk = Chunk::new((16, -32), &Generator::new(1), &mut HashMap::new()).unwrap();
+        chunk.edit(3, 200, 4, Block::new(chest));
+        let Some(BlockEntity::Container(inventory)) = chunk.block_entity_mut(3, 200, 4) else {
+            panic!("Chest has no inventory");
+        };
+        inventory.insert_one(5, stone);
+
+        let loaded = read_chunk((16, -32), &write_chunk(&chunk)).unwrap();
+        for y in (0..SECTIONS * SECTION_HEIGHT).step_by(7) {
+            for (x, z) in [(0, 0), (3, 4), (15, 9)] {
+                assert_eq!(loaded.get(x, y, z), chunk.get(x, y, z));
+            }
+        }
+        assert_eq!(loaded.block_entities, chunk.block_entities);
+        assert!(read_chunk((16, -32), &write_chunk(&chunk)[..100]).is_err());
+    }
+
+    #[test]
+    fn saved_chests_keep_their_items() {
+        load_registry(BLOCK_REGISTRY).unwrap();
+        let chest = BlockType::from_name("chest").unwrap();
+        let stone = BlockType::from_name("stone").unwrap();
+
+        let mut chunk = Chunk::new((0, 0), &Generator::new(1), &mut HashMap::new()).unwrap();
+        chunk.edit(7, 150, 7, Block::new(chest));
+        let Some(BlockEntity::Container(inventory)) = chunk.block_entity_mut(7, 150, 7) else {
+            panic!("Chest has no inventory");
+        };
+        inventory.insert_one(0, stone);
+
+        let mut loaded = read_chunk((0, 0), &write_chunk(&chunk)).unwrap();
+        assert!(loaded.holds_items(7, 150, 7));
+        assert!(!loaded.edit(7, 150, 7, Block::AIR));
+        assert_eq!(loaded.get(7, 150, 7).kind, chest);
+
+        let Some(BlockEntity::Container(inventory)) = loaded.block_entity_mut(7, 150, 7) else {
+            panic!("Chest lost its inventory");
+        };
+        assert_eq!(inventory.take_one(0), Some(stone));
+        assert!(loaded.edit(7, 150, 7, Block::AIR));
+        assert!(loaded.block_entity_mut(7, 150, 7).is_none());
+    }
+}
@@ -0,0 +1,326 @@
+use std::borrow::Cow;
+use crate::{physics::collision::{FULL_BOX, LocalBox}, world::{block::BlockSide, state::{Block, StateProperty}}};
+
+/// The shape of a block's model
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Shape {
+    /// A full block
+    Cube,
+    /// The bottom half of a block
+    Slab,
+    /// A slab with a half-height step on top, on the side opposite its facing
+    Stairs,
+    /// A post that connects to neighbouring fences and full blocks
+    Fence,
+    /// Two crossed, see-through sprites, for plants
+    Cross,
+    /// A thin panel across the back of the block when closed, which swings
+    /// round to the side when open
+    Door,
+    /// A thin panel across the bottom of the block when closed, which swings
+    /// up against the back when open
+    Trapdoor,
+}
+
+/// What a block's model depends on besides its shape
+#[derive(Copy, Clone, Debug)]
+pub struct ModelContext {
+    /// The side the block faces. Stairs go up away from it.
+    pub facing: BlockSide,
+    /// Whether a fence connects on each side in `BlockSide::HORIZONTAL`
+    pub connections: [bool; 4],
+    /// Whether doors and trapdoors are open
+    pub open: bool,
+}
+
+impl Default for ModelContext {
+    fn default() -> Self {
+        Self {
+            facing: BlockSide::Front,
+            connections: [false; 4],
+            open: false,
+        }
+    }
+}
+
+impl ModelContext {
+    /// Works out the context for the given block from its state and its
+    /// neighbours, which `neighbour` looks up by side (None if unknown)
+    pub fn new(block: Block, neighbour: impl Fn(BlockSide) -> Option<Block>) -> Self {
+        let mut out = Self::default();
+        if block.kind.has_state(StateProperty::Facing) {
+            out.facing = block.state.facing();
+        }
+        out.open = block.state.is_open();
+        if block.kind.shape() == Shape::Fence {
+            out.connections = BlockSide::HORIZONTAL
+                .map(|side| neighbour(side).is_some_and(connects_to_fence));
+        }
+        out
+    }
+}
+
+/// Whether fences connect to the given block
+fn connects_to_fence(block: Block) -> bool {
+    block.kind.shape() == Shape::Fence || block.kind.occludes()
+}
+
+/// Fence posts, in the middle of the block
+const POST_MIN: f32 = 6.0 / 16.0;
+const POST_MAX: f32 = 10.0 / 16.0;
+/// Fence rails, thinner than the post
+const RAIL_MIN: f32 = 7.0 / 16.0;
+const RAIL_MAX: f32 = 9.0 / 16.0;
+const RAILS_Y: [(f32, f32); 2] = [(6.0 / 16.0, 9.0 / 16.0), (12.0 / 16.0, 15.0 / 16.0)];
+/// Fences are too tall to jump over
+const FENCE_COLLISION_HEIGHT: f32 = 1.5;
+/// How far cross sprites' hit boxes are inset from the sides of the block
+const CROSS_INSET: f32 = 0.2;
+const CROSS_HEIGHT: f32 = 0.8;
+/// How thick door and trapdoor panels are
+const PANEL_THICKNESS: f32 = 3.0 / 16.0;
+
+impl Shape {
+    /// The shape with the given name in data files
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "cube" => Some(Self::Cube),
+            "slab" => Some(Self::Slab),
+            "stairs" => Some(Self::Stairs),
+            "fence" => Some(Self::Fence),
+            "cross" => Some(Self::Cross),
+            "door" => Some(Self::Door),
+            "trapdoor" => Some(Self::Trapdoor),
+            _ => None,
+        }
+    }
+
+    /// The boxes drawn for the shape. Empty for cross sprites, which aren't
+    /// made of boxes.
+    pub fn model_boxes(&self, ctx: ModelContext) -> Cow<'static, [LocalBox]> {
+        match self {
+            Self::Fence => {
+                let mut boxes = vec![post_box(1.0)];
+                for (side, connected) in BlockSide::HORIZONTAL.into_iter().zip(ctx.connections) {
+                    if connected {
+                        for (bottom, top) in RAILS_Y {
+                            boxes.push(arm_box(side, RAIL_MIN, RAIL_MAX, bottom, top));
+                        }
+                    }
+                }
+                Cow::Owned(boxes)
+            },
+            Self::Cross => Cow::Borrowed(&[]),
+            _ => self.collision_boxes(ctx),
+        }
+    }
+
+    /// The boxes entities collide with, if the block is solid
+    pub fn collision_boxes(&self, ctx: ModelContext) -> Cow<'static, [LocalBox]> {
+        match self {
+            Self::Cube => Cow::Borrowed(&[FULL_BOX]),
+            Self::Slab => Cow::Borrowed(&[([0.0; 3], [1.0, 0.5, 1.0])]),
+            Self::Stairs => Cow::Owned(vec![
+                ([0.0; 3], [1.0, 0.5, 1.0]),
+                step_box(ctx.facing),
+            ]),
+            Self::Fence => Cow::Owned(fence_boxes(ctx, 0.0, FENCE_COLLISION_HEIGHT)),
+            Self::Cross => Cow::Borrowed(&[]),
+            Self::Door => {
+                let side = if ctx.open {
+                    ctx.facing.turn_y(1)
+                } else {
+                    ctx.facing.opposite()
+                };
+                Cow::Owned(vec![panel_box(side)])
+            },
+            Self::Trapdoor if ctx.open => Cow::Owned(vec![panel_box(ctx.facing.opposite())]),
+            Self::Trapdoor => Cow::Owned(vec![panel_box(BlockSide::Bottom)]),
+        }
+    }
+
+    /// The boxes the player targets the block by
+    pub fn hit_boxes(&self, ctx: ModelContext) -> Cow<'static, [LocalBox]> {
+        match self {
+            Self::Fence => Cow::Owned(fence_boxes(ctx, RAILS_Y[0].0, RAILS_Y[1].1)),
+            Self::Cross => Cow::Borrowed(&[(
+                [CROSS_INSET, 0.0, CROSS_INSET],
+                [1.0 - CROSS_INSET, CROSS_HEIGHT, 1.0 - CROSS_INSET],
+            )]),
+            _ => self.collision_boxes(ctx),
+        }
+    }
+}
+
+/// The top half step of stairs facing the given way
+fn step_box(facing: BlockSide) -> LocalBox {
+    match facing {
+        BlockSide::Back => ([0.0, 0.5, 0.0], [1.0, 1.0, 0.5]),
+        BlockSide::Left => ([0.5, 0.5, 0.0], [1.0, 1.0, 1.0]),
+        BlockSide::Right => ([0.0, 0.5, 0.0], [0.5, 1.0, 1.0]),
+        // Front, and stairs can't face up or down
+        _ => ([0.0, 0.5, 0.5], [1.0, 1.0, 1.0]),
+    }
+}
+
+/// A door panel lying against the given side of the block
+fn panel_box(side: BlockSide) -> LocalBox {
+    const T: f32 = PANEL_THICKNESS;
+    match side {
+        BlockSide::Front => ([0.0, 0.0, 0.0], [1.0, 1.0, T]),
+        BlockSide::Back => ([0.0, 0.0, 1.0 - T], [1.0, 1.0, 1.0]),
+        BlockSide::Left => ([0.0, 0.0, 0.0], [T, 1.0, 1.0]),
+        BlockSide::Right => ([1.0 - T, 0.0, 0.0], [1.0, 1.0, 1.0]),
+        BlockSide::Bottom => ([0.0, 0.0, 0.0], [1.0, T, 1.0]),
+        BlockSide::Top => ([0.0, 1.0 - T, 0.0], [1.0, 1.0, 1.0]),
+    }
+}
+
+fn post_box(height: f32) -> LocalBox {
+    ([POST_MIN, 0.0, POST_MIN], [POST_MAX, height, POST_MAX])
+}
+
+/// A box reaching from the fence post out to the given side of the block,
+/// `min` to `max` wide and `bottom` to `top` tall
+fn arm_box(side: BlockSide, min: f32, max: f32, bottom: f32, top: f32) -> LocalBox {
+    match side {
+        BlockSide::Front => ([min, bottom, 0.0], [max, top, POST_MIN]),
+        BlockSide::Back => ([min, bottom, POST_MAX], [max, top, 1.0]),
+        BlockSide::Left => ([0.0, bottom, min], [POST_MIN, top, max]),
+        // Right, and fences don't connect up or down
+        _ => ([POST_MAX, bottom, min], [1.0, top, max]),
+    }
+}
+
+/// The post, plus a post-wide arm towards each connection, with the arms
+/// spanning `bottom` to `top`
+fn fence_boxes(ctx: ModelContext, bottom: f32, top: f32) -> Vec<LocalBox> {
+    let mut boxes = vec![post_box(top)];
+    for (side, connected) in BlockSide::HORIZONTAL.into_iter().zip(ctx.connections) {
+        if connected {
+            boxes.push(arm_box(side, POST_MIN, POST_MAX, bottom, top));
+        }
+    }
+    boxes
+}
+
+/// The corners of the given side of a box, as (block-local position, texture
+/// coordinates), in the order bottom left, top left, bottom right, top right
+/// when looking at the side. Texture coordinates follow the box, so parts of
+/// blocks show the matching part of their texture.
+pub fn box_face(side: BlockSide, (min, max): LocalBox) -> [([f32; 3], [f32; 2]); 4] {
+    let [x0, y0, z0] = min;
+    let [x1, y1, z1] = max;
+
+    let corners = match side {
+        BlockSide::Front => [[x0, y0, z0], [x0, y1, z0], [x1, y0, z0], [x1, y1, z0]],
+        BlockSide::Back => [[x1, y0, z1], [x1, y1, z1], [x0, y0, z1], [x0, y1, z1]],
+        BlockSide::Top => [[x0, y1, z0], [x0, y1, z1], [x1, y1, z0], [x1, y1, z1]],
+        BlockSide::Bottom => [[x0, y0, z1], [x0, y0, z0], [x1, y0, z1], [x1, y0, z0]],
+        BlockSide::Left => [[x0, y0, z1], [x0, y1, z1], [x0, y0, z0], [x0, y1, z0]],
+        BlockSide::Right => [[x1, y0, z0], [x1, y1, z0], [x1, y0, z1], [x1, y1, z1]],
+    };
+
+    corners.map(|[x, y, z]| {
+        let uv = match side {
+            BlockSide::Front => [1.0 - x, 1.0 - y],
+            BlockSide::Back => [x, 1.0 - y],
+            BlockSide::Top => [1.0 - x, 1.0 - z],
+            BlockSide::Bottom => [1.0 - x, z],
+            BlockSide::Left => [z, 1.0 - y],
+            BlockSide::Right => [1.0 - z, 1.0 - y],
+        };
+        ([x, y, z], uv)
+    })
+}
+
+/// Whether the given side of a box lies on the outside of its block, where a
+/// neighbouring block could cover it
+pub fn on_block_edge(side: BlockSide, (min, max): LocalBox) -> bool {
+    match side {
+        BlockSide::Front => min[2] <= 0.0,
+        BlockSide::Back => max[2] >= 1.0,
+        BlockSide::Left => min[0] <= 0.0,
+        BlockSide::Right => max[0] >= 1.0,
+        BlockSide::Bottom => min[1] <= 0.0,
+        BlockSide::Top => max[1] >= 1.0,
+    }
+}
+
+/// The two diagonal quads of a cross sprite, as corners like `box_face`
+pub fn cross_quads() -> [[([f32; 3], [f32; 2]); 4]; 2] {
+    // Pulled in a little, so the corners don't poke into neighbours
+    const A: f32 = 0.15;
+    const B: f32 = 1.0 - A;
+
+    [[(A, A), (B, B)], [(A, B), (B, A)]].map(|[(x0, z0), (x1, z1)]| [
+        ([x0, 0.0, z0], [1.0, 1.0]),
+        ([x0, 1.0, z0], [1.0, 0.0]),
+        ([x1, 0.0, z1], [0.0, 1.0]),
+        ([x1, 1.0, z1], [0.0, 0.0]),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_box_faces_match_cube_layout() {
+        // Same corners and texture coordinates cubes have always had
+        let front = box_face(BlockSide::Front, FULL_BOX);
+        assert_eq!(front, [
+            ([0.0, 0.0, 0.0], [1.0, 1.0]),
+            ([0.0, 1.0, 0.0], [1.0, 0.0]),
+            ([1.0, 0.0, 0.0], [0.0, 1.0]),
+            ([1.0, 1.0, 0.0], [0.0, 0.0]),
+        ]);
+
+        let top = box_face(BlockSide::Top, FULL_BOX);
+        assert_eq!(top[0], ([0.0, 1.0, 0.0], [1.0, 1.0]));
+        assert_eq!(top[3], ([1.0, 1.0, 1.0], [0.0, 0.0]));
+    }
+
+    #[test]
+    fn fence_arms_follow_connections() {
+        let ctx = ModelContext {
+            connections: [true, false, false, true],
+            ..Default::default()
+        };
+
+        let boxes = Shape::Fence.collision_boxes(ctx);
+        assert_eq!(boxes.len(), 3);
+        assert!(boxes.iter().all(|b| b.1[1] == FENCE_COLLISION_HEIGHT));
+        // Reaches the front and right edges, but not the others
+        assert!(boxes.iter().any(|b| on_block_edge(BlockSide::Front, *b)));
+        assert!(boxes.iter().any(|b| on_block_edge(BlockSide::Right, *b)));
+        assert!(!boxes.iter().any(|b| on_block_edge(BlockSide::Back, *b)));
+        assert!(!boxes.iter().any(|b| on_block_edge(BlockSide::Left, *b)));
+    }
+
+    #[test]
+    fn stairs_step_is_opposite_facing() {
+        for facing in BlockSide::HORIZONTAL {
+            let ctx = ModelContext { facing, ..Default::default() };
+            let step = Shape::Stairs.collision_boxes(ctx)[1];
+            assert!(on_block_edge(facing.opposite(), step));
+            assert!(!on_block_edge(facing, step));
+        }
+    }
+
+    #[test]
+    fn doors_swing_open_to_the_side() {
+        let closed = ModelContext { facing: BlockSide::Front, ..Default::default() };
+        let open = ModelContext { open: true, ..closed };
+
+        let door = Shape::Door.collision_boxes(closed)[0];
+        assert!(on_block_edge(BlockSide::Back, door) && !on_block_edge(BlockSide::Front, door));
+        let door = Shape::Door.collision_boxes(open)[0];
+        assert!(on_block_edge(BlockSide::Left, door) && !on_block_edge(BlockSide::Right, door));
+
+        let trapdoor = Shape::Trapdoor.collision_boxes(closed)[0];
+        assert!(trapdoor.1[1] <= PANEL_THICKNESS);
+        let trapdoor = Shape::Trapdoor.collision_boxes(open)[0];
+        assert_eq!(trapdoor.1[1], 1.0);
+    }
+}
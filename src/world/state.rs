@@ -0,0 +1,281 @@
+use cgmath::Vector3;
+use crate::{vectors::Dimension, world::block::{BlockSide, BlockType}};
+
+/// A property that blocks can have in their state, if the registry says so
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StateProperty {
+    /// Which horizontal side the block's front is on
+    Facing,
+    /// Which axis the block runs along, like logs
+    Axis,
+    /// Whether the block is open, like doors
+    Open,
+    /// Which half of a two block tall block this is, like doors
+    Half,
+    /// Whether there's water in the block too
+    Waterlogged,
+}
+
+impl StateProperty {
+    /// The property with the given name in data files
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "facing" => Some(Self::Facing),
+            "axis" => Some(Self::Axis),
+            "open" => Some(Self::Open),
+            "half" => Some(Self::Half),
+            "waterlogged" => Some(Self::Waterlogged),
+            _ => None,
+        }
+    }
+}
+
+/// Per-block properties, packed into a byte. The default state faces front,
+/// runs along Y, is closed, is the lower half and isn't waterlogged.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BlockState(u8);
+
+const FACING_MASK: u8 = 0b11;
+const AXIS_SHIFT: u8 = 2;
+const AXIS_MASK: u8 = 0b11 << AXIS_SHIFT;
+const OPEN_BIT: u8 = 1 << 4;
+const UPPER_BIT: u8 = 1 << 5;
+const WATERLOGGED_BIT: u8 = 1 << 6;
+
+impl BlockState {
+    /// The state packed into a byte, for saving
+    pub fn to_bits(self) -> u8 {
+        self.0
+    }
+
+    /// The state saved with `to_bits`
+    pub fn from_bits(bits: u8) -> Self {
+        Self(bits)
+    }
+
+    /// One of `BlockSide::HORIZONTAL`
+    pub fn facing(&self) -> BlockSide {
+        BlockSide::HORIZONTAL[(self.0 & FACING_MASK) as usize]
+    }
+
+    /// Facing up or down isn't supported, and leaves the facing as it was
+    pub fn with_facing(self, side: BlockSide) -> Self {
+        match BlockSide::HORIZONTAL.iter().position(|s| *s == side) {
+            Some(i) => Self((self.0 & !FACING_MASK) | i as u8),
+            None => self,
+        }
+    }
+
+    pub fn axis(&self) -> Dimension {
+        match (self.0 & AXIS_MASK) >> AXIS_SHIFT {
+            1 => Dimension::X,
+            2 => Dimension::Z,
+            _ => Dimension::Y,
+        }
+    }
+
+    pub fn with_axis(self, axis: Dimension) -> Self {
+        let bits = match axis {
+            Dimension::Y => 0,
+            Dimension::X => 1,
+            Dimension::Z => 2,
+        };
+        Self((self.0 & !AXIS_MASK) | bits << AXIS_SHIFT)
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.0 & OPEN_BIT != 0
+    }
+
+    pub fn with_open(self, open: bool) -> Self {
+        Self(if open { self.0 | OPEN_BIT } else { self.0 & !OPEN_BIT })
+    }
+
+    /// Whether this is the upper half of a two block tall block
+    pub fn is_upper(&self) -> bool {
+        self.0 & UPPER_BIT != 0
+    }
+
+    pub fn with_upper(self, upper: bool) -> Self {
+        Self(if upper { self.0 | UPPER_BIT } else { self.0 & !UPPER_BIT })
+    }
+
+    pub fn is_waterlogged(&self) -> bool {
+        self.0 & WATERLOGGED_BIT != 0
+    }
+
+    pub fn with_waterlogged(self, waterlogged: bool) -> Self {
+        Self(if waterlogged { self.0 | WATERLOGGED_BIT } else { self.0 & !WATERLOGGED_BIT })
+    }
+}
+
+/// A block in the world: its type, plus its state
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Block {
+    pub kind: BlockType,
+    pub state: BlockState,
+}
+
+impl From<BlockType> for Block {
+    fn from(kind: BlockType) -> Self {
+        Self::new(kind)
+    }
+}
+
+impl Block {
+    pub const AIR: Block = Block::new(BlockType::AIR);
+
+    /// A block of the given type in its default state
+    pub const fn new(kind: BlockType) -> Self {
+        Self {
+            kind,
+            state: BlockState(0),
+        }
+    }
+
+    /// Whether there's fluid in the block, either because it is one or it's
+    /// waterlogged
+    pub fn is_fluid(&self) -> bool {
+        self.kind.is_fluid() || self.state.is_waterlogged()
+    }
+
+    /// The block of the given type as placed against the `clicked` side of
+    /// another block, by a player looking along `look`, into a space that
+    /// held `replacing`
+    pub fn placed(kind: BlockType, clicked: BlockSide, look: Vector3<f32>, replacing: Block) -> Self {
+        let mut state = BlockState::default();
+
+        if kind.has_state(StateProperty::Facing) {
+            // Face back towards the player
+            let toward_player = if look.x.abs() > look.z.abs() {
+                if look.x > 0.0 { BlockSide::Left } else { BlockSide::Right }
+            } else if look.z > 0.0 {
+                BlockSide::Front
+            } else {
+                BlockSide::Back
+            };
+            state = state.with_facing(toward_player);
+        }
+
+        if kind.has_state(StateProperty::Axis) {
+            state = state.with_axis(match clicked {
+                BlockSide::Left | BlockSide::Right => Dimension::X,
+                BlockSide::Front | BlockSide::Back => Dimension::Z,
+                BlockSide::Top | BlockSide::Bottom => Dimension::Y,
+            });
+        }
+
+        if kind.has_state(StateProperty::Waterlogged) {
+            state = state.with_waterlogged(replacing.is_fluid());
+        }
+
+        Self { kind, state }
+    }
+
+    /// The layer of the block texture array drawn on the given side of the
+    /// block, along with whether its texture coordinates need turning a
+    /// quarter turn, so blocks that face a way or lie along an axis have
+    /// their textures follow. Block faces are looked up in `textures`.
+    pub fn texture(
+        &self,
+        side: BlockSide,
+        textures: impl Fn(BlockType, BlockSide) -> Option<u32>,
+    ) -> Option<(u32, bool)> {
+        let (model_side, turned) = self.model_side(side);
+        Some((textures(self.kind, model_side)?, turned))
+    }
+
+    /// The side of the unturned block that ends up on the given side, and
+    /// whether it got turned a quarter turn to get there
+    fn model_side(&self, side: BlockSide) -> (BlockSide, bool) {
+        if self.kind.has_state(StateProperty::Axis) {
+            return match (self.state.axis(), side) {
+                (Dimension::Y, _) => (side, false),
+                // The ends of the block point along the axis
+                (Dimension::X, BlockSide::Left) => (BlockSide::Bottom, false),
+                (Dimension::X, BlockSide::Right) => (BlockSide::Top, false),
+                (Dimension::Z, BlockSide::Front) => (BlockSide::Bottom, false),
+                (Dimension::Z, BlockSide::Back) => (BlockSide::Top, false),
+                // The rest are sides, with their grain running along the axis
+                (Dimension::X, _) => (BlockSide::Front, true),
+                (Dimension::Z, BlockSide::Top | BlockSide::Bottom) => (BlockSide::Front, false),
+                (Dimension::Z, _) => (BlockSide::Front, true),
+            };
+        }
+
+        if self.kind.has_state(StateProperty::Facing) {
+            // Undo the turn from facing front to facing the way it does
+            let turns = facing_turns(self.state.facing());
+            return (side.turn_y((4 - turns) % 4), false);
+        }
+
+        (side, false)
+    }
+}
+
+/// How many quarter turns (as in `BlockSide::turn_y`) it takes to get from
+/// facing front to facing the given side
+pub fn facing_turns(facing: BlockSide) -> u8 {
+    let mut turns = 0;
+    let mut side = BlockSide::Front;
+    while side != facing && turns < 4 {
+        side = side.turn_y(1);
+        turns += 1;
+    }
+    turns % 4
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{settings::BLOCK_REGISTRY, world::block::load_registry};
+    use super::*;
+
+    #[test]
+    fn state_properties_pack_independently() {
+        let state = BlockState::default()
+            .with_facing(BlockSide::Left)
+            .with_axis(Dimension::Z)
+            .with_open(true)
+            .with_waterlogged(true);
+
+        assert_eq!(state.facing(), BlockSide::Left);
+        assert_eq!(state.axis(), Dimension::Z);
+        assert!(state.is_open() && !state.is_upper() && state.is_waterlogged());
+
+        let state = state.with_open(false).with_upper(true).with_facing(BlockSide::Top);
+        assert_eq!(state.facing(), BlockSide::Left);
+        assert_eq!(state.axis(), Dimension::Z);
+        assert!(!state.is_open() && state.is_upper() && state.is_waterlogged());
+    }
+
+    #[test]
+    fn facing_turns_round_trip() {
+        for side in BlockSide::HORIZONTAL {
+            assert_eq!(BlockSide::Front.turn_y(facing_turns(side)), side);
+        }
+    }
+
+    #[test]
+    fn placed_blocks_turn_their_textures() {
+        load_registry(BLOCK_REGISTRY).unwrap();
+
+        // Logs placed against the side of a block lie along that axis
+        let log = BlockType::from_name("log").unwrap();
+        let sideways = Block::placed(log, BlockSide::Right, Vector3::unit_x(), Block::AIR);
+        assert_eq!(sideways.state.axis(), Dimension::X);
+        assert_eq!(sideways.model_side(BlockSide::Left), (BlockSide::Bottom, false));
+        assert_eq!(sideways.model_side(BlockSide::Top), (BlockSide::Front, true));
+
+        // Stairs face the player, and take on water they replace
+        let stairs = BlockType::from_name("stone_stairs").unwrap();
+        let water = Block::new(BlockType::from_name("water").unwrap());
+        let placed = Block::placed(stairs, BlockSide::Top, Vector3::unit_z(), water);
+        assert_eq!(placed.state.facing(), BlockSide::Front);
+        assert!(placed.state.is_waterlogged() && placed.is_fluid());
+
+        let placed = Block::placed(stairs, BlockSide::Top, -Vector3::unit_x(), Block::AIR);
+        assert_eq!(placed.state.facing(), BlockSide::Right);
+        assert_eq!(placed.model_side(BlockSide::Right), (BlockSide::Front, false));
+        assert!(!placed.is_fluid());
+    }
+}
@@ -0,0 +1,296 @@
+use crate::{settings::{CHUNK_SIZE, SECTION_HEIGHT}, world::state::Block};
+
+/// Compact storage for a fixed number of blocks.
+///
+/// Each distinct block (type and state) gets an entry in a palette, and blocks are
+/// stored as bit-packed indexes into it, using only as many bits as the
+/// palette needs. Storage that is all one block doesn't store indexes at
+/// all.
+#[derive(Debug, Clone)]
+pub enum BlockStorage {
+    /// Every block is the same
+    Uniform(Block),
+    Paletted {
+        palette: Vec<Block>,
+        /// Bits per packed index
+        bits: u32,
+        /// Packed indexes. Indexes never straddle two words.
+        data: Vec<u64>,
+    },
+}
+
+impl BlockStorage {
+    /// The number of blocks stored
+    pub const LEN: usize = CHUNK_SIZE * SECTION_HEIGHT * CHUNK_SIZE;
+
+    pub fn new(block: Block) -> Self {
+        Self::Uniform(block)
+    }
+
+    /// Whether every block is the given one
+    pub fn is_uniform(&self, block: Block) -> bool {
+        matches!(self, Self::Uniform(b) if *b == block)
+    }
+
+    pub fn get(&self, i: usize) -> Block {
+        match self {
+            Self::Uniform(b) => *b,
+            Self::Paletted { palette, bits, data } => {
+                palette[read_index(data, *bits, i)]
+            },
+        }
+    }
+
+    pub fn set(&mut self, i: usize, block: Block) {
+        if let Self::Uniform(b) = self {
+            if *b == block {
+                return;
+            }
+
+            *self = Self::Paletted {
+                palette: vec![*b],
+                bits: 1,
+                data: vec![0; words_needed(1)],
+            };
+        }
+
+        let Self::Paletted { palette, bits, data } = self else {
+            unreachable!();
+        };
+
+        let palette_i = match palette.iter().position(|b| *b == block) {
+            Some(p) => p,
+            None => {
+                palette.push(block);
+                if palette.len() > 1 << *bits {
+                    *data = repack(data, *bits, *bits + 1);
+                    *bits += 1;
+                }
+                palette.len() - 1
+            },
+        };
+
+        write_index(data, *bits, i, palette_i);
+    }
+
+    /// Drops palette entries that are no longer used, going back to uniform
+    /// storage if only one is left
+    pub fn compact(&mut self) {
+        let Self::Paletted { palette, bits, data } = &*self else {
+            return;
+        };
+
+        let mut used = vec![false; palette.len()];
+        for i in 0..Self::LEN {
+            used[read_index(data, *bits, i)] = true;
+        }
+
+        if used.iter().filter(|u| **u).count() == palette.len() {
+            return;
+        }
+
+        let blocks: Vec<Block> = (0..Self::LEN).map(|i| self.get(i)).collect();
+        *self = Self::from_blocks(&blocks);
+    }
+
+    /// Builds storage holding the given blocks, which must be `LEN` long
+    fn from_blocks(blocks: &[Block]) -> Self {
+        let mut out = Self::new(blocks[0]);
+        for (i, block) in blocks.iter().enumerate() {
+            out.set(i, *block);
+        }
+        out
+    }
+
+    /// The palette, bits per packed index and packed indexes, for saving.
+    /// Uniform storage has a one block palette, 0 bits and no indexes.
+    pub fn raw(&self) -> (&[Block], u32, &[u64]) {
+        match self {
+            Self::Uniform(b) => (std::slice::from_ref(b), 0, &[]),
+            Self::Paletted { palette, bits, data } => (palette, *bits, data),
+        }
+    }
+
+    /// Rebuilds storage from the parts `raw` gave. None if they don't fit
+    /// together.
+    pub fn from_raw(palette: Vec<Block>, bits: u32, data: Vec<u64>) -> Option<Self> {
+        if bits == 0 {
+            return (palette.len() == 1 && data.is_empty()).then(|| Self::Uniform(palette[0]));
+        }
+
+        if bits > 16 || palette.len() > 1 << bits || data.len() != words_needed(bits) {
+            return None;
+        }
+        if (0..Self::LEN).any(|i| read_index(&data, bits, i) >= palette.len()) {
+            return None;
+        }
+
+        Some(Self::Paletted { palette, bits, data })
+    }
+
+    /// Approximate heap memory used, in bytes
+    pub fn heap_size(&self) -> usize {
+        match self {
+            Self::Uniform(_) => 0,
+            Self::Paletted { palette, data, .. } => {
+                palette.capacity() * size_of::<Block>()
+                    + data.capacity() * size_of::<u64>()
+            },
+        }
+    }
+}
+
+fn words_needed(bits: u32) -> usize {
+    let per_word = (64 / bits) as usize;
+    BlockStorage::LEN.div_ceil(per_word)
+}
+
+fn read_index(data: &[u64], bits: u32, i: usize) -> usize {
+    let per_word = (64 / bits) as usize;
+    let shift = (i % per_word) as u32 * bits;
+    let mask = (1u64 << bits) - 1;
+    ((data[i / per_word] >> shift) & mask) as usize
+}
+
+fn write_index(data: &mut [u64], bits: u32, i: usize, value: usize) {
+    let per_word = (64 / bits) as usize;
+    let shift = (i % per_word) as u32 * bits;
+    let mask = (1u64 << bits) - 1;
+    let word = &mut data[i / per_word];
+    *word = (*word & !(mask << shift)) | ((value as u64 & mask) << shift);
+}
+
+fn repack(data: &[u64], old_bits: u32, new_bits: u32) -> Vec<u64> {
+    let mut out = vec![0; words_needed(new_bits)];
+    for i in 0..BlockStorage::LEN {
+        write_index(&mut out, new_bits, i, read_index(data, old_bits, i));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{hint::black_box, time::Instant};
+    use crate::world::block::BlockType;
+    use super::*;
+
+    // Storage doesn't care what ids mean, so these don't need the registry
+    const AIR: Block = Block::AIR;
+    const DIRT: Block = Block::new(BlockType(2));
+    const STONE: Block = Block::new(BlockType(5));
+    const TYPES: [Block; 8] = [
+        Block::new(BlockType(0)), Block::new(BlockType(1)),
+        Block::new(BlockType(2)), Block::new(BlockType(3)),
+        Block::new(BlockType(4)), Block::new(BlockType(5)),
+        Block::new(BlockType(6)), Block::new(BlockType(7)),
+    ];
+
+    /// A deterministic mix of block types
+    fn pattern(i: usize) -> Block {
+        TYPES[(i * 7 + i / 13) % TYPES.len()]
+    }
+
+    #[test]
+    fn round_trips_many_types() {
+        let mut storage = BlockStorage::new(AIR);
+        for i in 0..BlockStorage::LEN {
+            storage.set(i, pattern(i));
+        }
+
+        for i in 0..BlockStorage::LEN {
+            assert_eq!(storage.get(i), pattern(i), "block {i}");
+        }
+    }
+
+    #[test]
+    fn compacts_back_to_uniform() {
+        let mut storage = BlockStorage::new(AIR);
+        storage.set(10, STONE);
+        storage.set(20, DIRT);
+        assert!(!storage.is_uniform(AIR));
+
+        storage.set(10, AIR);
+        storage.set(20, AIR);
+        storage.compact();
+        assert!(storage.is_uniform(AIR));
+        assert_eq!(storage.heap_size(), 0);
+    }
+
+    #[test]
+    fn round_trips_through_raw() {
+        let mut storage = BlockStorage::new(AIR);
+        for i in 0..BlockStorage::LEN {
+            storage.set(i, pattern(i));
+        }
+
+        let (palette, bits, data) = storage.raw();
+        let loaded = BlockStorage::from_raw(palette.to_vec(), bits, data.to_vec()).unwrap();
+        for i in 0..BlockStorage::LEN {
+            assert_eq!(loaded.get(i), pattern(i), "block {i}");
+        }
+
+        // Indexes past the end of the palette don't load
+        assert!(BlockStorage::from_raw(palette[..2].to_vec(), bits, data.to_vec()).is_none());
+        assert!(BlockStorage::from_raw(vec![STONE], 0, vec![]).unwrap().is_uniform(STONE));
+    }
+
+    /// Compares against a plain dense array. Run with
+    /// `cargo test --release bench_storage -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_storage() {
+        const ROUNDS: usize = 200;
+
+        let mut dense = vec![AIR; BlockStorage::LEN];
+        let mut paletted = BlockStorage::new(AIR);
+        for (i, dense_block) in dense.iter_mut().enumerate() {
+            // Terrain-like: mostly stone, with a few other types mixed in
+            let block = if i % 17 == 0 { pattern(i) } else { STONE };
+            *dense_block = block;
+            paletted.set(i, block);
+        }
+
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            for i in 0..BlockStorage::LEN {
+                black_box(dense[black_box(i)]);
+            }
+        }
+        let dense_get = start.elapsed();
+
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            for i in 0..BlockStorage::LEN {
+                black_box(paletted.get(black_box(i)));
+            }
+        }
+        let paletted_get = start.elapsed();
+
+        let start = Instant::now();
+        for round in 0..ROUNDS {
+            for (i, block) in dense.iter_mut().enumerate() {
+                *block = pattern(i + round % 2);
+            }
+        }
+        let dense_set = start.elapsed();
+
+        let start = Instant::now();
+        for round in 0..ROUNDS {
+            for i in 0..BlockStorage::LEN {
+                paletted.set(i, pattern(i + round % 2));
+            }
+        }
+        let paletted_set = start.elapsed();
+
+        let lookups = (ROUNDS * BlockStorage::LEN) as f64;
+        let per_block = |d: std::time::Duration| d.as_nanos() as f64 / lookups;
+        println!("Per section memory:");
+        println!("  dense:    {} bytes", BlockStorage::LEN * size_of::<Block>());
+        println!("  paletted: {} bytes (terrain-like, 8 types)", paletted.heap_size());
+        println!("  uniform:  {} bytes", BlockStorage::new(AIR).heap_size());
+        println!("Per block get: dense {:.2}ns, paletted {:.2}ns",
+            per_block(dense_get), per_block(paletted_get));
+        println!("Per block set: dense {:.2}ns, paletted {:.2}ns",
+            per_block(dense_set), per_block(paletted_set));
+    }
+}
@@ -0,0 +1,98 @@
+use std::f32::consts::TAU;
+use cgmath::{InnerSpace, Vector3};
+use crate::settings::{DAY_LENGTH, PHYSICS_TICK_RATE, START_TIME};
+
+/// How far the sun's path leans away from straight overhead, so shadows
+/// never point straight along the ground's grid
+const SUN_TILT: f32 = 0.25;
+
+/// Below this much daylight, it counts as night
+const NIGHT_DAYLIGHT: f32 = 0.2;
+
+/// The time of day, which goes round once every `DAY_LENGTH` seconds
+#[derive(Copy, Clone, Debug)]
+pub struct WorldTime {
+    /// Physics ticks since the start of the first day
+    ticks: u64,
+}
+
+impl WorldTime {
+    pub fn new() -> Self {
+        let mut out = Self { ticks: 0 };
+        out.set_time_of_day(START_TIME);
+        out
+    }
+
+    /// The time after the given number of ticks, as saved
+    pub fn from_ticks(ticks: u64) -> Self {
+        Self { ticks }
+    }
+
+    /// Physics ticks since the start of the first day
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    fn ticks_per_day() -> u64 {
+        (DAY_LENGTH * PHYSICS_TICK_RATE) as u64
+    }
+
+    /// Moves time on by a physics tick
+    pub fn tick(&mut self) {
+        self.ticks += 1;
+    }
+
+    /// How far through the current day it is, from 0 to 1. 0 is sunrise,
+    /// 0.25 noon, 0.5 sunset and 0.75 midnight.
+    pub fn time_of_day(&self) -> f32 {
+        (self.ticks % Self::ticks_per_day()) as f32 / Self::ticks_per_day() as f32
+    }
+
+    /// Jumps forward to the given time of day (as in `time_of_day`)
+    pub fn set_time_of_day(&mut self, time: f32) {
+        let day_start = self.ticks - self.ticks % Self::ticks_per_day();
+        let into_day = (time.rem_euclid(1.0) * Self::ticks_per_day() as f32) as u64;
+        let mut ticks = day_start + into_day;
+        if ticks < self.ticks {
+            ticks += Self::ticks_per_day();
+        }
+        self.ticks = ticks;
+    }
+
+    /// The direction sunlight travels in
+    pub fn sun_direction(&self) -> Vector3<f32> {
+        let angle = self.time_of_day() * TAU;
+        -Vector3::new(angle.cos(), angle.sin(), SUN_TILT).normalize()
+    }
+
+    /// How light it is, from 0 at night to 1 during the day. Matches the sky.
+    pub fn daylight(&self) -> f32 {
+        (-self.sun_direction().y * 3.0 + 0.3).clamp(0.0, 1.0)
+    }
+
+    pub fn is_night(&self) -> bool {
+        self.daylight() < NIGHT_DAYLIGHT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn day_goes_round() {
+        let mut time = WorldTime::new();
+
+        time.set_time_of_day(0.25);
+        assert!((time.time_of_day() - 0.25).abs() < 1.0e-3);
+        assert!(time.sun_direction().y < -0.9 && !time.is_night());
+
+        time.set_time_of_day(0.75);
+        assert!(time.sun_direction().y > 0.9 && time.is_night());
+
+        // Setting an earlier time moves on to the next day
+        let before = time.ticks;
+        time.set_time_of_day(0.25);
+        assert!(time.ticks > before);
+    }
+}
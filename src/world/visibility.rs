@@ -0,0 +1,205 @@
+use std::collections::{HashSet, VecDeque};
+use crate::{settings::{CHUNK_SIZE, SECTION_HEIGHT}, world::{Coordinate, WorldPos, block::BlockSide, chunk::SECTIONS}};
+
+const CELLS: usize = CHUNK_SIZE * SECTION_HEIGHT * CHUNK_SIZE;
+
+/// Which faces of a chunk section can be seen from which others, through
+/// connected non-solid blocks inside of the section.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SectionVisibility {
+    /// Bit `a * 6 + b` is set if face `a` connects to face `b`
+    connections: u64,
+}
+
+fn side_index(side: BlockSide) -> usize {
+    BlockSide::ALL.iter().position(|s| *s == side).unwrap()
+}
+
+impl SectionVisibility {
+    /// A section that can be seen through in every direction (ie. all air)
+    pub const OPEN: Self = Self { connections: (1 << 36) - 1 };
+
+    /// Flood fills the section to find which faces connect. `is_open` gets
+    /// called with section-local (X, Y, Z) coordinates.
+    pub fn compute(is_open: impl Fn(usize, usize, usize) -> bool) -> Self {
+        let index = |x: usize, y: usize, z: usize| {
+            (y * CHUNK_SIZE + z) * CHUNK_SIZE + x
+        };
+
+        let mut out = Self { connections: 0 };
+        let mut visited = vec![false; CELLS];
+        let mut stack = vec![];
+
+        for y in 0..SECTION_HEIGHT {
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    if visited[index(x, y, z)] || !is_open(x, y, z) {
+                        continue;
+                    }
+
+                    // Fill this pocket of open blocks, noting which faces of
+                    // the section it touches
+                    let mut touched = vec![];
+                    visited[index(x, y, z)] = true;
+                    stack.push((x, y, z));
+
+                    while let Some((x, y, z)) = stack.pop() {
+                        for side in BlockSide::ALL {
+                            let (dx, dy, dz) = side.offset();
+                            let n_x = x as i32 + dx;
+                            let n_y = y as i32 + dy;
+                            let n_z = z as i32 + dz;
+
+                            if n_x < 0 || n_x >= CHUNK_SIZE as i32 ||
+                               n_y < 0 || n_y >= SECTION_HEIGHT as i32 ||
+                               n_z < 0 || n_z >= CHUNK_SIZE as i32 {
+
+                                if !touched.contains(&side) {
+                                    touched.push(side);
+                                }
+                                continue;
+                            }
+
+                            let (n_x, n_y, n_z) =
+                                (n_x as usize, n_y as usize, n_z as usize);
+                            if !visited[index(n_x, n_y, n_z)] &&
+                                is_open(n_x, n_y, n_z) {
+
+                                visited[index(n_x, n_y, n_z)] = true;
+                                stack.push((n_x, n_y, n_z));
+                            }
+                        }
+                    }
+
+                    for a in &touched {
+                        for b in &touched {
+                            out.connections |=
+                                1 << (side_index(*a) * 6 + side_index(*b));
+                        }
+                    }
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Whether something entering through face `from` can leave through face
+    /// `to`
+    pub fn connects(&self, from: BlockSide, to: BlockSide) -> bool {
+        self.connections & (1 << (side_index(from) * 6 + side_index(to))) != 0
+    }
+}
+
+/// Every section that can be seen from the given (chunk, section index) by
+/// looking through open space. Walks outwards from it, only passing through
+/// sections whose entry and exit faces connect, and never doubling back
+/// towards the start. `visibility` gives the visibility of a section, or
+/// None if its chunk isn't loaded.
+pub fn visible_sections(
+    start: (WorldPos, usize),
+    visibility: impl Fn(WorldPos, usize) -> Option<SectionVisibility>,
+) -> HashSet<(WorldPos, usize)> {
+    let mut visited = HashSet::new();
+    if visibility(start.0, start.1).is_none() {
+        return visited;
+    }
+
+    let mut queue = VecDeque::new();
+    visited.insert(start);
+    queue.push_back((start.0, start.1, None::<BlockSide>, 0u8));
+
+    while let Some((chunk_pos, section_i, entered, directions)) = queue.pop_front() {
+        let section = visibility(chunk_pos, section_i).unwrap();
+
+        for (i, side) in BlockSide::ALL.into_iter().enumerate() {
+            let opposite_i = side_index(side.opposite());
+            if directions & (1 << opposite_i) != 0 {
+                continue;
+            }
+
+            if let Some(from) = entered && !section.connects(from, side) {
+                continue;
+            }
+
+            let (dx, dy, dz) = side.offset();
+            let next_section = section_i as i32 + dy;
+            if next_section < 0 || next_section >= SECTIONS as i32 {
+                continue;
+            }
+
+            let next_chunk = (
+                chunk_pos.0 + dx * CHUNK_SIZE as Coordinate,
+                chunk_pos.1 + dz * CHUNK_SIZE as Coordinate,
+            );
+            let next = (next_chunk, next_section as usize);
+            if visibility(next.0, next.1).is_none() || !visited.insert(next) {
+                continue;
+            }
+
+            queue.push_back((
+                next.0, next.1, Some(side.opposite()), directions | (1 << i),
+            ));
+        }
+    }
+
+    visited
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_section_connects_every_face() {
+        let visibility = SectionVisibility::compute(|_, _, _| true);
+        assert_eq!(visibility, SectionVisibility::OPEN);
+        for from in BlockSide::ALL {
+            for to in BlockSide::ALL {
+                assert!(visibility.connects(from, to), "{:?} to {:?}", from, to);
+            }
+        }
+    }
+
+    #[test]
+    fn wall_splits_opposite_faces() {
+        // A solid wall across the middle of the section, facing X
+        let visibility = SectionVisibility::compute(|x, _, _| x != CHUNK_SIZE / 2);
+        assert!(!visibility.connects(BlockSide::Left, BlockSide::Right));
+        assert!(!visibility.connects(BlockSide::Right, BlockSide::Left));
+
+        // Either side of the wall still touches every other face
+        assert!(visibility.connects(BlockSide::Left, BlockSide::Top));
+        assert!(visibility.connects(BlockSide::Right, BlockSide::Front));
+        assert!(visibility.connects(BlockSide::Front, BlockSide::Back));
+    }
+
+    #[test]
+    fn sealed_section_blocks_the_walk() {
+        let sealed = SectionVisibility::compute(|_, _, _| false);
+        let size = CHUNK_SIZE as Coordinate;
+        // Three chunks in a row along X, with the middle one solid all the
+        // way up
+        let world = |middle: SectionVisibility| {
+            move |chunk: WorldPos, _| match chunk {
+                (0, 0) => Some(SectionVisibility::OPEN),
+                (x, 0) if x == size => Some(middle),
+                (x, 0) if x == size * 2 => Some(SectionVisibility::OPEN),
+                _ => None,
+            }
+        };
+        let start = ((0, 0), SECTIONS / 2);
+
+        let visible = visible_sections(start, world(SectionVisibility::OPEN));
+        assert!(visible.contains(&((size * 2, 0), SECTIONS / 2)));
+
+        let visible = visible_sections(start, world(sealed));
+        assert!(visible.contains(&((size, 0), SECTIONS / 2)), "the wall itself can be seen");
+        assert!(visible.iter().all(|(chunk, _)| chunk.0 != size * 2));
+    }
+
+    #[test]
+    fn unloaded_start_sees_nothing() {
+        assert!(visible_sections(((0, 0), 0), |_, _| None).is_empty());
+    }
+}